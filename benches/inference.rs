@@ -0,0 +1,44 @@
+#![allow(clippy::needless_return)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use vai::vaid::Workspace;
+
+/// Compares allocating and allocation-free inference on a network
+/// * name - The name of the network's shape
+/// * layers - The number of neurons in each layer
+fn compare_vaid(c: &mut Criterion, name: &str, layers: &[usize]) {
+    let ai = vai::VAID::new_deterministic(0, layers).create_variant(10.0);
+    let inputs = vec![0.5; layers[0]];
+    c.bench_function(&format!("VAID::process_slice {}", name), |b| {
+        b.iter(|| black_box(ai.process_slice(black_box(&inputs))));
+    });
+    let mut workspace = Workspace::for_network(&ai);
+    c.bench_function(&format!("VAID::process_with {}", name), |b| {
+        b.iter(|| black_box(ai.process_with(black_box(&inputs), &mut workspace)[0]));
+    });
+}
+
+fn vaid_inference(c: &mut Criterion) {
+    // Small networks, like the one in the delay example, are dominated by allocation
+    compare_vaid(c, "small", &[2, 4, 1]);
+    compare_vaid(c, "large", &[8, 32, 32, 4]);
+}
+
+fn vai_inference(c: &mut Criterion) {
+    let ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0).create_variant(10.0);
+    let inputs = [0.5; 2];
+    c.bench_function("VAI::process_slice", |b| {
+        b.iter(|| black_box(ai.process_slice(black_box(&inputs))));
+    });
+    let mut outputs = [0.; 1];
+    c.bench_function("VAI::process_into", |b| {
+        b.iter(|| {
+            ai.process_into(black_box(&inputs), &mut outputs);
+            black_box(outputs[0])
+        });
+    });
+}
+
+criterion_group!(benches, vaid_inference, vai_inference);
+criterion_main!(benches);
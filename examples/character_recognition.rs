@@ -13,7 +13,7 @@ use nalgebra as na;
 
 const IMAGE_SIZE: usize = 12;
 const PIXEL_COUNT: usize = IMAGE_SIZE * IMAGE_SIZE;
-const INPUTS: usize = PIXEL_COUNT + 1;
+const INPUTS: usize = PIXEL_COUNT;
 
 fn create_random_render(render_target: RenderTarget, font: &Font) -> usize {
     // 0..32, 0..32 camera
@@ -50,7 +50,6 @@ fn extract_pixels(texture: &Texture2D) -> na::SMatrix<f32, INPUTS, 1> {
     for i in 0..PIXEL_COUNT {
         result[i] = pixels[i * 4] as f32 / 256.;
     }
-    result[INPUTS - 1] = 1.0; // bias
     return result;
 }
 
@@ -2,21 +2,21 @@
 
 use std::f32::consts::PI;
 use std::io::BufRead;
-use std::iter::zip;
 
 use macroquad::prelude::{is_key_pressed, Conf, KeyCode, draw_circle, draw_rectangle, draw_text};
 use macroquad::prelude::{RED, WHITE, BLUE, BLACK};
 use macroquad::window::next_frame;
 use rayon::prelude::*;
 
-const HIDDEN: usize = 1;
-const LAYERS: usize = 0;
+use vai::vair::Cell;
 
-type AI = vai::VAI::<3, 1, HIDDEN, LAYERS>;
+const HIDDEN: usize = 2;
+
+type AI = vai::VAIR;
 
  struct State {
     ai: AI,
-    inputs: [f32; 3],
+    inputs: [f32; 1],
     target_timer: f32,
     lifetime: f32,
     score: f32
@@ -26,14 +26,15 @@ impl State {
     fn new(ai: AI) -> Self {
         Self {
             ai,
-            inputs: [0.,0.,1.],
+            inputs: [0.],
             target_timer: 0.,
             lifetime: 0.,
             score: 0.
         }
     }
     fn reset(&mut self) {
-        self.inputs = [0.,0.,1.];
+        self.ai.reset_state();
+        self.inputs = [0.];
         self.target_timer = 0.;
         self.lifetime = 0.;
         self.score = 0.;
@@ -43,7 +44,8 @@ impl State {
         self.target_timer += dt;
 
         let timer = self.target_timer;
-        if self.ai.process_slice(&self.inputs)[0] > 0. {
+        // The network's hidden state remembers when input 0 last triggered
+        if self.ai.step(&self.inputs)[0] > 0. {
             // The closer to the center, the better the score
             self.score += timer.sin().abs()/(0.1 + timer.cos().abs());
             self.target_timer = 0.;
@@ -51,9 +53,6 @@ impl State {
 
         // Let input 0 trigger when score would be low
         self.inputs[0] = if self.target_timer.sin().abs() < 0.25 { 1.0 } else { 0.0 };
-
-        // This will make input 1 tend towards input 0 at a rate of 1/e^x
-        self.inputs[1] += (self.inputs[0] - self.inputs[1]) * dt;
     }
 }
 
@@ -88,7 +87,7 @@ async fn main() {
     // box the states so they can be sorted quickly
     let mut simulations = Vec::<Box<State>>::new();
     for i in 0..200 {
-        simulations.push(Box::new(State::new(AI::new_deterministic(i))));
+        simulations.push(Box::new(State::new(AI::new_deterministic(i, 1, HIDDEN, 1, Cell::Elman))));
     }
     let mut frame_count: usize = 0;
     loop {
@@ -107,9 +106,13 @@ async fn main() {
             simulations.sort_by(|a, b| a.score.partial_cmp(&b.score).unwrap());
             let mid = simulations.len()/2;
             let (worst, best) = simulations.split_at_mut(mid);
-            for (good, bad) in zip(best, worst) {
-                bad.ai = good.ai.create_variant(0.1);
+            for (i, bad) in worst.iter_mut().enumerate() {
+                // breed each good simulation with the next best one
+                let other = best[(i + 1) % best.len()].ai.clone();
+                bad.ai = best[i].ai.crossover(&other).create_variant(0.1);
                 bad.reset();
+            }
+            for good in best {
                 good.reset();
             }
             generation += 1;
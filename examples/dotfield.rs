@@ -1,7 +1,6 @@
 #![allow(clippy::needless_return)]
 
 use std::io::BufRead;
-use std::sync::{Arc, Mutex};
 
 use macroquad::prelude::{is_key_pressed, Conf, KeyCode};
 use macroquad::prelude::{GRAY, GREEN, PURPLE, RED, WHITE, YELLOW};
@@ -9,7 +8,6 @@ use macroquad::{shapes::draw_circle, text::draw_text, window::next_frame};
 use nalgebra as na;
 use rand::rngs::StdRng;
 use rand::{self, Rng, SeedableRng};
-use rayon::prelude::*;
 
 fn relu(x: f32) -> f32 {
     return x.max(0.);
@@ -32,60 +30,54 @@ fn outside(x: f32, y: f32) -> f32 {
 fn test<const I: usize, const C: usize, const E: usize>(
     ai: &vai::VAI<I, 1, C, E>,
     random: &mut crate::rand::rngs::StdRng,
-    debug: impl Send + Fn(f32, f32, usize),
+    debug: impl Fn(f32, f32, usize),
 ) -> f32 {
     let tests = 1000;
-    let random_lock = Arc::new(Mutex::new(random));
-    let debug_lock = Arc::new(Mutex::new(debug));
-    let outer = Arc::new(Mutex::new(0.));
-    let miss_outer = Arc::new(Mutex::new(0.));
-    let inner = Arc::new(Mutex::new(0.));
-    let miss_inner = Arc::new(Mutex::new(0.));
-    (0..tests).into_par_iter().for_each(|_| {
-        let x: f32;
-        let y: f32;
-        {
-            let mut random = random_lock.lock().unwrap();
-            x = random.gen();
-            y = random.gen();
-        }
-        let mut input = na::SMatrix::<f32, I, 1>::zeros();
-        input[0] = 1.0;
-        input[1] = x;
-        input[2] = y;
+    let mut inputs = na::DMatrix::<f32>::zeros(I, tests);
+    for mut input in inputs.column_iter_mut() {
+        input[0] = random.gen();
+        input[1] = random.gen();
         // This line of code, along with an extra input node, unsurprisingly makes the neural
         // network run a lot better.
-        // input[3] = ((x*std::f32::consts::PI).sin() + (y*std::f32::consts::PI).sin()) * 0.5;
-        let out = ai.process(&input)[0];
+        // input[2] = ((input[0]*std::f32::consts::PI).sin() + (input[1]*std::f32::consts::PI).sin()) * 0.5;
+    }
+    // Evaluate every point at once
+    let outputs = ai.process_batch(&inputs);
+    let mut outer = 0.;
+    let mut miss_outer = 0.;
+    let mut inner = 0.;
+    let mut miss_inner = 0.;
+    for (input, out) in inputs.column_iter().zip(outputs.iter()) {
+        let (x, y) = (input[0], input[1]);
         let actual = outside(x, y);
         let path: usize;
         if actual > 0. {
-            *outer.lock().unwrap() += 1.;
-            if out > 0. {
+            outer += 1.;
+            if *out > 0. {
                 path = 2;
             } else {
                 path = 1;
-                *miss_outer.lock().unwrap() += 1.;
+                miss_outer += 1.;
             }
         } else {
-            *inner.lock().unwrap() += 1.;
-            if out > 0. {
+            inner += 1.;
+            if *out > 0. {
                 path = 3;
-                *miss_inner.lock().unwrap() += 1.;
+                miss_inner += 1.;
             } else {
                 path = 4;
             }
         }
-        debug_lock.lock().unwrap()(x, y, path);
-    });
+        debug(x, y, path);
+    }
     let mut outer_cost = 0.;
-    if *outer.lock().unwrap() > 0. {
-        outer_cost = *miss_outer.lock().unwrap() / *outer.lock().unwrap();
+    if outer > 0. {
+        outer_cost = miss_outer / outer;
         outer_cost *= outer_cost;
     }
     let mut inner_cost = 0.;
-    if *inner.lock().unwrap() > 0. {
-        inner_cost = *miss_inner.lock().unwrap() / *inner.lock().unwrap();
+    if inner > 0. {
+        inner_cost = miss_inner / inner;
         inner_cost *= inner_cost;
     }
     return (inner_cost + outer_cost) * 0.5;
@@ -104,7 +96,7 @@ fn window_conf() -> Conf {
 #[macroquad::main(window_conf)]
 async fn main() {
     let mut rng = StdRng::seed_from_u64(0);
-    let mut best_ai = vai::VAI::<3, 1, 16, 1>::new_deterministic(0);
+    let mut best_ai = vai::VAI::<2, 1, 16, 1>::new_deterministic(0);
     let mut score = test(&best_ai, &mut rng, |_, _, _| ());
 
     println!("Starting ai:\n{}", best_ai);
@@ -196,7 +188,7 @@ async fn main() {
         if is_key_pressed(KeyCode::O) {
             match std::fs::File::open("./dotfield-save.vai") {
                 Ok(file) => {
-                    match vai::VAI::<3, 1, 16, 1>::read(&mut std::io::BufReader::new(file).lines())
+                    match vai::VAI::<2, 1, 16, 1>::read(&mut std::io::BufReader::new(file).lines())
                     {
                         Ok(result) => {
                             best_ai = result;
@@ -0,0 +1,143 @@
+#![allow(clippy::needless_return)]
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{Scalar, VaiError};
+
+/// A function applied to the value of each neuron in a layer
+///
+/// Hidden layers default to [`Activation::ReLU`], and output layers default
+/// to [`Activation::Identity`] (ie. a linear output).
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Activation {
+    /// max(0, x)
+    #[default]
+    ReLU,
+    /// Like ReLU, but negative values are multiplied by the given slope
+    LeakyReLU(f32),
+    /// Hyperbolic tangent, mapping to (-1, 1)
+    Tanh,
+    /// Logistic function, mapping to (0, 1)
+    Sigmoid,
+    /// x / (1 + |x|), mapping to (-1, 1)
+    Softsign,
+    /// Leaves the value unchanged
+    Identity,
+    /// A user supplied function.
+    /// Networks with a different [`Scalar`] type convert to and from f32.
+    ///
+    /// Note: custom activations can not be written to, or read from, a file.
+    Custom(fn(f32) -> f32),
+}
+
+impl PartialEq for Activation {
+    fn eq(&self, other: &Self) -> bool {
+        use Activation::*;
+        match (self, other) {
+            (ReLU, ReLU) => true,
+            (LeakyReLU(a), LeakyReLU(b)) => a == b,
+            (Tanh, Tanh) => true,
+            (Sigmoid, Sigmoid) => true,
+            (Softsign, Softsign) => true,
+            (Identity, Identity) => true,
+            (Custom(a), Custom(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+impl Activation {
+    /// Applies the activation function to a single value
+    pub fn apply<T: Scalar>(&self, x: T) -> T {
+        match self {
+            Activation::ReLU => x.max(T::zero()),
+            Activation::LeakyReLU(slope) => {
+                if x > T::zero() {
+                    x
+                } else {
+                    x * T::of_f32(*slope)
+                }
+            }
+            Activation::Tanh => x.tanh(),
+            Activation::Sigmoid => T::one() / (T::one() + (-x).exp()),
+            Activation::Softsign => x / (T::one() + x.abs()),
+            Activation::Identity => x,
+            Activation::Custom(function) => T::of_f32(function(x.as_f64() as f32)),
+        }
+    }
+
+    /// The derivative of the activation function at a single value
+    ///
+    /// Custom activations are differentiated numerically.
+    pub fn derivative<T: Scalar>(&self, x: T) -> T {
+        match self {
+            Activation::ReLU => {
+                if x > T::zero() {
+                    T::one()
+                } else {
+                    T::zero()
+                }
+            }
+            Activation::LeakyReLU(slope) => {
+                if x > T::zero() {
+                    T::one()
+                } else {
+                    T::of_f32(*slope)
+                }
+            }
+            Activation::Tanh => T::one() - x.tanh() * x.tanh(),
+            Activation::Sigmoid => {
+                let s = self.apply(x);
+                s * (T::one() - s)
+            }
+            Activation::Softsign => T::one() / ((T::one() + x.abs()) * (T::one() + x.abs())),
+            Activation::Identity => T::one(),
+            Activation::Custom(function) => {
+                let x = x.as_f64() as f32;
+                let h = 1e-3 * x.abs().max(1.);
+                T::of_f32((function(x + h) - function(x - h)) / (2. * h))
+            }
+        }
+    }
+}
+
+impl Display for Activation {
+    /// Writes the name of the activation, as read by [`Activation::from_str`]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Activation::ReLU => write!(f, "relu"),
+            Activation::LeakyReLU(slope) => write!(f, "leaky_relu({})", slope),
+            Activation::Tanh => write!(f, "tanh"),
+            Activation::Sigmoid => write!(f, "sigmoid"),
+            Activation::Softsign => write!(f, "softsign"),
+            Activation::Identity => write!(f, "identity"),
+            Activation::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
+impl FromStr for Activation {
+    type Err = VaiError;
+
+    /// Parses the name of an activation, as written by its Display
+    /// implementation. Custom activations can not be parsed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || VaiError::UnknownActivation(s.to_owned());
+        let result = match s {
+            "relu" => Activation::ReLU,
+            "tanh" => Activation::Tanh,
+            "sigmoid" => Activation::Sigmoid,
+            "softsign" => Activation::Softsign,
+            "identity" => Activation::Identity,
+            _ => {
+                let slope = s
+                    .strip_prefix("leaky_relu(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .ok_or_else(unknown)?;
+                Activation::LeakyReLU(slope.parse().map_err(|_| unknown())?)
+            }
+        };
+        return Ok(result);
+    }
+}
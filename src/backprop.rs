@@ -0,0 +1,214 @@
+#![allow(clippy::needless_return)]
+
+extern crate nalgebra as na;
+use na::{DMatrix, DMatrixView, DVector, DVectorView};
+
+use crate::Activation;
+
+/// How the error between outputs and targets is measured for training
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Loss {
+    /// The mean of the squared difference of every output and target
+    #[default]
+    MeanSquaredError,
+    /// Treats each column of outputs as the logits of a softmax,
+    /// and each column of targets as a probability distribution.
+    /// The loss is the mean cross entropy of each column.
+    CrossEntropy,
+}
+
+impl Loss {
+    /// Calculates the loss, and its gradient with respect to the outputs
+    /// * outputs - The outputs of the network, with one sample per column
+    /// * targets - The desired outputs, in the same shape
+    pub fn evaluate(&self, outputs: &DMatrix<f32>, targets: &DMatrix<f32>) -> (f32, DMatrix<f32>) {
+        assert_eq!(outputs.shape(), targets.shape(), "outputs and targets must have the same shape");
+        match self {
+            Loss::MeanSquaredError => {
+                let difference = outputs - targets;
+                let count = difference.len().max(1) as f32;
+                let loss = difference.norm_squared() / count;
+                return (loss, difference * (2. / count));
+            }
+            Loss::CrossEntropy => {
+                let samples = outputs.ncols().max(1) as f32;
+                let mut loss = 0.;
+                let mut gradient = outputs.clone();
+                for (mut column, target) in gradient.column_iter_mut().zip(targets.column_iter()) {
+                    let max = column.max();
+                    column.apply(|x| *x = (*x - max).exp());
+                    let sum = column.sum();
+                    column /= sum;
+                    for (p, t) in column.iter().zip(target.iter()) {
+                        loss -= t * p.max(f32::MIN_POSITIVE).ln();
+                    }
+                    column -= target;
+                }
+                return (loss / samples, gradient / samples);
+            }
+        }
+    }
+}
+
+/// The values of every layer of a network, as calculated by a forward pass,
+/// kept for use by a backward pass
+///
+/// Each matrix has one column per sample.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForwardCache {
+    /// The weighted sum (plus bias) of each layer, before its activation
+    pub sums: Vec<DMatrix<f32>>,
+    /// The inputs, followed by the value of each layer after its activation
+    pub outputs: Vec<DMatrix<f32>>,
+}
+
+impl ForwardCache {
+    /// The final outputs of the network
+    pub fn result(&self) -> &DMatrix<f32> {
+        return self.outputs.last().unwrap();
+    }
+}
+
+/// The gradient of a loss with respect to each connection matrix and bias
+/// of a network, shaped like the network's own layers
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradients {
+    pub connections: Vec<DMatrix<f32>>,
+    pub biases: Vec<DVector<f32>>,
+}
+
+impl Gradients {
+    /// Each connection gradient followed by each bias gradient,
+    /// as column-major slices, in the order of [`Trainable::parameters_mut`]
+    pub fn slices(&self) -> impl Iterator<Item = &[f32]> {
+        let connections = self.connections.iter().map(|x| x.as_slice());
+        return connections.chain(self.biases.iter().map(|x| x.as_slice()));
+    }
+
+    /// Mutable version of [`Gradients::slices`]
+    pub fn slices_mut(&mut self) -> impl Iterator<Item = &mut [f32]> {
+        let connections = self.connections.iter_mut().map(|x| x.as_mut_slice());
+        return connections.chain(self.biases.iter_mut().map(|x| x.as_mut_slice()));
+    }
+
+    /// Creates gradients of zeros with the same shape as these gradients
+    pub fn zeros_like(&self) -> Self {
+        return Gradients {
+            connections: self.connections.iter().map(|x| x.map(|_| 0.)).collect(),
+            biases: self.biases.iter().map(|x| x.map(|_| 0.)).collect(),
+        };
+    }
+
+    /// Checks if these gradients have the same shape as other gradients
+    pub fn same_shape(&self, other: &Self) -> bool {
+        let shapes = |g: &Self| -> Vec<(usize, usize)> {
+            let connections = g.connections.iter().map(|x| x.shape());
+            return connections.chain(g.biases.iter().map(|x| x.shape())).collect();
+        };
+        return shapes(self) == shapes(other);
+    }
+}
+
+/// A network that can be trained by gradient descent
+///
+/// see also:
+///  * [`crate::optimizer::Optimizer`]
+pub trait Trainable {
+    /// Runs an input matrix through the neural network,
+    /// keeping the value of every layer
+    /// * inputs - The inputs, with one sample per column
+    fn forward(&self, inputs: &DMatrix<f32>) -> ForwardCache;
+
+    /// Calculates the gradient of a loss with respect to every connection
+    /// and bias. Returns the loss, and its gradients.
+    /// * cache - The result of [`Trainable::forward`]
+    /// * targets - The desired outputs, with one sample per column
+    /// * loss - How the error is measured
+    fn backward(&self, cache: &ForwardCache, targets: &DMatrix<f32>, loss: Loss) -> (f32, Gradients);
+
+    /// Each connection matrix followed by each bias, as column-major slices,
+    /// in the order of [`Gradients::slices`]
+    fn parameters_mut(&mut self) -> Vec<&mut [f32]>;
+
+    /// Trains the network by one step of gradient descent.
+    /// Returns the loss from before the step.
+    /// * inputs - The inputs, with one sample per column
+    /// * targets - The desired outputs, with one sample per column
+    /// * learning_rate - Scaler for the gradient
+    /// * loss - How the error is measured
+    fn descend(
+        &mut self,
+        inputs: &DMatrix<f32>,
+        targets: &DMatrix<f32>,
+        learning_rate: f32,
+        loss: Loss,
+    ) -> f32 {
+        let cache = self.forward(inputs);
+        let (loss, gradients) = self.backward(&cache, targets, loss);
+        for (parameters, gradient) in self.parameters_mut().into_iter().zip(gradients.slices()) {
+            for (x, g) in parameters.iter_mut().zip(gradient) {
+                *x -= learning_rate * g;
+            }
+        }
+        return loss;
+    }
+}
+
+/// A borrowed layer of a network, used to share the forward and
+/// backward passes between network types
+pub(crate) struct LayerRef<'a> {
+    pub connections: DMatrixView<'a, f32>,
+    pub bias: DVectorView<'a, f32>,
+    pub activation: Activation,
+}
+
+/// Runs inputs through layers, keeping the value of every layer
+/// * layers - The layers of the network, from input to output
+/// * inputs - The inputs, with one sample per column
+pub(crate) fn forward(layers: &[LayerRef], inputs: &DMatrix<f32>) -> ForwardCache {
+    let mut cache = ForwardCache {
+        sums: Vec::with_capacity(layers.len()),
+        outputs: vec![inputs.clone()],
+    };
+    for layer in layers {
+        let mut sum = layer.connections * cache.outputs.last().unwrap();
+        for mut column in sum.column_iter_mut() {
+            column += layer.bias;
+        }
+        let mut output = sum.clone();
+        output.apply(|x| *x = layer.activation.apply(*x));
+        cache.sums.push(sum);
+        cache.outputs.push(output);
+    }
+    return cache;
+}
+
+/// Propagates the gradient of a loss backwards through layers
+/// * layers - The layers of the network, from input to output
+/// * cache - The result of a forward pass through the same layers
+/// * targets - The desired outputs, with one sample per column
+/// * loss - How the error is measured
+///
+/// Returns the loss, and its gradients.
+pub(crate) fn backward(
+    layers: &[LayerRef],
+    cache: &ForwardCache,
+    targets: &DMatrix<f32>,
+    loss: Loss,
+) -> (f32, Gradients) {
+    let (loss, mut delta) = loss.evaluate(cache.result(), targets);
+    let mut connections = Vec::with_capacity(layers.len());
+    let mut biases = Vec::with_capacity(layers.len());
+    for (i, layer) in layers.iter().enumerate().rev() {
+        // delta becomes the gradient with respect to this layer's sums
+        delta.zip_apply(&cache.sums[i], |d, sum| *d *= layer.activation.derivative(sum));
+        connections.push(&delta * cache.outputs[i].transpose());
+        biases.push(delta.column_sum());
+        if i > 0 {
+            delta = layer.connections.transpose() * &delta;
+        }
+    }
+    connections.reverse();
+    biases.reverse();
+    return (loss, Gradients { connections, biases });
+}
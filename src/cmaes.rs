@@ -0,0 +1,301 @@
+#![allow(clippy::needless_return)]
+
+extern crate nalgebra as na;
+use na::{DMatrix, DVector};
+extern crate rand;
+use rand::rngs::StdRng;
+
+use crate::population::GenerationStats;
+use crate::{MutationDistribution, NetworkRng, Parameters, Scalar};
+
+/// The covariance matrix adaptation evolution strategy (CMA-ES),
+/// treating every weight and bias of a network as one parameter vector.
+///
+/// Candidates are sampled from a multivariate normal distribution around
+/// `mean`, whose shape (`covariance`) and scale (`sigma`) are learned from
+/// the ranked fitness of each generation, so the search can follow narrow or
+/// rotated valleys that isotropic mutation stalls in.
+///
+/// Use [`CmaEs::ask`] to get a generation of candidates, score them however
+/// is convenient, and pass their fitness (higher is better) to
+/// [`CmaEs::tell`]. [`CmaEs::step`] does both with a fitness function.
+///
+/// The covariance matrix has a row and column for every parameter,
+/// so this is best suited to networks with at most a few thousand weights.
+///
+/// The rng is a [`StdRng`] by default, but can be any [`NetworkRng`].
+#[derive(Clone)]
+pub struct CmaEs<N: Parameters, G: NetworkRng = StdRng> {
+    /// A network with the structure of every candidate
+    pub template: N,
+    /// The center of the search distribution
+    pub mean: DVector<f64>,
+    /// The overall step size
+    pub sigma: f64,
+    /// The shape of the search distribution
+    pub covariance: DMatrix<f64>,
+    /// The number of generations that have been told
+    pub generation: usize,
+    /// Used to sample candidates
+    pub rng: G,
+    population_size: usize,
+    weights: Vec<f64>,
+    mueff: f64,
+    cc: f64,
+    cs: f64,
+    c1: f64,
+    cmu: f64,
+    damps: f64,
+    chi_n: f64,
+    /// The evolution path of the covariance matrix
+    pc: DVector<f64>,
+    /// The evolution path of the step size
+    ps: DVector<f64>,
+    /// The eigenvectors of the covariance matrix
+    b: DMatrix<f64>,
+    /// The square roots of the eigenvalues of the covariance matrix
+    d: DVector<f64>,
+    /// The generation the eigendecomposition was last updated
+    eigen_generation: usize,
+    /// The candidates of the last call to ask
+    candidates: Vec<DVector<f64>>,
+    best: Option<(N, f32)>,
+}
+
+// Constructors without an rng type use StdRng
+impl<N: Parameters> CmaEs<N> {
+    /// Creates a CMA-ES using a random seed, with the default population size
+    /// of `4 + 3 ln(n)` for `n` parameters
+    /// * network - The starting mean, and the structure of every candidate
+    /// * sigma - The starting step size
+    pub fn new(network: N, sigma: f64) -> Self {
+        return Self::new_deterministic(rand::random(), network, sigma);
+    }
+
+    /// Creates a CMA-ES using a specific seed.
+    ///
+    /// See [`CmaEs::new`] for defaults.
+    /// * network - The starting mean, and the structure of every candidate
+    /// * sigma - The starting step size
+    pub fn new_deterministic(seed: u64, network: N, sigma: f64) -> Self {
+        return Self::with_seed(seed, network, sigma);
+    }
+}
+
+impl<N: Parameters, G: NetworkRng> CmaEs<N, G> {
+    /// Creates a CMA-ES like [`CmaEs::new_deterministic`], with any [`NetworkRng`]
+    /// * seed - The seed for random number generation
+    /// * network - The starting mean, and the structure of every candidate
+    /// * sigma - The starting step size
+    pub fn with_seed(seed: u64, network: N, sigma: f64) -> Self {
+        let mean = DVector::from_iterator(
+            network.parameter_count(),
+            network.parameters().iter().map(|x| x.as_f64()),
+        );
+        let n = mean.len();
+        let mut result = Self {
+            template: network,
+            mean,
+            sigma,
+            covariance: DMatrix::identity(n, n),
+            generation: 0,
+            rng: G::seed_from_u64(seed),
+            population_size: 0,
+            weights: Vec::new(),
+            mueff: 0.,
+            cc: 0.,
+            cs: 0.,
+            c1: 0.,
+            cmu: 0.,
+            damps: 0.,
+            chi_n: 0.,
+            pc: DVector::zeros(n),
+            ps: DVector::zeros(n),
+            b: DMatrix::identity(n, n),
+            d: DVector::from_element(n, 1.0),
+            eigen_generation: 0,
+            candidates: Vec::new(),
+            best: None,
+        };
+        let size = 4 + (3.0 * (n.max(1) as f64).ln()) as usize;
+        result.set_population_size(size);
+        return result;
+    }
+
+    /// The number of candidates in each generation (λ)
+    pub fn population_size(&self) -> usize {
+        return self.population_size;
+    }
+
+    /// Changes the number of candidates in each generation (λ),
+    /// along with the learning rates that depend on it.
+    /// Larger populations search more globally.
+    /// * size - The new population size, which must be at least 2
+    pub fn set_population_size(&mut self, size: usize) {
+        let size = size.max(2);
+        let n = self.mean.len().max(1) as f64;
+        let mu = size / 2;
+        let weights: Vec<f64> =
+            (1..=mu).map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln()).collect();
+        let total: f64 = weights.iter().sum();
+        self.weights = weights.iter().map(|x| x / total).collect();
+        let mueff = 1.0 / self.weights.iter().map(|x| x * x).sum::<f64>();
+
+        self.population_size = size;
+        self.mueff = mueff;
+        self.cc = (4.0 + mueff / n) / (n + 4.0 + 2.0 * mueff / n);
+        self.cs = (mueff + 2.0) / (n + mueff + 5.0);
+        self.c1 = 2.0 / ((n + 1.3).powi(2) + mueff);
+        self.cmu = (1.0 - self.c1)
+            .min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((n + 2.0).powi(2) + mueff));
+        self.damps = 1.0 + 2.0 * (((mueff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + self.cs;
+        self.chi_n = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+    }
+
+    /// The best candidate told so far, and its fitness
+    pub fn best(&self) -> Option<(&N, f32)> {
+        return self.best.as_ref().map(|(network, score)| (network, *score));
+    }
+
+    /// A network whose parameters are the mean of the search distribution,
+    /// which is usually the best estimate of the optimum
+    pub fn mean_network(&self) -> N {
+        return self.network(&self.mean);
+    }
+
+    /// Samples a generation of candidate networks, whose fitness should be
+    /// passed to [`CmaEs::tell`] in the same order
+    pub fn ask(&mut self) -> Vec<N> {
+        self.update_eigen();
+        let gaussian = MutationDistribution::Gaussian;
+        let n = self.mean.len();
+        let mut networks = Vec::with_capacity(self.population_size);
+        self.candidates.clear();
+        for _ in 0..self.population_size {
+            let z = DVector::from_fn(n, |_, _| gaussian.sample(&mut self.rng) as f64);
+            let y = &self.b * z.component_mul(&self.d);
+            let x = &self.mean + y * self.sigma;
+            networks.push(self.network(&x));
+            self.candidates.push(x);
+        }
+        return networks;
+    }
+
+    /// Updates the search distribution from the fitness of the candidates
+    /// of the last call to [`CmaEs::ask`]
+    /// * fitness - The fitness of each candidate, in the order they were asked.
+    ///   Higher is better, and NaN is treated as the worst possible fitness.
+    ///
+    /// Returns the statistics of the candidates.
+    ///
+    /// # Panics
+    /// If there are no candidates to tell, because [`CmaEs::ask`] was not
+    /// called since the last call to tell, or if the number of fitness values
+    /// does not match the number of candidates
+    pub fn tell(&mut self, fitness: &[f32]) -> GenerationStats {
+        assert!(!self.candidates.is_empty(), "tell called without ask");
+        assert_eq!(fitness.len(), self.candidates.len(), "Wrong number of fitness values");
+        let scores: Vec<f32> =
+            fitness.iter().map(|x| if x.is_nan() { f32::NEG_INFINITY } else { *x }).collect();
+        // Indices of the candidates, from best to worst
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        let stats = GenerationStats {
+            generation: self.generation,
+            best: order.first().map_or(f32::NAN, |i| scores[*i]),
+            mean: scores.iter().sum::<f32>() / scores.len() as f32,
+            worst: order.last().map_or(f32::NAN, |i| scores[*i]),
+        };
+        if let Some(&i) = order.first() {
+            if self.best.as_ref().is_none_or(|best| scores[i] > best.1) {
+                self.best = Some((self.network(&self.candidates[i]), scores[i]));
+            }
+        }
+
+        let n = self.mean.len() as f64;
+        let old_mean = self.mean.clone();
+        let steps: Vec<DVector<f64>> = order
+            .iter()
+            .take(self.weights.len())
+            .map(|i| (&self.candidates[*i] - &old_mean) / self.sigma)
+            .collect();
+        let mut step = DVector::zeros(self.mean.len());
+        for (weight, y) in self.weights.iter().zip(&steps) {
+            step += y * *weight;
+        }
+        self.mean = &old_mean + &step * self.sigma;
+
+        // C^(-1/2) = B D^-1 B^T
+        let inv_d = DMatrix::from_diagonal(&self.d.map(|x| 1.0 / x));
+        let inv_sqrt = &self.b * inv_d * self.b.transpose();
+        let cs = self.cs;
+        self.ps = &self.ps * (1.0 - cs) + inv_sqrt * &step * (cs * (2.0 - cs) * self.mueff).sqrt();
+        let decay = 1.0 - (1.0 - cs).powi(2 * (self.generation as i32 + 1));
+        let hsig = self.ps.norm() / decay.sqrt() / self.chi_n < 1.4 + 2.0 / (n + 1.0);
+        let hsig = if hsig { 1.0 } else { 0.0 };
+        let cc = self.cc;
+        self.pc = &self.pc * (1.0 - cc) + &step * (hsig * (cc * (2.0 - cc) * self.mueff).sqrt());
+
+        let mut rank_mu = DMatrix::zeros(self.mean.len(), self.mean.len());
+        for (weight, y) in self.weights.iter().zip(&steps) {
+            rank_mu += y * y.transpose() * *weight;
+        }
+        let rank_one = &self.pc * self.pc.transpose()
+            + &self.covariance * ((1.0 - hsig) * cc * (2.0 - cc));
+        self.covariance = &self.covariance * (1.0 - self.c1 - self.cmu)
+            + rank_one * self.c1
+            + rank_mu * self.cmu;
+        self.sigma *= ((cs / self.damps) * (self.ps.norm() / self.chi_n - 1.0)).exp();
+
+        self.candidates.clear();
+        self.generation += 1;
+        return stats;
+    }
+
+    /// Asks for a generation of candidates, scores them, and tells their fitness
+    /// * fitness - Scores a network. Higher is better.
+    ///
+    /// Returns the statistics of the candidates.
+    pub fn step(&mut self, mut fitness: impl FnMut(&N) -> f32) -> GenerationStats {
+        let scores: Vec<f32> = self.ask().iter().map(&mut fitness).collect();
+        return self.tell(&scores);
+    }
+
+    /// Runs [`CmaEs::step`] for a number of generations
+    /// * fitness - Scores a network. Higher is better.
+    ///
+    /// Returns the statistics of each generation.
+    pub fn run(
+        &mut self,
+        generations: usize,
+        mut fitness: impl FnMut(&N) -> f32,
+    ) -> Vec<GenerationStats> {
+        return (0..generations).map(|_| self.step(&mut fitness)).collect();
+    }
+
+    /// Creates a network with the given parameters
+    fn network(&self, parameters: &DVector<f64>) -> N {
+        let mut network = self.template.clone();
+        let parameters: Vec<N::Scalar> =
+            parameters.iter().map(|x| N::Scalar::of_f64(*x)).collect();
+        network.set_parameters(&parameters);
+        return network;
+    }
+
+    /// Updates the eigendecomposition of the covariance matrix, which is only
+    /// done every few generations as it is the most expensive part of CMA-ES
+    fn update_eigen(&mut self) {
+        let n = self.mean.len() as f64;
+        // The usual interval of λ/(c1+cμ)/n/10 counts evaluations, not generations
+        let interval = 1.0 / (self.c1 + self.cmu) / n / 10.0;
+        if self.generation == 0 || ((self.generation - self.eigen_generation) as f64) < interval {
+            return;
+        }
+        self.eigen_generation = self.generation;
+        // Keep the matrix exactly symmetric, despite rounding
+        self.covariance = (&self.covariance + self.covariance.transpose()) * 0.5;
+        let eigen = self.covariance.clone().symmetric_eigen();
+        self.d = eigen.eigenvalues.map(|x| x.max(1e-20).sqrt());
+        self.b = eigen.eigenvectors;
+    }
+}
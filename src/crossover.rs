@@ -0,0 +1,85 @@
+#![allow(clippy::needless_return)]
+
+extern crate nalgebra as na;
+use na::{Dim, Matrix, Storage, StorageMut, Vector};
+extern crate rand;
+use rand::Rng;
+
+use crate::Scalar;
+
+/// The ways in which two parent networks can be recombined into a child
+///
+/// see also:
+///  * [`crate::VAI::crossover_with`]
+///  * [`crate::VAID::crossover_with`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Crossover {
+    /// Each weight and bias is taken from a randomly chosen parent
+    #[default]
+    Uniform,
+    /// For each layer, a random neuron (matrix row) is chosen.
+    /// Neurons before it come from the first parent,
+    /// and the rest come from the second parent.
+    SinglePoint,
+    /// Each layer, including its bias and activation,
+    /// is taken from a randomly chosen parent
+    LayerSwap,
+    /// Each weight and bias is a weighted average of the two parents.
+    /// The weighting is chosen randomly for each layer.
+    Blend,
+}
+
+/// Recombines one layer of a child with the same layer from another parent.
+/// The child should start as a copy of the first parent.
+///
+/// [`Crossover::LayerSwap`] chooses whole layers, so it must be handled
+/// by the caller, and leaves the layer unchanged here.
+/// * rng - The random number generator used to pick weights
+/// * mode - The kind of crossover
+/// * weights, bias - The child's layer, which will be modified
+/// * other_weights, other_bias - The other parent's layer
+pub(crate) fn cross_layer<T, R, C, S1, S2, B1, B2>(
+    rng: &mut (impl Rng + ?Sized),
+    mode: Crossover,
+    weights: &mut Matrix<T, R, C, S1>,
+    bias: &mut Vector<T, R, B1>,
+    other_weights: &Matrix<T, R, C, S2>,
+    other_bias: &Vector<T, R, B2>,
+) where
+    T: Scalar,
+    R: Dim,
+    C: Dim,
+    S1: StorageMut<T, R, C>,
+    S2: Storage<T, R, C>,
+    B1: StorageMut<T, R>,
+    B2: Storage<T, R>,
+{
+    match mode {
+        Crossover::Uniform => {
+            for (x, y) in weights.iter_mut().zip(other_weights.iter()) {
+                if rng.gen::<bool>() {
+                    *x = *y;
+                }
+            }
+            for (x, y) in bias.iter_mut().zip(other_bias.iter()) {
+                if rng.gen::<bool>() {
+                    *x = *y;
+                }
+            }
+        }
+        Crossover::SinglePoint => {
+            let rows = weights.nrows();
+            let point = rng.gen_range(0..=rows);
+            for r in point..rows {
+                weights.row_mut(r).copy_from(&other_weights.row(r));
+                bias[r] = other_bias[r];
+            }
+        }
+        Crossover::LayerSwap => {}
+        Crossover::Blend => {
+            let alpha = T::of_f32(rng.gen::<f32>());
+            weights.zip_apply(other_weights, |x, y| *x = alpha * *x + (T::one() - alpha) * y);
+            bias.zip_apply(other_bias, |x, y| *x = alpha * *x + (T::one() - alpha) * y);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+#![allow(clippy::needless_return)]
+
+use std::fmt::Display;
+use std::io;
+
+use crate::format::NetworkKind;
+use crate::Scalar;
+
+/// An error from reading, writing, or validating a network
+///
+/// Line and column numbers count from 1, starting at the first line read
+/// by the failing call. Layers count from 0, starting at the input layer.
+#[derive(Debug)]
+pub enum VaiError {
+    /// An error from the underlying reader or writer
+    Io(io::Error),
+    /// A token that could not be parsed
+    Parse { line: usize, column: usize, token: String },
+    /// A matrix row, bias, or layer with the wrong number of values
+    DimensionMismatch { layer: usize, expected: usize, found: usize },
+    /// The input ended in the middle of a network
+    UnexpectedEnd { line: usize },
+    /// The layer sizes of a file do not match the network being read
+    ShapeMismatch { expected: Vec<usize>, found: Vec<usize> },
+    /// The file holds a different kind of network than the one being read
+    WrongKind { expected: NetworkKind, found: NetworkKind },
+    /// The file was written by a newer version of the format
+    UnsupportedVersion(u32),
+    /// The checksum of a binary file does not match its contents
+    ChecksumMismatch,
+    /// An [`crate::Activation::Custom`] can not be written
+    CustomActivation,
+    /// An activation name that is not recognized
+    UnknownActivation(String),
+    /// Any other problem with the structure of a file or network
+    Format(String),
+    /// Inputs whose length does not match the network's first layer
+    InputLength { expected: usize, found: usize },
+    /// An input that is NaN or infinite, at the given index
+    /// (in column-major order, for a matrix of inputs)
+    NonFiniteInput { index: usize },
+}
+
+impl Display for VaiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VaiError::Io(err) => write!(f, "{}", err),
+            VaiError::Parse { line, column, token } => {
+                write!(f, "Could not parse '{}' at line {}, column {}", token, line, column)
+            }
+            VaiError::DimensionMismatch { layer, expected, found } => write!(
+                f,
+                "Wrong number of values in layer {}: expected {}, found {}",
+                layer, expected, found
+            ),
+            VaiError::UnexpectedEnd { line } => write!(f, "Unexpected end after line {}", line),
+            VaiError::ShapeMismatch { expected, found } => {
+                write!(f, "Expected layer sizes {:?}, found {:?}", expected, found)
+            }
+            VaiError::WrongKind { expected, found } => {
+                write!(f, "Expected a {:?} model, found {:?}", expected, found)
+            }
+            VaiError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported format version: {}", version)
+            }
+            VaiError::ChecksumMismatch => write!(f, "Checksum mismatch"),
+            VaiError::CustomActivation => write!(f, "Custom activations can not be written"),
+            VaiError::UnknownActivation(name) => write!(f, "Unknown activation: {}", name),
+            VaiError::Format(message) => write!(f, "Invalid model: {}", message),
+            VaiError::InputLength { expected, found } => {
+                write!(f, "Wrong number of inputs: expected {}, found {}", expected, found)
+            }
+            VaiError::NonFiniteInput { index } => write!(f, "Input {} is not finite", index),
+        }
+    }
+}
+
+impl std::error::Error for VaiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VaiError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VaiError {
+    fn from(err: io::Error) -> Self {
+        return VaiError::Io(err);
+    }
+}
+
+impl From<VaiError> for io::Error {
+    fn from(err: VaiError) -> Self {
+        match err {
+            VaiError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Checks inputs before they are processed
+/// * length - The length of each sample of the inputs
+/// * expected - The number of inputs the network takes
+/// * values - Every input value
+/// * require_finite - Whether to reject NaN and infinite inputs
+pub(crate) fn check_inputs<T: Scalar>(
+    length: usize,
+    expected: usize,
+    values: &[T],
+    require_finite: bool,
+) -> Result<(), VaiError> {
+    if length != expected {
+        return Err(VaiError::InputLength { expected, found: length });
+    }
+    if require_finite {
+        if let Some(index) = values.iter().position(|x| !x.is_finite()) {
+            return Err(VaiError::NonFiniteInput { index });
+        }
+    }
+    return Ok(());
+}
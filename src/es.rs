@@ -0,0 +1,390 @@
+#![allow(clippy::needless_return)]
+
+extern crate rand;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::population::GenerationStats;
+use crate::{MutationDistribution, NetworkRng, Parameters, Scalar};
+
+/// The smallest a self-adapted step size can become,
+/// so that a converged search can still move
+const MIN_SIGMA: f32 = 1e-10;
+
+/// Which parameters of an [`Adaptive`] network share a step size
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StepSizes {
+    /// One step size for the whole network
+    #[default]
+    Network,
+    /// One step size for each layer
+    Layer,
+    /// One step size for each weight and bias
+    Weight,
+}
+
+/// A network that carries its own mutation step sizes (sigmas).
+///
+/// Each variant first mutates the step sizes log-normally, then adds
+/// gaussian noise scaled by them to every parameter, so that the step sizes
+/// adapt to the problem along with the weights, and the caller doesn't need
+/// to choose an intensity each generation.
+#[derive(Clone)]
+pub struct Adaptive<N: Parameters> {
+    pub network: N,
+    /// The step sizes, with one for the network, for each layer,
+    /// or for each parameter, depending on `step_sizes`
+    pub sigmas: Vec<f32>,
+    pub step_sizes: StepSizes,
+}
+
+impl<N: Parameters> Adaptive<N> {
+    /// Gives a network its own step sizes
+    /// * network - The network to mutate
+    /// * sigma - The starting value of every step size
+    /// * step_sizes - Which parameters share a step size
+    pub fn new(network: N, sigma: f32, step_sizes: StepSizes) -> Self {
+        let count = match step_sizes {
+            StepSizes::Network => 1,
+            StepSizes::Layer => network.layer_parameter_counts().len(),
+            StepSizes::Weight => network.parameter_count(),
+        };
+        return Self {network, sigmas: vec![sigma; count], step_sizes};
+    }
+
+    /// Creates a random variant of this network, and of its step sizes
+    /// * rng - The random number generator used for the mutation
+    ///
+    /// The step sizes are multiplied by `exp(tau' * N(0, 1) + tau * N_i(0, 1))`,
+    /// where the first factor is shared by every step size, and the second is
+    /// drawn for each one. Each parameter then changes by its new step size
+    /// multiplied by `N(0, 1)`.
+    pub fn create_variant<G: Rng + ?Sized>(&self, rng: &mut G) -> Self {
+        let gaussian = MutationDistribution::Gaussian;
+        let count = self.network.parameter_count();
+        let n = count.max(1) as f32;
+        // The learning rates recommended by Schwefel
+        let (shared_rate, rate) = if self.sigmas.len() == 1 {
+            (0.0, 1.0 / n.sqrt())
+        } else {
+            (1.0 / (2.0 * n).sqrt(), 1.0 / (2.0 * n.sqrt()).sqrt())
+        };
+        let shared = shared_rate * gaussian.sample(rng);
+        let mut result = self.clone();
+        for sigma in result.sigmas.iter_mut() {
+            *sigma = (*sigma * (shared + rate * gaussian.sample(rng)).exp()).max(MIN_SIGMA);
+        }
+
+        let sigmas: Vec<f32> = match self.step_sizes {
+            StepSizes::Network => vec![result.sigmas[0]; count],
+            StepSizes::Layer => {
+                let counts = self.network.layer_parameter_counts();
+                let layers = counts.iter().zip(&result.sigmas);
+                layers.flat_map(|(count, sigma)| std::iter::repeat_n(*sigma, *count)).collect()
+            }
+            StepSizes::Weight => result.sigmas.clone(),
+        };
+        let parameters: Vec<N::Scalar> = self
+            .network
+            .parameters()
+            .iter()
+            .zip(&sigmas)
+            .map(|(x, sigma)| *x + N::Scalar::of_f32(sigma * gaussian.sample(rng)))
+            .collect();
+        result.network.set_parameters(&parameters);
+        return result;
+    }
+}
+
+/// Scores a network, treating a NaN fitness as the worst possible fitness
+fn score<N>(fitness: &mut impl FnMut(&N) -> f32, network: &N) -> f32 {
+    let score = fitness(network);
+    return if score.is_nan() { f32::NEG_INFINITY } else { score };
+}
+
+/// A (1+1) evolution strategy with the 1/5 success rule.
+///
+/// Each generation, a single child is made by adding gaussian noise scaled
+/// by `sigma` to every parameter of the parent, and replaces the parent if
+/// it is at least as fit (higher is better).
+///
+/// Every `window` generations, `sigma` is divided by `factor` if more than
+/// a fifth of the children were fitter than their parent,
+/// and multiplied by `factor` if fewer were.
+///
+/// The rng is a [`StdRng`] by default, but can be any [`NetworkRng`].
+pub struct OnePlusOne<N: Parameters, F: FnMut(&N) -> f32, G: NetworkRng = StdRng> {
+    pub parent: N,
+    pub fitness: F,
+    /// The step size of the mutation
+    pub sigma: f32,
+    /// The number of generations between updates of `sigma`
+    pub window: usize,
+    /// Scales `sigma` when it is updated. Should be between 0 and 1.
+    pub factor: f32,
+    /// The number of generations that have been run
+    pub generation: usize,
+    /// Used for mutation
+    pub rng: G,
+    score: Option<f32>,
+    successes: usize,
+}
+
+// Constructors without an rng type use StdRng
+impl<N: Parameters, F: FnMut(&N) -> f32> OnePlusOne<N, F> {
+    /// Creates a (1+1)-ES using a random seed.
+    ///
+    /// It defaults to updating the step size every 10 generations,
+    /// with a factor of 0.82.
+    /// * parent - The starting network
+    /// * sigma - The starting step size
+    /// * fitness - Scores a network. Higher is better.
+    pub fn new(parent: N, sigma: f32, fitness: F) -> Self {
+        return Self::new_deterministic(rand::random(), parent, sigma, fitness);
+    }
+
+    /// Creates a (1+1)-ES using a specific seed.
+    ///
+    /// See [`OnePlusOne::new`] for defaults.
+    /// * parent - The starting network
+    /// * sigma - The starting step size
+    /// * fitness - Scores a network. Higher is better.
+    pub fn new_deterministic(seed: u64, parent: N, sigma: f32, fitness: F) -> Self {
+        return Self::with_seed(seed, parent, sigma, fitness);
+    }
+}
+
+impl<N: Parameters, F: FnMut(&N) -> f32, G: NetworkRng> OnePlusOne<N, F, G> {
+    /// Creates a (1+1)-ES like [`OnePlusOne::new_deterministic`], with any [`NetworkRng`]
+    /// * seed - The seed for random number generation
+    /// * parent - The starting network
+    /// * sigma - The starting step size
+    /// * fitness - Scores a network. Higher is better.
+    pub fn with_seed(seed: u64, parent: N, sigma: f32, fitness: F) -> Self {
+        return Self {
+            parent,
+            fitness,
+            sigma,
+            window: 10,
+            factor: 0.82,
+            generation: 0,
+            rng: G::seed_from_u64(seed),
+            score: None,
+            successes: 0,
+        };
+    }
+
+    /// The fitness of the parent, once it has been scored
+    pub fn score(&self) -> Option<f32> {
+        return self.score;
+    }
+
+    /// Creates and scores a child, replacing the parent if it is at least as fit
+    ///
+    /// Returns whether the child replaced the parent.
+    pub fn step(&mut self) -> bool {
+        let parent_score = match self.score {
+            Some(parent_score) => parent_score,
+            None => score(&mut self.fitness, &self.parent),
+        };
+        let gaussian = MutationDistribution::Gaussian;
+        let mut child = self.parent.clone();
+        let parameters: Vec<N::Scalar> = child
+            .parameters()
+            .iter()
+            .map(|x| *x + N::Scalar::of_f32(self.sigma * gaussian.sample(&mut self.rng)))
+            .collect();
+        child.set_parameters(&parameters);
+        let child_score = score(&mut self.fitness, &child);
+
+        if child_score > parent_score {
+            self.successes += 1;
+        }
+        let replaced = child_score >= parent_score;
+        if replaced {
+            self.parent = child;
+            self.score = Some(child_score);
+        } else {
+            self.score = Some(parent_score);
+        }
+
+        self.generation += 1;
+        if self.generation.is_multiple_of(self.window.max(1)) {
+            let rate = self.successes as f32 / self.window.max(1) as f32;
+            if rate > 0.2 {
+                self.sigma /= self.factor;
+            } else if rate < 0.2 {
+                self.sigma *= self.factor;
+            }
+            self.successes = 0;
+        }
+        return replaced;
+    }
+
+    /// Runs [`OnePlusOne::step`] for a number of generations
+    ///
+    /// Returns the fitness of the parent afterwards.
+    pub fn run(&mut self, generations: usize) -> f32 {
+        for _ in 0..generations {
+            self.step();
+        }
+        return self.score.unwrap_or(f32::NAN);
+    }
+}
+
+/// Whether the parents of an [`EvolutionStrategy`] compete with their children
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// (μ,λ)-ES: the next parents are the best μ children.
+    /// The number of children should be at least the number of parents.
+    Comma,
+    /// (μ+λ)-ES: the next parents are the best μ of the parents and children
+    Plus,
+}
+
+/// A (μ,λ) or (μ+λ) evolution strategy, with self-adaptive step sizes.
+///
+/// Each generation, λ children are made from uniformly chosen parents with
+/// [`Adaptive::create_variant`], and scored by the fitness function
+/// (higher is better). The best μ of them (and of the parents, for
+/// [`Strategy::Plus`]) become the next parents.
+///
+/// A NaN fitness is treated as the worst possible fitness.
+///
+/// The rng is a [`StdRng`] by default, but can be any [`NetworkRng`].
+pub struct EvolutionStrategy<N: Parameters, F: FnMut(&N) -> f32, G: NetworkRng = StdRng> {
+    /// The μ parents
+    pub parents: Vec<Adaptive<N>>,
+    /// The fitness of each parent, or empty if they haven't been scored yet.
+    /// Should be cleared if the parents are changed by hand.
+    pub parent_scores: Vec<f32>,
+    pub fitness: F,
+    /// λ, the number of children made each generation
+    pub offspring: usize,
+    pub strategy: Strategy,
+    /// The number of generations that have been scored
+    pub generation: usize,
+    /// Used to choose parents, and for mutation
+    pub rng: G,
+    best: Option<(N, f32)>,
+}
+
+// Constructors without an rng type use StdRng
+impl<N: Parameters, F: FnMut(&N) -> f32> EvolutionStrategy<N, F> {
+    /// Creates an evolution strategy using a random seed
+    /// * parents - The μ starting parents
+    /// * offspring - λ, the number of children made each generation
+    /// * strategy - Whether the parents compete with their children
+    /// * fitness - Scores a network. Higher is better.
+    pub fn new(
+        parents: Vec<Adaptive<N>>,
+        offspring: usize,
+        strategy: Strategy,
+        fitness: F,
+    ) -> Self {
+        return Self::new_deterministic(rand::random(), parents, offspring, strategy, fitness);
+    }
+
+    /// Creates an evolution strategy using a specific seed
+    /// * parents - The μ starting parents
+    /// * offspring - λ, the number of children made each generation
+    /// * strategy - Whether the parents compete with their children
+    /// * fitness - Scores a network. Higher is better.
+    pub fn new_deterministic(
+        seed: u64,
+        parents: Vec<Adaptive<N>>,
+        offspring: usize,
+        strategy: Strategy,
+        fitness: F,
+    ) -> Self {
+        return Self::with_seed(seed, parents, offspring, strategy, fitness);
+    }
+}
+
+impl<N: Parameters, F: FnMut(&N) -> f32, G: NetworkRng> EvolutionStrategy<N, F, G> {
+    /// Creates an evolution strategy like [`EvolutionStrategy::new_deterministic`],
+    /// with any [`NetworkRng`]
+    /// * seed - The seed for random number generation
+    /// * parents - The μ starting parents
+    /// * offspring - λ, the number of children made each generation
+    /// * strategy - Whether the parents compete with their children
+    /// * fitness - Scores a network. Higher is better.
+    pub fn with_seed(
+        seed: u64,
+        parents: Vec<Adaptive<N>>,
+        offspring: usize,
+        strategy: Strategy,
+        fitness: F,
+    ) -> Self {
+        return Self {
+            parents,
+            parent_scores: Vec::new(),
+            fitness,
+            offspring,
+            strategy,
+            generation: 0,
+            rng: G::seed_from_u64(seed),
+            best: None,
+        };
+    }
+
+    /// The best network scored so far, and its fitness
+    pub fn best(&self) -> Option<(&N, f32)> {
+        return self.best.as_ref().map(|(network, score)| (network, *score));
+    }
+
+    /// Creates and scores a generation of children, and chooses the next parents
+    ///
+    /// Returns the statistics of the children.
+    pub fn step(&mut self) -> GenerationStats {
+        let mut children = Vec::with_capacity(self.offspring);
+        if !self.parents.is_empty() {
+            for _ in 0..self.offspring {
+                let parent = &self.parents[self.rng.gen_range(0..self.parents.len())];
+                let child = parent.create_variant(&mut self.rng);
+                let child_score = score(&mut self.fitness, &child.network);
+                children.push((child, child_score));
+            }
+        }
+        let scores: Vec<f32> = children.iter().map(|(_, x)| *x).collect();
+        let stats = GenerationStats {
+            generation: self.generation,
+            best: scores.iter().copied().reduce(f32::max).unwrap_or(f32::NAN),
+            mean: scores.iter().sum::<f32>() / scores.len() as f32,
+            worst: scores.iter().copied().reduce(f32::min).unwrap_or(f32::NAN),
+        };
+
+        let mu = self.parents.len();
+        let mut pool = children;
+        if self.strategy == Strategy::Plus {
+            if self.parent_scores.len() != mu {
+                let fitness = &mut self.fitness;
+                let parents = self.parents.iter();
+                self.parent_scores = parents.map(|x| score(fitness, &x.network)).collect();
+            }
+            let parents = std::mem::take(&mut self.parents);
+            pool.extend(parents.into_iter().zip(self.parent_scores.drain(..)));
+        }
+        // Stable, so parents don't displace children of equal fitness
+        pool.sort_by(|a, b| b.1.total_cmp(&a.1));
+        pool.truncate(mu);
+
+        if let Some((network, best)) = pool.first() {
+            if self.best.as_ref().is_none_or(|x| *best > x.1) {
+                self.best = Some((network.network.clone(), *best));
+            }
+        }
+        if !pool.is_empty() {
+            (self.parents, self.parent_scores) = pool.into_iter().unzip();
+        }
+        self.generation += 1;
+        return stats;
+    }
+
+    /// Runs [`EvolutionStrategy::step`] for a number of generations
+    ///
+    /// Returns the statistics of each generation.
+    pub fn run(&mut self, generations: usize) -> Vec<GenerationStats> {
+        return (0..generations).map(|_| self.step()).collect();
+    }
+}
@@ -0,0 +1,759 @@
+#![allow(clippy::needless_return)]
+
+//! The self-describing text format used by [`crate::VAI::write`]
+//! and [`crate::VAID::write`].
+//!
+//! A model file starts with a header:
+//!
+//! ```text
+//! VAI-MODEL 1
+//! kind VAID
+//! layers 2 4 1
+//! activations relu identity
+//! generation 12
+//! fitness 0.5
+//! weights
+//! ```
+//!
+//! The first line holds the magic string and the format version.
+//! The generation, fitness and timestamp lines are optional.
+//! After the `weights` line, each layer is written as a `bias` line
+//! followed by the rows of its connection matrix.
+//!
+//! A [`crate::VAIR`] (`kind VAIR`) has three layer sizes: its inputs, its
+//! hidden state, and its outputs, and two activations: one for its cell and
+//! one for its outputs. After the `weights` line comes a `cell` line naming
+//! its [`crate::vair::Cell`], then its recurrent layer, whose matrix reads the
+//! inputs followed by the previous hidden state, then its output layer.
+//!
+//! Files without a header use the legacy layout, where matrices are written
+//! one after another. In the legacy layout, each matrix may be preceded by
+//! option lines (`activation` and `bias`), which start with a keyword rather
+//! than a number. Layers without them are read using the defaults.
+//!
+//! There is also a compact little-endian binary format, used by
+//! [`crate::VAI::write_binary`] and [`crate::VAID::write_binary`]:
+//!
+//!  * [`BINARY_MAGIC`], and the format version as a u32
+//!  * The network kind as a u8 (0 for VAI, 1 for VAID, 2 for VAIR)
+//!  * A u8 of flags: 1 if there is a checksum, 2 if there is a generation,
+//!    4 if there is a fitness, 8 if there is a timestamp, 16 if the
+//!    weights are f64s rather than f32s, and 32 if there is an rng state
+//!  * The number of layers as a u32, followed by each layer's size as a u32
+//!  * Each activation, as a u8 tag and an f32 parameter
+//!  * The generation (u64), fitness (f32) and timestamp (u64), if present
+//!  * The [`RngState`], if present: the 32 byte seed, the stream as a u64,
+//!    and the word position as a u128
+//!  * For a VAIR, its cell as a u8 (0 for Elman, 1 for GRU, 2 for LSTM)
+//!  * For each layer, its connection matrix as raw f32s (or f64s) in
+//!    column-major order, followed by its bias
+//!  * A CRC-32 of every preceding byte, if there is a checksum
+
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+extern crate nalgebra as na;
+use na::{DMatrix, Dim, Matrix, RawStorage};
+
+use crate::{Activation, Scalar, VaiError};
+
+/// The first word of every model file
+pub const MAGIC: &str = "VAI-MODEL";
+
+/// The newest format version that can be written and read
+pub const VERSION: u32 = 1;
+
+/// The first bytes of every binary model file
+pub const BINARY_MAGIC: &[u8; 4] = b"VAIB";
+
+/// The type of network stored in a model file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkKind {
+    VAI,
+    VAID,
+    VAIR,
+}
+
+/// Optional information stored alongside a network
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    /// The generation in which the network was created
+    pub generation: Option<u64>,
+    /// The network's most recent fitness score
+    pub fitness: Option<f32>,
+    /// When the network was saved, in seconds since the unix epoch
+    pub timestamp: Option<u64>,
+}
+
+/// The full state of a network's rng, stored in checkpoints
+/// so that evolution can be resumed exactly.
+/// See [`crate::CheckpointRng`].
+///
+/// The fields hold the state of a ChaCha rng. Other rngs with up to
+/// 256 bits of state can store it in `seed`, and leave the rest as 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RngState {
+    pub seed: [u8; 32],
+    pub stream: u64,
+    /// The number of 32 bit words generated so far
+    pub word_pos: u128,
+}
+
+/// How the weights of a binary file are stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Encoding {
+    /// Whether the weights are followed by a checksum
+    pub checksum: bool,
+    /// Whether the weights are f64s rather than f32s
+    pub wide: bool,
+}
+
+impl Encoding {
+    /// The encoding for a network of the given scalar type.
+    /// Scalars larger than an f32 are stored as f64s.
+    /// * checksum - Whether the weights are followed by a checksum
+    pub fn new<T: Scalar>(checksum: bool) -> Self {
+        return Self {checksum, wide: std::mem::size_of::<T>() > 4};
+    }
+}
+
+/// The header of a model file, describing the network that follows it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub kind: NetworkKind,
+    /// The number of neurons in each layer, starting with the inputs
+    pub layers: Vec<usize>,
+    /// The activation of each layer after the inputs
+    pub activations: Vec<Activation>,
+    pub metadata: Metadata,
+    /// The state of the network's rng, which is only stored by checkpoints
+    /// in the binary format
+    pub rng: Option<RngState>,
+}
+
+impl Header {
+    /// Writes the header, ending with the `weights` line
+    /// * file - The file to write to
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    pub fn write(&self, file: &mut impl Write) -> Result<(), VaiError> {
+        writeln!(file, "{} {}", MAGIC, self.version)?;
+        let kind = match self.kind {
+            NetworkKind::VAI => "VAI",
+            NetworkKind::VAID => "VAID",
+            NetworkKind::VAIR => "VAIR",
+        };
+        writeln!(file, "kind {}", kind)?;
+        write!(file, "layers")?;
+        for size in &self.layers {
+            write!(file, " {}", size)?;
+        }
+        writeln!(file)?;
+        write!(file, "activations")?;
+        for activation in &self.activations {
+            if let Activation::Custom(_) = activation {
+                return Err(VaiError::CustomActivation);
+            }
+            write!(file, " {}", activation)?;
+        }
+        writeln!(file)?;
+        if let Some(generation) = self.metadata.generation {
+            writeln!(file, "generation {}", generation)?;
+        }
+        if let Some(fitness) = self.metadata.fitness {
+            writeln!(file, "fitness {}", fitness)?;
+        }
+        if let Some(timestamp) = self.metadata.timestamp {
+            writeln!(file, "timestamp {}", timestamp)?;
+        }
+        writeln!(file, "weights")?;
+        return Ok(());
+    }
+
+    /// Reads a header, if there is one. Empty lines are skipped.
+    /// If the first line does not start with [`MAGIC`], it is left unread,
+    /// and `None` is returned so that the legacy layout can be read instead.
+    ///
+    /// Unknown header lines are ignored.
+    /// If there is no activations line, the default activations are used.
+    /// * lines - The lines from which to read the header
+    pub(crate) fn read<L>(lines: &mut LineReader<L>) -> Result<Option<Self>, VaiError>
+    where
+        L: Iterator<Item = io::Result<String>>,
+    {
+        loop {
+            match lines.peek()? {
+                Some(line) if line.trim().is_empty() => {}
+                Some(line) if line.split_whitespace().next() == Some(MAGIC) => break,
+                _ => return Ok(None),
+            }
+            lines.next_line()?;
+        }
+        let first_line = lines.expect_line()?;
+        let version = match first_line.split_whitespace().nth(1) {
+            Some(token) => lines.parse(&first_line, token)?,
+            None => return Err(VaiError::Format("Missing format version".to_owned())),
+        };
+        if version > VERSION {
+            return Err(VaiError::UnsupportedVersion(version));
+        }
+
+        let mut kind = None;
+        let mut layers = None;
+        let mut activations = None;
+        let mut metadata = Metadata::default();
+        loop {
+            let line = lines.expect_line()?;
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next();
+            let mut value = || match tokens.next() {
+                Some(token) => Ok(token),
+                None => Err(VaiError::Format(format!("Missing value for {}", keyword.unwrap()))),
+            };
+            match keyword {
+                Some("weights") => break,
+                Some("kind") => {
+                    let token = value()?;
+                    kind = match token {
+                        "VAI" => Some(NetworkKind::VAI),
+                        "VAID" => Some(NetworkKind::VAID),
+                        "VAIR" => Some(NetworkKind::VAIR),
+                        _ => return Err(lines.parse_error(&line, token)),
+                    }
+                }
+                Some("layers") => {
+                    let sizes: Result<Vec<usize>, _> = tokens.map(|x| lines.parse(&line, x)).collect();
+                    layers = Some(sizes?);
+                }
+                Some("activations") => {
+                    let parsed: Result<Vec<Activation>, _> =
+                        tokens.map(|x| lines.parse(&line, x)).collect();
+                    activations = Some(parsed?);
+                }
+                Some("generation") => metadata.generation = Some(lines.parse(&line, value()?)?),
+                Some("fitness") => metadata.fitness = Some(lines.parse(&line, value()?)?),
+                Some("timestamp") => metadata.timestamp = Some(lines.parse(&line, value()?)?),
+                _ => {}
+            }
+        }
+        let kind = kind.ok_or_else(|| VaiError::Format("Missing network kind".to_owned()))?;
+        let layers: Vec<usize> =
+            layers.ok_or_else(|| VaiError::Format("Missing layer sizes".to_owned()))?;
+        let connections = layers.len().saturating_sub(1);
+        let activations = activations.unwrap_or_else(|| default_activations(connections));
+        if activations.len() != connections {
+            return Err(VaiError::Format("Wrong number of activations".to_owned()));
+        }
+        return Ok(Some(Self {version, kind, layers, activations, metadata, rng: None}));
+    }
+
+    /// Writes the header in the binary format
+    /// * writer - The writer to write to
+    /// * encoding - How the weights that follow will be stored
+    pub(crate) fn write_binary<W: Write>(
+        &self,
+        writer: &mut BinaryWriter<W>,
+        encoding: Encoding,
+    ) -> Result<(), VaiError> {
+        let metadata = &self.metadata;
+        let flags = encoding.checksum as u8
+            | (metadata.generation.is_some() as u8) << 1
+            | (metadata.fitness.is_some() as u8) << 2
+            | (metadata.timestamp.is_some() as u8) << 3
+            | (encoding.wide as u8) << 4
+            | (self.rng.is_some() as u8) << 5;
+        writer.write_bytes(BINARY_MAGIC)?;
+        writer.write_u32(self.version)?;
+        writer.write_u8(match self.kind {
+            NetworkKind::VAI => 0,
+            NetworkKind::VAID => 1,
+            NetworkKind::VAIR => 2,
+        })?;
+        writer.write_u8(flags)?;
+        writer.write_u32(self.layers.len() as u32)?;
+        for size in &self.layers {
+            writer.write_u32(*size as u32)?;
+        }
+        for activation in &self.activations {
+            let (tag, parameter) = match activation {
+                Activation::ReLU => (0, 0.),
+                Activation::LeakyReLU(slope) => (1, *slope),
+                Activation::Tanh => (2, 0.),
+                Activation::Sigmoid => (3, 0.),
+                Activation::Softsign => (4, 0.),
+                Activation::Identity => (5, 0.),
+                Activation::Custom(_) => return Err(VaiError::CustomActivation),
+            };
+            writer.write_u8(tag)?;
+            writer.write_f32(parameter)?;
+        }
+        if let Some(generation) = metadata.generation {
+            writer.write_u64(generation)?;
+        }
+        if let Some(fitness) = metadata.fitness {
+            writer.write_f32(fitness)?;
+        }
+        if let Some(timestamp) = metadata.timestamp {
+            writer.write_u64(timestamp)?;
+        }
+        if let Some(rng) = &self.rng {
+            writer.write_bytes(&rng.seed)?;
+            writer.write_u64(rng.stream)?;
+            writer.write_bytes(&rng.word_pos.to_le_bytes())?;
+        }
+        return Ok(());
+    }
+
+    /// Reads a header in the binary format.
+    /// Returns the header, and how the weights that follow are stored.
+    /// * reader - The reader from which to read the header
+    pub(crate) fn read_binary<R: Read>(
+        reader: &mut BinaryReader<R>,
+    ) -> Result<(Self, Encoding), VaiError> {
+        let invalid = |message: &str| VaiError::Format(message.to_owned());
+        let mut magic = [0; 4];
+        reader.read_bytes(&mut magic)?;
+        if &magic != BINARY_MAGIC {
+            return Err(invalid("Not a binary model file"));
+        }
+        let version = reader.read_u32()?;
+        if version > VERSION {
+            return Err(VaiError::UnsupportedVersion(version));
+        }
+        let kind = match reader.read_u8()? {
+            0 => NetworkKind::VAI,
+            1 => NetworkKind::VAID,
+            2 => NetworkKind::VAIR,
+            _ => return Err(invalid("Unknown network kind")),
+        };
+        let flags = reader.read_u8()?;
+        let count = reader.read_u32()? as usize;
+        let mut layers = Vec::new();
+        for _ in 0..count {
+            layers.push(reader.read_u32()? as usize);
+        }
+        let mut activations = Vec::new();
+        for _ in 1..count {
+            let tag = reader.read_u8()?;
+            let parameter = reader.read_f32()?;
+            activations.push(match tag {
+                0 => Activation::ReLU,
+                1 => Activation::LeakyReLU(parameter),
+                2 => Activation::Tanh,
+                3 => Activation::Sigmoid,
+                4 => Activation::Softsign,
+                5 => Activation::Identity,
+                _ => return Err(invalid("Unknown activation")),
+            });
+        }
+        let mut metadata = Metadata::default();
+        if flags & 2 != 0 {
+            metadata.generation = Some(reader.read_u64()?);
+        }
+        if flags & 4 != 0 {
+            metadata.fitness = Some(reader.read_f32()?);
+        }
+        if flags & 8 != 0 {
+            metadata.timestamp = Some(reader.read_u64()?);
+        }
+        let mut rng = None;
+        if flags & 32 != 0 {
+            let mut seed = [0; 32];
+            reader.read_bytes(&mut seed)?;
+            let stream = reader.read_u64()?;
+            let mut word_pos = [0; 16];
+            reader.read_bytes(&mut word_pos)?;
+            rng = Some(RngState {seed, stream, word_pos: u128::from_le_bytes(word_pos)});
+        }
+        let header = Self {version, kind, layers, activations, metadata, rng};
+        let encoding = Encoding {checksum: flags & 1 != 0, wide: flags & 16 != 0};
+        return Ok((header, encoding));
+    }
+
+    /// Checks that the header describes the expected kind of network
+    pub(crate) fn expect_kind(&self, kind: NetworkKind) -> Result<(), VaiError> {
+        if self.kind != kind {
+            return Err(VaiError::WrongKind { expected: kind, found: self.kind });
+        }
+        return Ok(());
+    }
+
+    /// Checks that the header describes the expected layer sizes
+    pub(crate) fn expect_layers(&self, layers: &[usize]) -> Result<(), VaiError> {
+        if self.layers != layers {
+            return Err(VaiError::ShapeMismatch {
+                expected: layers.to_vec(),
+                found: self.layers.clone(),
+            });
+        }
+        return Ok(());
+    }
+}
+
+/// Writes little-endian values, keeping a CRC-32 of every byte written
+pub(crate) struct BinaryWriter<W: Write> {
+    writer: W,
+    crc: u32,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    pub fn new(writer: W) -> Self {
+        return Self {writer, crc: 0xFFFF_FFFF};
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.crc = crc32_update(self.crc, bytes);
+        return self.writer.write_all(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        return self.write_bytes(&[value]);
+    }
+
+    pub fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        return self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        return self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) -> io::Result<()> {
+        return self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Writes weights as f64s if wide, or f32s otherwise
+    pub fn write_scalars<T: Scalar>(&mut self, values: &[T], wide: bool) -> io::Result<()> {
+        let bytes: Vec<u8> = match wide {
+            true => values.iter().flat_map(|x| x.as_f64().to_le_bytes()).collect(),
+            false => values.iter().flat_map(|x| (x.as_f64() as f32).to_le_bytes()).collect(),
+        };
+        return self.write_bytes(&bytes);
+    }
+
+    /// Writes the checksum of everything written so far, if requested
+    pub fn finish(&mut self, checksum: bool) -> Result<(), VaiError> {
+        if checksum {
+            let crc = !self.crc;
+            self.write_u32(crc)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Reads little-endian values, keeping a CRC-32 of every byte read
+pub(crate) struct BinaryReader<R: Read> {
+    reader: R,
+    crc: u32,
+}
+
+impl<R: Read> BinaryReader<R> {
+    pub fn new(reader: R) -> Self {
+        return Self {reader, crc: 0xFFFF_FFFF};
+    }
+
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(bytes)?;
+        self.crc = crc32_update(self.crc, bytes);
+        return Ok(());
+    }
+
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        let mut bytes = [0; 1];
+        self.read_bytes(&mut bytes)?;
+        return Ok(bytes[0]);
+    }
+
+    pub fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        self.read_bytes(&mut bytes)?;
+        return Ok(u32::from_le_bytes(bytes));
+    }
+
+    pub fn read_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0; 8];
+        self.read_bytes(&mut bytes)?;
+        return Ok(u64::from_le_bytes(bytes));
+    }
+
+    pub fn read_f32(&mut self) -> io::Result<f32> {
+        let mut bytes = [0; 4];
+        self.read_bytes(&mut bytes)?;
+        return Ok(f32::from_le_bytes(bytes));
+    }
+
+    pub fn read_f32s(&mut self, count: usize) -> Result<Vec<f32>, VaiError> {
+        let bytes = self.read_values(count, 4)?;
+        let values = bytes.chunks_exact(4);
+        return Ok(values.map(|x| f32::from_le_bytes(x.try_into().unwrap())).collect());
+    }
+
+    /// Reads weights written by [`BinaryWriter::write_scalars`]
+    pub fn read_scalars<T: Scalar>(
+        &mut self,
+        count: usize,
+        wide: bool,
+    ) -> Result<Vec<T>, VaiError> {
+        if !wide {
+            return Ok(self.read_f32s(count)?.into_iter().map(T::of_f32).collect());
+        }
+        let bytes = self.read_values(count, 8)?;
+        let values = bytes.chunks_exact(8);
+        return Ok(values.map(|x| T::of_f64(f64::from_le_bytes(x.try_into().unwrap()))).collect());
+    }
+
+    /// Reads `count` values of `width` bytes each.
+    /// The count comes from the file, so the bytes are only allocated as they
+    /// are read, and a corrupt count fails at the end of the file.
+    fn read_values(&mut self, count: usize, width: usize) -> Result<Vec<u8>, VaiError> {
+        let len = checked_size(count, width)?;
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.crc = crc32_update(self.crc, &bytes);
+        return Ok(bytes);
+    }
+
+    /// Reads and verifies the checksum of everything read so far, if there is one
+    pub fn finish(&mut self, checksum: bool) -> Result<(), VaiError> {
+        if checksum {
+            let expected = !self.crc;
+            if self.read_u32()? != expected {
+                return Err(VaiError::ChecksumMismatch);
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Multiplies two sizes read from a file, failing if the result overflows
+pub(crate) fn checked_size(a: usize, b: usize) -> Result<usize, VaiError> {
+    return a.checked_mul(b).ok_or_else(|| VaiError::Format("Layer is too large".to_owned()));
+}
+
+/// Adds bytes to a running CRC-32 (IEEE), which starts as 0xFFFFFFFF
+/// and is inverted when finished
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    return crc;
+}
+
+/// The default activations for a given number of connection matrices:
+/// ReLU for every hidden layer, and Identity for the output layer
+pub(crate) fn default_activations(layers: usize) -> Vec<Activation> {
+    let mut activations = vec![Activation::ReLU; layers];
+    if let Some(last) = activations.last_mut() {
+        *last = Activation::Identity;
+    }
+    return activations;
+}
+
+/// Writes a layer after the header, as a `bias` line followed by the rows
+/// of its connection matrix, and an empty line
+/// * file - The file to write to
+/// * connections - The layer's connection matrix
+/// * bias - The bias added to each neuron of the layer
+pub(crate) fn write_layer<T: Scalar, R: Dim, C: Dim, S: RawStorage<T, R, C>>(
+    file: &mut impl Write,
+    connections: &Matrix<T, R, C, S>,
+    bias: &[T],
+) -> Result<(), VaiError> {
+    write!(file, "bias ")?;
+    for val in bias {
+        write!(file, "{} ", val)?;
+    }
+    writeln!(file)?;
+    for row in connections.row_iter() {
+        for val in row.iter() {
+            write!(file, "{} ", val)?;
+        }
+        writeln!(file)?;
+    }
+    writeln!(file)?;
+    return Ok(());
+}
+
+/// Reads a layer written by [`write_layer`]. Empty lines are skipped.
+/// * lines - The lines from which to read the layer
+/// * layer - The index of the layer, for error reporting
+/// * rows, columns - The expected shape of the connection matrix
+pub(crate) fn read_layer<T: Scalar, L>(
+    lines: &mut LineReader<L>,
+    layer: usize,
+    rows: usize,
+    columns: usize,
+) -> Result<(DMatrix<T>, Vec<T>), VaiError>
+where
+    L: Iterator<Item = io::Result<String>>,
+{
+    let mut options = read_layer_options(lines)?;
+    let bias = options.take_bias(layer, rows)?;
+    // Not preallocated, as the sizes come from the file
+    let mut values = Vec::<T>::new();
+    for _ in 0..rows {
+        values.extend(read_row::<T, L>(lines, layer, Some(columns))?);
+    }
+    return Ok((DMatrix::from_row_slice(rows, columns, &values), bias));
+}
+
+/// Reads the next non-empty line as a row of space-delimited numbers
+/// * lines - The lines from which to read the row
+/// * layer - The index of the layer, for error reporting
+/// * columns - The expected number of values, if known
+pub(crate) fn read_row<T: Scalar, L>(
+    lines: &mut LineReader<L>,
+    layer: usize,
+    columns: Option<usize>,
+) -> Result<Vec<T>, VaiError>
+where
+    L: Iterator<Item = io::Result<String>>,
+{
+    let line = lines.expect_nonempty_line()?;
+    let row = line
+        .split_whitespace()
+        .map(|val| lines.parse(&line, val))
+        .collect::<Result<Vec<T>, _>>()?;
+    if let Some(columns) = columns {
+        if row.len() != columns {
+            return Err(VaiError::DimensionMismatch { layer, expected: columns, found: row.len() });
+        }
+    }
+    return Ok(row);
+}
+
+/// Optional settings that may precede a matrix
+pub(crate) struct LayerOptions<T> {
+    pub activation: Option<Activation>,
+    pub bias: Option<Vec<T>>,
+}
+
+impl<T: Scalar> LayerOptions<T> {
+    /// Takes the bias, checking that it has one value per neuron.
+    /// Layers without a bias line get a zero bias.
+    /// * layer - The index of the layer, for error reporting
+    /// * neurons - The number of neurons in the layer
+    pub fn take_bias(&mut self, layer: usize, neurons: usize) -> Result<Vec<T>, VaiError> {
+        match self.bias.take() {
+            None => Ok(vec![T::zero(); neurons]),
+            Some(bias) if bias.len() == neurons => Ok(bias),
+            Some(bias) => Err(VaiError::DimensionMismatch {
+                layer,
+                expected: neurons,
+                found: bias.len(),
+            }),
+        }
+    }
+}
+
+/// Reads any option lines preceding a matrix. Empty lines are skipped,
+/// and the first line that is not an option line is left unread.
+/// * lines - The lines from which to read the options
+pub(crate) fn read_layer_options<T: Scalar, L>(
+    lines: &mut LineReader<L>,
+) -> Result<LayerOptions<T>, VaiError>
+where
+    L: Iterator<Item = io::Result<String>>,
+{
+    let mut options = LayerOptions {activation: None, bias: None};
+    while let Some(line) = lines.peek()? {
+        let keyword = line.split_whitespace().next().map(|x| x.to_owned());
+        match keyword.as_deref() {
+            None => {}
+            Some("activation") => {
+                let line = lines.expect_line()?;
+                let name = line.split_whitespace().nth(1).unwrap_or_default();
+                options.activation = Some(lines.parse(&line, name)?);
+                continue;
+            }
+            Some("bias") => {
+                let line = lines.expect_line()?;
+                let values = line.split_whitespace().skip(1);
+                let bias = values.map(|val| lines.parse(&line, val)).collect::<Result<_, _>>();
+                options.bias = Some(bias?);
+                continue;
+            }
+            Some(_) => break,
+        }
+        lines.next_line()?;
+    }
+    return Ok(options);
+}
+
+/// A line iterator that can look at the next line without reading it,
+/// and counts the lines it has read, to report where errors occur
+pub(crate) struct LineReader<L> {
+    lines: L,
+    peeked: Option<String>,
+    /// The number of lines read so far
+    line: usize,
+}
+
+impl<L: Iterator<Item = io::Result<String>>> LineReader<L> {
+    pub fn new(lines: L) -> Self {
+        return Self {lines, peeked: None, line: 0};
+    }
+
+    /// Returns the next line without reading it
+    pub fn peek(&mut self) -> Result<Option<&str>, VaiError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lines.next().transpose()?;
+        }
+        return Ok(self.peeked.as_deref());
+    }
+
+    /// Reads the next line
+    pub fn next_line(&mut self) -> Result<Option<String>, VaiError> {
+        let line = match self.peeked.take() {
+            Some(line) => Some(line),
+            None => self.lines.next().transpose()?,
+        };
+        if line.is_some() {
+            self.line += 1;
+        }
+        return Ok(line);
+    }
+
+    /// Reads the next line, failing if there are none left
+    pub fn expect_line(&mut self) -> Result<String, VaiError> {
+        return self.next_line()?.ok_or(VaiError::UnexpectedEnd { line: self.line });
+    }
+
+    /// Reads the next line that is not empty (or whitespace),
+    /// failing if there are none left
+    pub fn expect_nonempty_line(&mut self) -> Result<String, VaiError> {
+        loop {
+            let line = self.expect_line()?;
+            if !line.trim().is_empty() {
+                return Ok(line);
+            }
+        }
+    }
+
+    /// Parses a token from the most recently read line
+    /// * line - The most recently read line
+    /// * token - A slice of the line to parse
+    pub fn parse<T: FromStr>(&self, line: &str, token: &str) -> Result<T, VaiError> {
+        return token.parse().map_err(|_| self.parse_error(line, token));
+    }
+
+    /// The error for a token of the most recently read line that could not be parsed
+    /// * line - The most recently read line
+    /// * token - A slice of the line that could not be parsed
+    pub fn parse_error(&self, line: &str, token: &str) -> VaiError {
+        let offset = (token.as_ptr() as usize).saturating_sub(line.as_ptr() as usize);
+        return VaiError::Parse {
+            line: self.line,
+            column: line[..offset.min(line.len())].chars().count() + 1,
+            token: token.to_owned(),
+        };
+    }
+}
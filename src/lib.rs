@@ -1,9 +1,39 @@
 #![allow(clippy::needless_return)]
 
+use rand::Rng;
+
+pub mod activation;
+pub mod backprop;
+pub mod cmaes;
+pub mod crossover;
+pub mod error;
+pub mod es;
+pub mod format;
+pub mod mutation;
+pub mod neat;
+pub mod network;
+pub mod optimizer;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod population;
+pub mod scalar;
+#[cfg(feature = "serde")]
+pub mod serialization;
 pub mod vai;
 pub mod vaid;
+pub mod vair;
+pub use activation::Activation;
+pub use backprop::{Loss, Trainable};
+pub use crossover::Crossover;
+pub use error::VaiError;
+pub use mutation::{MutationConfig, MutationDistribution};
+pub use network::{CheckpointRng, Network, NetworkRng, Parameters};
+pub use optimizer::Optimizer;
+pub use population::Population;
+pub use scalar::Scalar;
 pub use vai::VAI;
 pub use vaid::VAID;
+pub use vair::VAIR;
 
 /// Maps a 0-1 value to +- infinity, with low weighted extremes
 pub fn infinite_map(input: f32) -> f32 {
@@ -16,5 +46,5 @@ pub fn infinite_map(input: f32) -> f32 {
 
 /// Gets a random index less than the provided length
 pub fn rand_index(len: usize) -> usize {
-    (rand::random::<f32>() * (len + 1) as f32).floor() as usize
+    rand::thread_rng().gen_range(0..len.max(1))
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+#![allow(clippy::needless_return)]
+
+use std::f32::consts::PI;
+
+extern crate nalgebra as na;
+use na::{DefaultAllocator, Dim, OMatrix};
+use rand::Rng;
+
+use crate::{infinite_map, Scalar};
+
+/// The distribution that the change to each weight is drawn from
+/// when a network is mutated. Every distribution is centered on 0,
+/// and the change is scaled by the intensity of the mutation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MutationDistribution {
+    /// [`infinite_map`] of a uniform value: heavy-tailed, favoring small
+    /// changes while still allowing arbitrarily large ones
+    #[default]
+    InfiniteMap,
+    /// A normal distribution with a standard deviation of 1
+    Gaussian,
+    /// A Cauchy distribution with a scale of 1, whose tails are even heavier
+    /// than those of [`MutationDistribution::InfiniteMap`]
+    Cauchy,
+    /// A uniform distribution between -1 and 1
+    Uniform,
+    /// A Laplace distribution with a scale of 1
+    Laplace,
+}
+
+impl MutationDistribution {
+    /// Draws a single change, before it is scaled by the intensity
+    /// * rng - The random number generator to draw from
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        match self {
+            MutationDistribution::InfiniteMap => return infinite_map(rng.gen::<f32>()),
+            MutationDistribution::Gaussian => {
+                // Box-Muller transform, with u in (0, 1] so the log is finite
+                let u = 1.0 - rng.gen::<f32>();
+                let v = rng.gen::<f32>();
+                return (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos();
+            }
+            MutationDistribution::Cauchy => {
+                let x = rng.gen::<f32>() - 0.5;
+                if x == -0.5 {
+                    return 0.;
+                }
+                return (PI * x).tan();
+            }
+            MutationDistribution::Uniform => return rng.gen_range(-1.0..=1.0),
+            MutationDistribution::Laplace => {
+                let x = rng.gen::<f32>() - 0.5;
+                if x == -0.5 {
+                    return 0.;
+                }
+                return -x.signum() * (1.0 - 2.0 * x.abs()).ln();
+            }
+        }
+    }
+}
+
+/// How the weights of a network are changed when it is mutated
+///
+/// The default changes every weight, using [`MutationDistribution::InfiniteMap`],
+/// which is how [`crate::VAI::create_variant`] mutates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MutationConfig {
+    /// The distribution each change is drawn from
+    pub distribution: MutationDistribution,
+    /// The probability of each weight being changed, from 0 to 1.
+    /// Lower values give sparse mutations, that leave most weights unchanged.
+    pub probability: f32,
+}
+
+impl Default for MutationConfig {
+    fn default() -> Self {
+        return Self::new(MutationDistribution::InfiniteMap, 1.0);
+    }
+}
+
+impl MutationConfig {
+    /// Creates a mutation
+    /// * distribution - The distribution each change is drawn from
+    /// * probability - The probability of each weight being changed
+    pub fn new(distribution: MutationDistribution, probability: f32) -> Self {
+        return Self {distribution, probability};
+    }
+
+    /// Creates a mutation that changes every weight
+    /// * distribution - The distribution each change is drawn from
+    pub fn dense(distribution: MutationDistribution) -> Self {
+        return Self::new(distribution, 1.0);
+    }
+
+    /// Draws the change to a single weight, which may be 0 for sparse mutations
+    /// * rng - The random number generator to draw from
+    /// * intensity - Scaler for the change
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, intensity: f32) -> f32 {
+        // Dense mutations don't draw for the probability,
+        // so they use the same random numbers as before
+        if self.probability < 1.0 && rng.gen::<f32>() >= self.probability {
+            return 0.;
+        }
+        return intensity * self.distribution.sample(rng);
+    }
+}
+
+/// Creates a random variation of a matrix (or vector)
+/// * rng - Any rng, such as a network's rng or a fast PCG or Xoshiro
+/// * original - The matrix that will be varied
+/// * intensity - Scaler for each change
+/// * mutation - How each weight is changed
+///
+/// see also:
+///  * [`crate::vaid::create_variant_with_rng`]
+pub fn mutate<T, G, R, C>(
+    rng: &mut G,
+    original: &OMatrix<T, R, C>,
+    intensity: f32,
+    mutation: MutationConfig,
+) -> OMatrix<T, R, C>
+where
+    T: Scalar,
+    G: Rng + ?Sized,
+    R: Dim,
+    C: Dim,
+    DefaultAllocator: na::allocator::Allocator<T, R, C>,
+{
+    let mut result = original.clone_owned();
+    result.apply(|x| *x += T::of_f32(mutation.sample(rng, intensity)));
+    return result;
+}
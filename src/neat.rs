@@ -0,0 +1,546 @@
+#![allow(clippy::needless_return)]
+
+//! A NEAT-style genome: a network whose topology is a graph of node and
+//! connection genes, rather than a stack of layers.
+//!
+//! Unlike [`crate::VAI`] and [`crate::VAID`], a [`Genome`] can have
+//! connections that skip layers, and its structure can grow through
+//! mutation. Every structural change is given an innovation number by an
+//! [`InnovationTracker`] shared by a population, so that genomes can be
+//! lined up for crossover and compared for speciation.
+
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::{infinite_map, Activation, NetworkRng};
+
+/// The role of a node in a [`Genome`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Input,
+    Hidden,
+    Output,
+}
+
+/// A neuron of a [`Genome`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeGene {
+    /// Identifies the node across every genome sharing an [`InnovationTracker`]
+    pub id: usize,
+    pub kind: NodeKind,
+    /// Added to the weighted sum of the node's inputs (unused by input nodes)
+    pub bias: f32,
+    /// Applied to the node's value (unused by input nodes)
+    pub activation: Activation,
+}
+
+/// A weighted connection between two nodes of a [`Genome`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectionGene {
+    /// Identifies the connection across every genome sharing an [`InnovationTracker`]
+    pub innovation: usize,
+    /// The id of the node the connection reads from
+    pub from: usize,
+    /// The id of the node the connection adds to
+    pub to: usize,
+    pub weight: f32,
+    /// Disabled connections are ignored, but kept for crossover
+    pub enabled: bool,
+}
+
+/// Hands out node ids and innovation numbers, so that the same structural
+/// mutation in different genomes gets the same numbers.
+/// A single tracker should be shared by every genome in a population.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InnovationTracker {
+    next_node: usize,
+    next_innovation: usize,
+    /// The innovation number of each connection, by its nodes
+    connections: HashMap<(usize, usize), usize>,
+    /// The node created by splitting each connection, by its innovation number
+    splits: HashMap<usize, usize>,
+}
+
+impl InnovationTracker {
+    /// Creates a tracker for genomes with the given number of inputs and outputs,
+    /// which take the first node ids
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        return Self {next_node: inputs + outputs, ..Default::default()};
+    }
+
+    /// The innovation number of a connection between two nodes
+    pub fn connection(&mut self, from: usize, to: usize) -> usize {
+        let next = &mut self.next_innovation;
+        return *self.connections.entry((from, to)).or_insert_with(|| {
+            *next += 1;
+            *next - 1
+        });
+    }
+
+    /// The id of the node created by splitting a connection
+    pub fn split(&mut self, innovation: usize) -> usize {
+        let next = &mut self.next_node;
+        return *self.splits.entry(innovation).or_insert_with(|| {
+            *next += 1;
+            *next - 1
+        });
+    }
+}
+
+/// The chance of each kind of mutation made by [`Genome::mutate`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MutationRates {
+    /// Scaler for the randomness added to every weight and bias
+    pub weight_intensity: f32,
+    /// The chance of connecting two unconnected nodes
+    pub add_connection: f32,
+    /// The chance of splitting a connection with a new node
+    pub add_node: f32,
+    /// The chance of enabling or disabling a connection
+    pub toggle_connection: f32,
+}
+
+impl Default for MutationRates {
+    fn default() -> Self {
+        return Self {
+            weight_intensity: 1.0,
+            add_connection: 0.05,
+            add_node: 0.03,
+            toggle_connection: 0.01,
+        };
+    }
+}
+
+/// The weights of each term of [`Genome::compatibility`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Compatibility {
+    /// Weight of genes beyond the other genome's newest innovation
+    pub excess: f32,
+    /// Weight of unmatched genes within the other genome's innovations
+    pub disjoint: f32,
+    /// Weight of the mean weight difference of matching genes
+    pub weight: f32,
+}
+
+impl Default for Compatibility {
+    fn default() -> Self {
+        return Self {excess: 1.0, disjoint: 1.0, weight: 0.4};
+    }
+}
+
+/// A feed-forward network described by node and connection genes
+///
+/// The rng is a [`StdRng`] by default, but can be any [`NetworkRng`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Genome<R: NetworkRng = StdRng> {
+    pub rng: R,
+    pub inputs: usize,
+    pub outputs: usize,
+    /// The input nodes, then the output nodes, then any hidden nodes
+    pub nodes: Vec<NodeGene>,
+    /// Every connection, ordered by innovation number
+    pub connections: Vec<ConnectionGene>,
+}
+
+// Constructors without an rng type use StdRng
+impl Genome {
+    /// Creates a genome with every input connected to every output,
+    /// with zeros for all weights,
+    /// using a random seed for random number generation.
+    /// * inputs - The number of inputs
+    /// * outputs - The number of outputs
+    /// * tracker - The innovation tracker shared by the population
+    pub fn new(inputs: usize, outputs: usize, tracker: &mut InnovationTracker) -> Self {
+        return Self::new_deterministic(rand::random(), inputs, outputs, tracker);
+    }
+
+    /// Creates a genome like [`Genome::new`],
+    /// using a specific seed for random number generation.
+    /// * seed - The seed for random number generation
+    /// * inputs - The number of inputs
+    /// * outputs - The number of outputs
+    /// * tracker - The innovation tracker shared by the population
+    pub fn new_deterministic(
+        seed: u64,
+        inputs: usize,
+        outputs: usize,
+        tracker: &mut InnovationTracker,
+    ) -> Self {
+        return Self::with_seed(seed, inputs, outputs, tracker);
+    }
+}
+
+impl<R: NetworkRng> Genome<R> {
+    /// Creates a genome like [`Genome::new_deterministic`], with any [`NetworkRng`]
+    /// * seed - The seed for random number generation
+    /// * inputs - The number of inputs
+    /// * outputs - The number of outputs
+    /// * tracker - The innovation tracker shared by the population
+    pub fn with_seed(
+        seed: u64,
+        inputs: usize,
+        outputs: usize,
+        tracker: &mut InnovationTracker,
+    ) -> Self {
+        let node = |id, kind, activation| NodeGene {id, kind, bias: 0., activation};
+        let mut nodes: Vec<NodeGene> =
+            (0..inputs).map(|id| node(id, NodeKind::Input, Activation::Identity)).collect();
+        for id in inputs..inputs + outputs {
+            nodes.push(node(id, NodeKind::Output, Activation::Identity));
+        }
+        let mut connections = Vec::new();
+        for to in inputs..inputs + outputs {
+            for from in 0..inputs {
+                connections.push(ConnectionGene {
+                    innovation: tracker.connection(from, to),
+                    from,
+                    to,
+                    weight: 0.,
+                    enabled: true,
+                });
+            }
+        }
+        connections.sort_by_key(|x| x.innovation);
+        let rng = R::seed_from_u64(seed);
+        return Self {rng, inputs, outputs, nodes, connections};
+    }
+
+    /// Creates a random variant of this genome, changing only its weights
+    /// * intensity - Scaler for the added randomness
+    ///
+    /// Randomness is applied to the weight of each connection,
+    /// and to the bias of each non-input node. As with
+    /// [`crate::VAID::create_variant`], the intensity is scaled down
+    /// by the number of connections before being applied.
+    pub fn create_variant(&mut self, intensity: f32) -> Self {
+        let mut result = self.clone();
+        let s_intensity = intensity / (1 + self.connections.len()) as f32;
+        for connection in &mut result.connections {
+            connection.weight += s_intensity * infinite_map(self.rng.gen::<f32>());
+        }
+        for node in result.nodes.iter_mut().filter(|x| x.kind != NodeKind::Input) {
+            node.bias += s_intensity * infinite_map(self.rng.gen::<f32>());
+        }
+        return result;
+    }
+
+    /// Creates a mutated copy of this genome. Its weights are always changed,
+    /// as by [`Genome::create_variant`], and each structural mutation
+    /// happens with the chance given in the rates.
+    /// * tracker - The innovation tracker shared by the population
+    /// * rates - The chance of each kind of mutation
+    ///
+    /// see also:
+    ///  * [`Genome::add_connection`]
+    ///  * [`Genome::add_node`]
+    ///  * [`Genome::toggle_connection`]
+    pub fn mutate(&mut self, tracker: &mut InnovationTracker, rates: &MutationRates) -> Self {
+        let mut result = self.create_variant(rates.weight_intensity);
+        if self.rng.gen::<f32>() < rates.add_connection {
+            result.add_connection(tracker);
+        }
+        if self.rng.gen::<f32>() < rates.add_node {
+            result.add_node(tracker);
+        }
+        if self.rng.gen::<f32>() < rates.toggle_connection {
+            result.toggle_connection();
+        }
+        return result;
+    }
+
+    /// Connects two randomly chosen nodes that are not yet connected,
+    /// with a weight of zero, so the network's outputs are unchanged.
+    /// Connections that would form a cycle are never added.
+    /// Returns false if no connection could be found.
+    /// * tracker - The innovation tracker shared by the population
+    pub fn add_connection(&mut self, tracker: &mut InnovationTracker) -> bool {
+        let mut candidates = Vec::new();
+        for from in self.nodes.iter().filter(|x| x.kind != NodeKind::Output) {
+            for to in self.nodes.iter().filter(|x| x.kind != NodeKind::Input) {
+                let exists = self.connections.iter().any(|x| x.from == from.id && x.to == to.id);
+                if from.id != to.id && !exists && !self.reaches(to.id, from.id) {
+                    candidates.push((from.id, to.id));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return false;
+        }
+        let (from, to) = candidates[self.rng.gen_range(0..candidates.len())];
+        let innovation = tracker.connection(from, to);
+        self.insert_connection(ConnectionGene {innovation, from, to, weight: 0., enabled: true});
+        return true;
+    }
+
+    /// Splits a randomly chosen enabled connection with a new hidden node.
+    /// The old connection is disabled, the connection into the new node gets
+    /// a weight of one, and the connection out of it gets the old weight.
+    /// Returns false if there is no enabled connection to split.
+    /// * tracker - The innovation tracker shared by the population
+    pub fn add_node(&mut self, tracker: &mut InnovationTracker) -> bool {
+        let enabled: Vec<usize> = (0..self.connections.len())
+            .filter(|&i| self.connections[i].enabled)
+            .collect();
+        if enabled.is_empty() {
+            return false;
+        }
+        let index = enabled[self.rng.gen_range(0..enabled.len())];
+        let split = self.connections[index];
+        let id = tracker.split(split.innovation);
+        if self.nodes.iter().any(|x| x.id == id) {
+            // This genome already split this connection, and has since re-enabled it.
+            // The connection is left enabled, so the genome is unchanged.
+            return false;
+        }
+        self.connections[index].enabled = false;
+        self.nodes.push(NodeGene {id, kind: NodeKind::Hidden, bias: 0., activation: Activation::ReLU});
+        self.insert_connection(ConnectionGene {
+            innovation: tracker.connection(split.from, id),
+            from: split.from,
+            to: id,
+            weight: 1.,
+            enabled: true,
+        });
+        self.insert_connection(ConnectionGene {
+            innovation: tracker.connection(id, split.to),
+            from: id,
+            to: split.to,
+            weight: split.weight,
+            enabled: true,
+        });
+        return true;
+    }
+
+    /// Enables or disables a randomly chosen connection.
+    /// Returns false if there are no connections.
+    pub fn toggle_connection(&mut self) -> bool {
+        if self.connections.is_empty() {
+            return false;
+        }
+        let index = self.rng.gen_range(0..self.connections.len());
+        self.connections[index].enabled = !self.connections[index].enabled;
+        return true;
+    }
+
+    /// Creates a child of this genome and another.
+    /// This genome is treated as the fitter parent, so the child has its
+    /// structure: connections that only one parent has are taken from this
+    /// genome, and matching connections (and nodes) are taken from either
+    /// parent at random. A connection disabled in either parent has a 75%
+    /// chance of being disabled in the child.
+    /// The child's random number generator is cloned from this genome,
+    /// and this genome's random number generator is used for the crossover.
+    /// * other - The other, less fit, parent
+    pub fn crossover(&mut self, other: &Self) -> Self {
+        let mut result = self.clone();
+        let other_connections: HashMap<usize, &ConnectionGene> =
+            other.connections.iter().map(|x| (x.innovation, x)).collect();
+        for connection in &mut result.connections {
+            if let Some(matching) = other_connections.get(&connection.innovation) {
+                if self.rng.gen::<bool>() {
+                    connection.weight = matching.weight;
+                }
+                let disabled = !connection.enabled || !matching.enabled;
+                connection.enabled = !(disabled && self.rng.gen::<f32>() < 0.75);
+            }
+        }
+        let other_nodes: HashMap<usize, &NodeGene> = other.nodes.iter().map(|x| (x.id, x)).collect();
+        for node in &mut result.nodes {
+            if let Some(matching) = other_nodes.get(&node.id) {
+                if self.rng.gen::<bool>() {
+                    node.bias = matching.bias;
+                    node.activation = matching.activation;
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Measures how different two genomes are, for speciation
+    /// * other - The genome to compare with
+    /// * coefficients - The weight of each kind of difference
+    ///
+    /// The distance is `excess * E / N + disjoint * D / N + weight * W`,
+    /// where E and D are the number of excess and disjoint connections,
+    /// N is the number of connections in the larger genome,
+    /// and W is the mean weight difference of matching connections.
+    pub fn compatibility(&self, other: &Self, coefficients: &Compatibility) -> f32 {
+        let last = |g: &Self| g.connections.iter().map(|x| x.innovation).max();
+        let (self_last, other_last) = (last(self), last(other));
+        let other_connections: HashMap<usize, f32> =
+            other.connections.iter().map(|x| (x.innovation, x.weight)).collect();
+        let mut matching = 0;
+        let mut weight_difference = 0.;
+        let mut excess = 0;
+        let mut disjoint = 0;
+        for connection in &self.connections {
+            match other_connections.get(&connection.innovation) {
+                Some(weight) => {
+                    matching += 1;
+                    weight_difference += (connection.weight - weight).abs();
+                }
+                None if Some(connection.innovation) > other_last => excess += 1,
+                None => disjoint += 1,
+            }
+        }
+        for connection in &other.connections {
+            if !self.connections.iter().any(|x| x.innovation == connection.innovation) {
+                if Some(connection.innovation) > self_last {
+                    excess += 1;
+                } else {
+                    disjoint += 1;
+                }
+            }
+        }
+        let genes = self.connections.len().max(other.connections.len()).max(1) as f32;
+        let mean_difference = if matching > 0 { weight_difference / matching as f32 } else { 0. };
+        return coefficients.excess * excess as f32 / genes
+            + coefficients.disjoint * disjoint as f32 / genes
+            + coefficients.weight * mean_difference;
+    }
+
+    /// Runs an input slice through the network to get an output
+    /// * inputs - The inputs
+    ///
+    /// Panics if the number of inputs is not [`Genome::inputs`].
+    ///
+    /// see also:
+    ///  * [`Genome::process_slice_transparent`]
+    pub fn process_slice(&self, inputs: &[f32]) -> Vec<f32> {
+        let (_, values) = self.evaluate(inputs);
+        let outputs = self.nodes.iter().enumerate().filter(|(_, x)| x.kind == NodeKind::Output);
+        return outputs.map(|(i, _)| values[i]).collect();
+    }
+
+    /// Runs an input slice through the network to get an output
+    /// returning the value of all the nodes: input, hidden, and output.
+    ///
+    /// Note: as with [`crate::VAID::process_slice_transparent`],
+    /// the value of hidden nodes is supplied *before* their activation.
+    /// The output nodes are supplied after their activation.
+    /// Hidden nodes are in the order of [`Genome::nodes`].
+    /// * inputs - The inputs
+    pub fn process_slice_transparent(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
+        let (sums, values) = self.evaluate(inputs);
+        let of_kind = |kind: NodeKind, source: &[f32]| -> Vec<f32> {
+            let nodes = self.nodes.iter().enumerate().filter(|(_, x)| x.kind == kind);
+            return nodes.map(|(i, _)| source[i]).collect();
+        };
+        return vec![
+            of_kind(NodeKind::Input, &values),
+            of_kind(NodeKind::Hidden, &sums),
+            of_kind(NodeKind::Output, &values),
+        ];
+    }
+
+    /// Calculates every node, in the order of [`Genome::nodes`].
+    /// Returns the weighted sum (plus bias) of each node,
+    /// and its value after its activation.
+    fn evaluate(&self, inputs: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        assert_eq!(inputs.len(), self.inputs, "wrong number of inputs");
+        let index: HashMap<usize, usize> =
+            self.nodes.iter().enumerate().map(|(i, x)| (x.id, i)).collect();
+        let mut sums: Vec<f32> = self.nodes.iter().map(|x| x.bias).collect();
+        let mut values = vec![0.; self.nodes.len()];
+        let mut input = inputs.iter();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.kind == NodeKind::Input {
+                sums[i] = *input.next().unwrap();
+            }
+        }
+        for i in self.order(&index) {
+            let node = &self.nodes[i];
+            values[i] = match node.kind {
+                NodeKind::Input => sums[i],
+                _ => node.activation.apply(sums[i]),
+            };
+            for connection in self.connections.iter().filter(|x| x.enabled && x.from == node.id) {
+                sums[index[&connection.to]] += connection.weight * values[i];
+            }
+        }
+        return (sums, values);
+    }
+
+    /// The indices of every node, ordered so that each node comes after
+    /// every node connected to it
+    fn order(&self, index: &HashMap<usize, usize>) -> Vec<usize> {
+        let mut incoming = vec![0; self.nodes.len()];
+        for connection in self.connections.iter().filter(|x| x.enabled) {
+            incoming[index[&connection.to]] += 1;
+        }
+        let mut ready: Vec<usize> = (0..self.nodes.len()).filter(|&i| incoming[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(i) = ready.pop() {
+            order.push(i);
+            let id = self.nodes[i].id;
+            for connection in self.connections.iter().filter(|x| x.enabled && x.from == id) {
+                let to = index[&connection.to];
+                incoming[to] -= 1;
+                if incoming[to] == 0 {
+                    ready.push(to);
+                }
+            }
+        }
+        return order;
+    }
+
+    /// Checks if there is a path of connections (enabled or not) between two nodes
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut stack = vec![from];
+        let mut visited = vec![from];
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            for connection in self.connections.iter().filter(|x| x.from == id) {
+                if !visited.contains(&connection.to) {
+                    visited.push(connection.to);
+                    stack.push(connection.to);
+                }
+            }
+        }
+        return false;
+    }
+
+    /// Adds a connection, keeping the connections ordered by innovation number
+    fn insert_connection(&mut self, connection: ConnectionGene) {
+        let position = self.connections.partition_point(|x| x.innovation < connection.innovation);
+        self.connections.insert(position, connection);
+    }
+}
+
+/// Groups genomes into species. Each genome joins the first species whose
+/// first member is within the threshold of it, or starts a new species.
+/// Returns the indices of the genomes in each species.
+/// * genomes - The genomes to group
+/// * threshold - The largest [`Genome::compatibility`] within a species
+/// * coefficients - The weight of each kind of difference
+pub fn speciate<R: NetworkRng>(genomes: &[Genome<R>], threshold: f32, coefficients: &Compatibility) -> Vec<Vec<usize>> {
+    let mut species: Vec<Vec<usize>> = Vec::new();
+    for (i, genome) in genomes.iter().enumerate() {
+        let existing = species
+            .iter_mut()
+            .find(|x| genomes[x[0]].compatibility(genome, coefficients) <= threshold);
+        match existing {
+            Some(members) => members.push(i),
+            None => species.push(vec![i]),
+        }
+    }
+    return species;
+}
+
+/// Divides each genome's fitness by the size of its species,
+/// so that large species do not crowd out new structures
+/// * species - The indices of the genomes in each species, from [`speciate`]
+/// * fitness - The fitness of each genome
+pub fn shared_fitness(species: &[Vec<usize>], fitness: &[f32]) -> Vec<f32> {
+    let mut result = fitness.to_vec();
+    for members in species {
+        for &i in members {
+            result[i] = fitness[i] / members.len() as f32;
+        }
+    }
+    return result;
+}
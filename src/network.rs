@@ -0,0 +1,96 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::format::RngState;
+use crate::Crossover;
+
+/// The random number generator a network uses for mutation and crossover,
+/// or an evolution driver such as [`crate::Population`] uses for selection.
+/// Any seedable rng can be used, such as a fast PCG or Xoshiro,
+/// so that runs are deterministic for a given seed.
+///
+/// Networks and evolution drivers use a [`rand::rngs::StdRng`] by default.
+/// To write checkpoints, use a [`CheckpointRng`] such as [`ChaCha12Rng`].
+pub trait NetworkRng: RngCore + SeedableRng + Clone + 'static {}
+
+impl<R: RngCore + SeedableRng + Clone + 'static> NetworkRng for R {}
+
+/// A [`NetworkRng`] whose full state can be stored in a checkpoint, so that
+/// a resumed network continues the exact same sequence of random numbers.
+/// Implemented for [`ChaCha12Rng`], which a network opts into with its rng
+/// type parameter, such as `VAID<f32, ChaCha12Rng>`.
+///
+/// see also:
+///  * [`crate::VAID::write_checkpoint`]
+///  * [`crate::VAID::resume`]
+pub trait CheckpointRng: NetworkRng {
+    /// Captures the full state of this rng
+    fn state(&self) -> RngState;
+
+    /// Creates an rng in the given state
+    fn from_state(state: &RngState) -> Self;
+}
+
+impl CheckpointRng for ChaCha12Rng {
+    fn state(&self) -> RngState {
+        return RngState {seed: self.get_seed(), stream: self.get_stream(), word_pos: self.get_word_pos()};
+    }
+
+    fn from_state(state: &RngState) -> Self {
+        let mut rng = ChaCha12Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(state.word_pos);
+        return rng;
+    }
+}
+
+/// Operations shared by [`crate::VAI`] and [`crate::VAID`],
+/// so that they can be used interchangeably by generic code such as
+/// [`crate::Population`].
+pub trait Network: Clone {
+    /// The type of the network's weights, inputs, and outputs.
+    /// See [`crate::Scalar`].
+    type Scalar: crate::Scalar;
+
+    /// Runs an input slice through the neural network to get an output
+    fn process_slice(&self, inputs: &[Self::Scalar]) -> Vec<Self::Scalar>;
+
+    /// Creates a random variant of this network.
+    /// See [`crate::VAI::create_variant`].
+    fn create_variant(&mut self, intensity: f32) -> Self;
+
+    /// Creates a random variant of this network that only changes one layer.
+    /// See [`crate::VAI::create_layer_variant`].
+    fn create_layer_variant(&mut self, intensity: f32) -> Self;
+
+    /// Creates a child of this network and another.
+    /// See [`crate::VAI::crossover_with`].
+    fn crossover_with(&mut self, other: &Self, mode: Crossover) -> Self;
+}
+
+/// Every weight and bias of a network as one flat parameter vector,
+/// for optimizers that don't care about the network's structure, such as
+/// [`crate::es::EvolutionStrategy`].
+///
+/// Parameters are ordered layer by layer, starting at the input layer.
+/// Each layer has its connection weights (in column-major order)
+/// followed by its biases.
+pub trait Parameters: Network {
+    /// The number of parameters of each layer
+    fn layer_parameter_counts(&self) -> Vec<usize>;
+
+    /// The total number of parameters
+    fn parameter_count(&self) -> usize {
+        return self.layer_parameter_counts().iter().sum();
+    }
+
+    /// Copies every parameter into a vector
+    fn parameters(&self) -> Vec<Self::Scalar>;
+
+    /// Replaces every parameter
+    /// * parameters - The new parameters, in the order of [`Parameters::parameters`]
+    ///
+    /// # Panics
+    /// If the number of parameters does not match [`Parameters::parameter_count`]
+    fn set_parameters(&mut self, parameters: &[Self::Scalar]);
+}
@@ -0,0 +1,343 @@
+#![allow(clippy::needless_return)]
+
+use std::io::{self, Write};
+
+extern crate nalgebra as na;
+use na::{DMatrix, DVector};
+
+use crate::backprop::{Gradients, Loss, Trainable};
+use crate::format::LineReader;
+use crate::vaid::{read_layer_matrix, write_matrix};
+use crate::VaiError;
+
+/// The rule an [`Optimizer`] uses to turn gradients into weight updates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    /// Stochastic gradient descent. A momentum of 0 disables momentum.
+    Sgd { momentum: f32 },
+    /// Divides each gradient by a running average of its magnitude
+    RmsProp { decay: f32, epsilon: f32 },
+    /// Adaptive moment estimation
+    Adam { beta1: f32, beta2: f32, epsilon: f32 },
+}
+
+/// How the learning rate of an [`Optimizer`] changes as it takes steps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Schedule {
+    /// The learning rate never changes
+    #[default]
+    Constant,
+    /// The learning rate is multiplied by `factor` every `step_size` steps
+    Step { step_size: usize, factor: f32 },
+    /// The learning rate is multiplied by `factor` every step
+    Exponential { factor: f32 },
+    /// The learning rate follows half a cosine wave down to `min_rate`
+    /// over `period` steps, then stays at `min_rate`
+    Cosine { period: usize, min_rate: f32 },
+}
+
+/// Trains a network by gradient descent, keeping state (such as momentum)
+/// between steps.
+///
+/// The moment buffers are shaped like the gradients of the network
+/// being trained, and are created on the first step. An optimizer can be
+/// written after a network, and read back later, so that training can be
+/// resumed without losing its momentum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Optimizer {
+    pub method: Method,
+    /// The learning rate before it is adjusted by the schedule
+    pub learning_rate: f32,
+    pub schedule: Schedule,
+    /// Each step, connection weights (but not biases) are shrunk by
+    /// the learning rate multiplied by the weight decay
+    pub weight_decay: f32,
+    /// The number of steps taken
+    pub steps: usize,
+    /// The momentum (for SGD), or the first moment (for Adam)
+    pub first_moments: Option<Gradients>,
+    /// The second moment (for RMSProp and Adam)
+    pub second_moments: Option<Gradients>,
+}
+
+impl Optimizer {
+    /// Creates an optimizer with a constant learning rate, and no weight decay
+    /// * method - The update rule
+    /// * learning_rate - Scaler for each update
+    pub fn new(method: Method, learning_rate: f32) -> Self {
+        Self {
+            method,
+            learning_rate,
+            schedule: Schedule::Constant,
+            weight_decay: 0.,
+            steps: 0,
+            first_moments: None,
+            second_moments: None,
+        }
+    }
+
+    /// Creates an SGD optimizer
+    /// * learning_rate - Scaler for each update
+    /// * momentum - How much of the previous update is carried into the next
+    pub fn sgd(learning_rate: f32, momentum: f32) -> Self {
+        Self::new(Method::Sgd { momentum }, learning_rate)
+    }
+
+    /// Creates an RMSProp optimizer with a decay of 0.9
+    /// * learning_rate - Scaler for each update
+    pub fn rms_prop(learning_rate: f32) -> Self {
+        Self::new(Method::RmsProp { decay: 0.9, epsilon: 1e-7 }, learning_rate)
+    }
+
+    /// Creates an Adam optimizer with betas of 0.9 and 0.999
+    /// * learning_rate - Scaler for each update
+    pub fn adam(learning_rate: f32) -> Self {
+        Self::new(
+            Method::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-7 },
+            learning_rate,
+        )
+    }
+
+    /// The learning rate for the next step, as adjusted by the schedule
+    pub fn current_learning_rate(&self) -> f32 {
+        let steps = self.steps as f32;
+        match self.schedule {
+            Schedule::Constant => self.learning_rate,
+            Schedule::Step { step_size, factor } => {
+                self.learning_rate * factor.powi((self.steps / step_size.max(1)) as i32)
+            }
+            Schedule::Exponential { factor } => self.learning_rate * factor.powf(steps),
+            Schedule::Cosine { period, min_rate } => {
+                let progress = (steps / period.max(1) as f32).min(1.);
+                let cosine = 0.5 * (1. + (std::f32::consts::PI * progress).cos());
+                min_rate + (self.learning_rate - min_rate) * cosine
+            }
+        }
+    }
+
+    /// Updates a network using gradients calculated for it
+    /// * network - The network to update
+    /// * gradients - The gradients, as calculated by [`Trainable::backward`]
+    pub fn step(&mut self, network: &mut impl Trainable, gradients: &Gradients) {
+        let rate = self.current_learning_rate();
+        self.steps += 1;
+        let (use_first, use_second) = match self.method {
+            Method::Sgd { momentum } => (momentum != 0., false),
+            Method::RmsProp { .. } => (false, true),
+            Method::Adam { .. } => (true, true),
+        };
+        let prepare = |moments: &mut Option<Gradients>, used: bool| {
+            match moments {
+                Some(m) if !used || !m.same_shape(gradients) => *moments = None,
+                _ => {}
+            }
+            if used && moments.is_none() {
+                *moments = Some(gradients.zeros_like());
+            }
+        };
+        prepare(&mut self.first_moments, use_first);
+        prepare(&mut self.second_moments, use_second);
+
+        let mut first: Vec<&mut [f32]> = match &mut self.first_moments {
+            Some(m) => m.slices_mut().collect(),
+            None => Vec::new(),
+        };
+        let mut second: Vec<&mut [f32]> = match &mut self.second_moments {
+            Some(m) => m.slices_mut().collect(),
+            None => Vec::new(),
+        };
+        let t = self.steps as i32;
+        let decayed = gradients.connections.len();
+        let parameters = network.parameters_mut();
+        for (i, (weights, gradient)) in parameters.into_iter().zip(gradients.slices()).enumerate() {
+            for j in 0..weights.len() {
+                let g = gradient[j];
+                let update = match self.method {
+                    Method::Sgd { momentum } => {
+                        if let Some(m) = first.get_mut(i) {
+                            m[j] = momentum * m[j] + g;
+                            m[j]
+                        } else {
+                            g
+                        }
+                    }
+                    Method::RmsProp { decay, epsilon } => {
+                        let v = &mut second[i][j];
+                        *v = decay * *v + (1. - decay) * g * g;
+                        g / (v.sqrt() + epsilon)
+                    }
+                    Method::Adam { beta1, beta2, epsilon } => {
+                        let m = &mut first[i][j];
+                        *m = beta1 * *m + (1. - beta1) * g;
+                        let m_hat = *m / (1. - beta1.powi(t));
+                        let v = &mut second[i][j];
+                        *v = beta2 * *v + (1. - beta2) * g * g;
+                        let v_hat = *v / (1. - beta2.powi(t));
+                        m_hat / (v_hat.sqrt() + epsilon)
+                    }
+                };
+                let decay = if i < decayed { self.weight_decay * weights[j] } else { 0. };
+                weights[j] -= rate * (update + decay);
+            }
+        }
+    }
+
+    /// Calculates gradients for a batch, and uses them to update a network
+    /// * network - The network to update
+    /// * inputs - The inputs, with one sample per column
+    /// * targets - The desired outputs, with one sample per column
+    /// * loss - How the error is measured
+    ///
+    /// Returns the loss from before the step.
+    pub fn train_step(
+        &mut self,
+        network: &mut impl Trainable,
+        inputs: &DMatrix<f32>,
+        targets: &DMatrix<f32>,
+        loss: Loss,
+    ) -> f32 {
+        let cache = network.forward(inputs);
+        let (loss, gradients) = network.backward(&cache, targets, loss);
+        self.step(network, &gradients);
+        return loss;
+    }
+
+    /// Writes the optimizer's settings and state to a file,
+    /// with moment buffers written by [`write_matrix`]
+    /// * file - The file to write to
+    pub fn write(&self, file: &mut impl Write) -> Result<(), VaiError> {
+        match self.method {
+            Method::Sgd { momentum } => writeln!(file, "optimizer sgd {}", momentum)?,
+            Method::RmsProp { decay, epsilon } => {
+                writeln!(file, "optimizer rmsprop {} {}", decay, epsilon)?
+            }
+            Method::Adam { beta1, beta2, epsilon } => {
+                writeln!(file, "optimizer adam {} {} {}", beta1, beta2, epsilon)?
+            }
+        }
+        writeln!(file, "learning_rate {}", self.learning_rate)?;
+        match self.schedule {
+            Schedule::Constant => writeln!(file, "schedule constant")?,
+            Schedule::Step { step_size, factor } => {
+                writeln!(file, "schedule step {} {}", step_size, factor)?
+            }
+            Schedule::Exponential { factor } => writeln!(file, "schedule exponential {}", factor)?,
+            Schedule::Cosine { period, min_rate } => {
+                writeln!(file, "schedule cosine {} {}", period, min_rate)?
+            }
+        }
+        writeln!(file, "weight_decay {}", self.weight_decay)?;
+        writeln!(file, "steps {}", self.steps)?;
+        for (name, moments) in [("first", &self.first_moments), ("second", &self.second_moments)] {
+            match moments {
+                None => writeln!(file, "{}_moments 0", name)?,
+                Some(moments) => {
+                    writeln!(file, "{}_moments {}", name, moments.connections.len())?;
+                    for (mat, bias) in moments.connections.iter().zip(&moments.biases) {
+                        write_matrix(mat, file)?;
+                        write_matrix(&DMatrix::from_column_slice(bias.len(), 1, bias.as_slice()), file)?;
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Reads an optimizer from lines of a file, as written by
+    /// [`Optimizer::write`]
+    /// * lines - A line iterator from which to read the optimizer
+    ///   (generally provided by BufReader::new(file).lines())
+    pub fn read(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Self, VaiError> {
+        let mut lines = LineReader::new(lines);
+        let lines = &mut lines;
+        let method = {
+            let line = read_line(lines, "optimizer")?;
+            match line.split_whitespace().nth(1) {
+                Some("sgd") => Method::Sgd { momentum: parse(lines, &line, 1)? },
+                Some("rmsprop") => Method::RmsProp {
+                    decay: parse(lines, &line, 1)?,
+                    epsilon: parse(lines, &line, 2)?,
+                },
+                Some("adam") => Method::Adam {
+                    beta1: parse(lines, &line, 1)?,
+                    beta2: parse(lines, &line, 2)?,
+                    epsilon: parse(lines, &line, 3)?,
+                },
+                Some(token) => return Err(lines.parse_error(&line, token)),
+                None => return Err(VaiError::Format("Missing optimizer".to_owned())),
+            }
+        };
+        let line = read_line(lines, "learning_rate")?;
+        let learning_rate = parse(lines, &line, 0)?;
+        let schedule = {
+            let line = read_line(lines, "schedule")?;
+            match line.split_whitespace().nth(1) {
+                Some("constant") => Schedule::Constant,
+                Some("step") => Schedule::Step {
+                    step_size: parse(lines, &line, 1)?,
+                    factor: parse(lines, &line, 2)?,
+                },
+                Some("exponential") => Schedule::Exponential { factor: parse(lines, &line, 1)? },
+                Some("cosine") => Schedule::Cosine {
+                    period: parse(lines, &line, 1)?,
+                    min_rate: parse(lines, &line, 2)?,
+                },
+                Some(token) => return Err(lines.parse_error(&line, token)),
+                None => return Err(VaiError::Format("Missing schedule".to_owned())),
+            }
+        };
+        let line = read_line(lines, "weight_decay")?;
+        let weight_decay = parse(lines, &line, 0)?;
+        let line = read_line(lines, "steps")?;
+        let steps = parse(lines, &line, 0)?;
+        let mut read_moments = |name: &str| -> Result<Option<Gradients>, VaiError> {
+            let line = read_line(lines, name)?;
+            let layers: usize = parse(lines, &line, 0)?;
+            if layers == 0 {
+                return Ok(None);
+            }
+            let mut moments = Gradients { connections: Vec::new(), biases: Vec::new() };
+            for i in 0..layers {
+                moments.connections.push(read_layer_matrix(lines, i)?);
+                let bias = read_layer_matrix(lines, i)?;
+                moments.biases.push(DVector::from_column_slice(bias.as_slice()));
+            }
+            return Ok(Some(moments));
+        };
+        let first_moments = read_moments("first_moments")?;
+        let second_moments = read_moments("second_moments")?;
+        return Ok(Self {
+            method,
+            learning_rate,
+            schedule,
+            weight_decay,
+            steps,
+            first_moments,
+            second_moments,
+        });
+    }
+}
+
+/// Reads the next non-empty line, which must start with the given keyword
+fn read_line<L: Iterator<Item = io::Result<String>>>(
+    lines: &mut LineReader<L>,
+    keyword: &str,
+) -> Result<String, VaiError> {
+    let line = lines.expect_nonempty_line()?;
+    match line.split_whitespace().next() {
+        Some(x) if x == keyword => return Ok(line),
+        _ => return Err(VaiError::Format(format!("Expected {}", keyword))),
+    }
+}
+
+/// Parses one of the values that follow the keyword of a line read by [`read_line`]
+fn parse<T: std::str::FromStr, L: Iterator<Item = io::Result<String>>>(
+    lines: &LineReader<L>,
+    line: &str,
+    index: usize,
+) -> Result<T, VaiError> {
+    match line.split_whitespace().nth(index + 1) {
+        Some(token) => return lines.parse(line, token),
+        None => return Err(VaiError::Format(format!("Missing value in: {}", line.trim()))),
+    }
+}
@@ -0,0 +1,76 @@
+#![allow(clippy::needless_return)]
+
+//! Parallel evaluation of many networks, enabled by the `parallel` feature.
+//!
+//! Fitness functions often need randomness (eg. to choose test cases).
+//! Each individual gets its own rng, seeded from a master seed and the
+//! individual's index, so scores do not depend on how the individuals
+//! are scheduled between threads. The rng can be any [`NetworkRng`],
+//! chosen by the type of the fitness function's rng parameter.
+
+use rayon::prelude::*;
+
+use crate::NetworkRng;
+
+/// Derives the seed of an individual's rng from a master seed.
+/// Nearby indices (and master seeds) give unrelated seeds.
+/// * seed - The master seed
+/// * index - The index of the individual
+pub fn stream_seed(seed: u64, index: usize) -> u64 {
+    // The output function of SplitMix64
+    let mut z = seed.wrapping_add((index as u64).wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
+
+/// Creates the rng of an individual, as seeded by [`stream_seed`]
+/// * seed - The master seed
+/// * index - The index of the individual
+pub fn stream<G: NetworkRng>(seed: u64, index: usize) -> G {
+    return G::seed_from_u64(stream_seed(seed, index));
+}
+
+/// Scores every individual in parallel
+/// * individuals - The networks to score
+/// * seed - The master seed, from which each individual's rng is derived
+/// * fitness - Scores an individual, using its own rng
+///
+/// Returns the score of each individual, in order.
+///
+/// see also:
+///  * [`evaluate_mut`]
+///  * [`crate::Population::step_parallel`]
+pub fn evaluate<N, F, G>(individuals: &[N], seed: u64, fitness: F) -> Vec<f32>
+where
+    N: Sync,
+    F: Fn(&N, &mut G) -> f32 + Sync,
+    G: NetworkRng,
+{
+    return individuals
+        .par_iter()
+        .enumerate()
+        .map(|(i, individual)| fitness(individual, &mut stream::<G>(seed, i)))
+        .collect();
+}
+
+/// Scores every individual in parallel, like [`evaluate`], for fitness
+/// functions that change the individuals (eg. the state of a [`crate::VAIR`],
+/// or the rng of a [`crate::VAI`] that is mutated during evaluation)
+/// * individuals - The networks to score
+/// * seed - The master seed, from which each individual's rng is derived
+/// * fitness - Scores an individual, using its own rng
+///
+/// Returns the score of each individual, in order.
+pub fn evaluate_mut<N, F, G>(individuals: &mut [N], seed: u64, fitness: F) -> Vec<f32>
+where
+    N: Send,
+    F: Fn(&mut N, &mut G) -> f32 + Sync,
+    G: NetworkRng,
+{
+    return individuals
+        .par_iter_mut()
+        .enumerate()
+        .map(|(i, individual)| fitness(individual, &mut stream::<G>(seed, i)))
+        .collect();
+}
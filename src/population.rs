@@ -0,0 +1,263 @@
+#![allow(clippy::needless_return)]
+
+extern crate rand;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::{Crossover, Network, NetworkRng};
+
+/// How parents are chosen from a generation, once it has been scored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    /// Parents are chosen uniformly from the given fraction of the
+    /// best individuals (eg. 0.5 chooses from the top half)
+    Truncation(f32),
+    /// Each parent is the best of the given number of randomly chosen
+    /// individuals
+    Tournament(usize),
+    /// Parents are chosen with a probability proportional to how much
+    /// their fitness exceeds the worst fitness in the generation
+    Roulette,
+    /// Parents are chosen with a probability proportional to their rank,
+    /// where the worst individual has a rank of 1
+    Rank,
+}
+
+/// How children are varied from their parents
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mutation {
+    /// Uses [`Network::create_variant`] with the given intensity
+    Variant(f32),
+    /// Uses [`Network::create_layer_variant`] with the given intensity
+    LayerVariant(f32),
+}
+
+/// The fitness of a generation, as scored by a [`Population`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenerationStats {
+    /// The number of generations scored before this one
+    pub generation: usize,
+    pub best: f32,
+    pub mean: f32,
+    pub worst: f32,
+}
+
+/// A group of networks that evolves one generation at a time.
+///
+/// Each generation, every individual is scored by the fitness function
+/// (higher is better), the best `elitism` individuals are kept unchanged,
+/// and the rest of the next generation is made of children of parents
+/// chosen by `selection`. Children are created by `crossover` (if any)
+/// followed by `mutation`.
+///
+/// A NaN fitness is treated as the worst possible fitness.
+///
+/// The rng used for selection is a [`StdRng`] by default,
+/// but can be any [`NetworkRng`].
+pub struct Population<N: Network, F: FnMut(&N) -> f32, G: NetworkRng = StdRng> {
+    pub individuals: Vec<N>,
+    pub fitness: F,
+    pub selection: Selection,
+    pub elitism: usize,
+    pub mutation: Mutation,
+    pub crossover: Option<Crossover>,
+    /// The number of generations that have been scored
+    pub generation: usize,
+    /// Used for selection. Mutation and crossover use the networks' own rngs.
+    pub rng: G,
+    best: Option<(N, f32)>,
+}
+
+// Constructors without an rng type use StdRng
+impl<N: Network, F: FnMut(&N) -> f32> Population<N, F> {
+    /// Creates a population using a random seed for selection.
+    ///
+    /// It defaults to tournament selection of 3, an elitism of 1,
+    /// no crossover, and [`Mutation::Variant`] with an intensity of 1.
+    /// * individuals - The first generation
+    /// * fitness - Scores an individual. Higher is better.
+    pub fn new(individuals: Vec<N>, fitness: F) -> Self {
+        Self::new_deterministic(rand::random(), individuals, fitness)
+    }
+
+    /// Creates a population using a specific seed for selection.
+    ///
+    /// See [`Population::new`] for defaults.
+    /// * individuals - The first generation
+    /// * fitness - Scores an individual. Higher is better.
+    pub fn new_deterministic(seed: u64, individuals: Vec<N>, fitness: F) -> Self {
+        Self::with_seed(seed, individuals, fitness)
+    }
+}
+
+impl<N: Network, F: FnMut(&N) -> f32, G: NetworkRng> Population<N, F, G> {
+    /// Creates a population like [`Population::new_deterministic`],
+    /// with any [`NetworkRng`] for selection
+    /// * seed - The seed for selection
+    /// * individuals - The first generation
+    /// * fitness - Scores an individual. Higher is better.
+    pub fn with_seed(seed: u64, individuals: Vec<N>, fitness: F) -> Self {
+        Self {
+            individuals,
+            fitness,
+            selection: Selection::Tournament(3),
+            elitism: 1,
+            mutation: Mutation::Variant(1.0),
+            crossover: None,
+            generation: 0,
+            rng: G::seed_from_u64(seed),
+            best: None,
+        }
+    }
+
+    /// The best individual scored so far, and its fitness
+    pub fn best(&self) -> Option<(&N, f32)> {
+        return self.best.as_ref().map(|(individual, score)| (individual, *score));
+    }
+
+    /// Scores the current generation, and replaces it with the next one
+    ///
+    /// Returns the statistics of the generation that was scored.
+    pub fn step(&mut self) -> GenerationStats {
+        let scores: Vec<f32> = self.individuals.iter().map(&mut self.fitness).collect();
+        return self.advance(scores);
+    }
+
+    /// Scores the current generation in parallel, and replaces it with the
+    /// next one, as [`Population::step`] does. Enabled by the `parallel` feature.
+    ///
+    /// Returns the statistics of the generation that was scored.
+    ///
+    /// see also:
+    ///  * [`crate::parallel::evaluate`]
+    #[cfg(feature = "parallel")]
+    pub fn step_parallel(&mut self) -> GenerationStats
+    where
+        N: Sync,
+        F: Fn(&N) -> f32 + Sync,
+    {
+        use rayon::prelude::*;
+        let fitness = &self.fitness;
+        let scores: Vec<f32> = self.individuals.par_iter().map(fitness).collect();
+        return self.advance(scores);
+    }
+
+    /// Runs [`Population::step`] for a number of generations
+    ///
+    /// Returns the statistics of each generation.
+    pub fn run(&mut self, generations: usize) -> Vec<GenerationStats> {
+        return (0..generations).map(|_| self.step()).collect();
+    }
+
+    /// Replaces the current generation with the next one
+    /// * scores - The fitness of each individual of the current generation
+    fn advance(&mut self, mut scores: Vec<f32>) -> GenerationStats {
+        for score in scores.iter_mut().filter(|x| x.is_nan()) {
+            *score = f32::NEG_INFINITY;
+        }
+        // Indices of the individuals, from best to worst
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+
+        let stats = GenerationStats {
+            generation: self.generation,
+            best: order.first().map_or(f32::NAN, |i| scores[*i]),
+            mean: scores.iter().sum::<f32>() / scores.len() as f32,
+            worst: order.last().map_or(f32::NAN, |i| scores[*i]),
+        };
+        if let Some(&i) = order.first() {
+            if self.best.as_ref().is_none_or(|best| scores[i] > best.1) {
+                self.best = Some((self.individuals[i].clone(), scores[i]));
+            }
+        }
+
+        let size = self.individuals.len();
+        let mut next = Vec::<N>::with_capacity(size);
+        for &i in order.iter().take(self.elitism) {
+            next.push(self.individuals[i].clone());
+        }
+        while next.len() < size {
+            let a = select(&mut self.rng, self.selection, &order, &scores);
+            let child = match self.crossover {
+                Some(mode) => {
+                    let b = select(&mut self.rng, self.selection, &order, &scores);
+                    let other = self.individuals[b].clone();
+                    let mut child = self.individuals[a].crossover_with(&other, mode);
+                    match self.mutation {
+                        Mutation::Variant(intensity) => child.create_variant(intensity),
+                        Mutation::LayerVariant(intensity) => child.create_layer_variant(intensity),
+                    }
+                }
+                None => {
+                    let parent = &mut self.individuals[a];
+                    match self.mutation {
+                        Mutation::Variant(intensity) => parent.create_variant(intensity),
+                        Mutation::LayerVariant(intensity) => parent.create_layer_variant(intensity),
+                    }
+                }
+            };
+            next.push(child);
+        }
+        self.individuals = next;
+        self.generation += 1;
+        return stats;
+    }
+}
+
+/// Chooses the index of a parent
+/// * order - The indices of the individuals from best to worst
+/// * scores - The fitness of each individual
+fn select<G: Rng + ?Sized>(rng: &mut G, selection: Selection, order: &[usize], scores: &[f32]) -> usize {
+    let size = order.len();
+    match selection {
+        Selection::Truncation(fraction) => {
+            let count = ((fraction * size as f32).ceil() as usize).clamp(1, size);
+            return order[rng.gen_range(0..count)];
+        }
+        Selection::Tournament(rounds) => {
+            let mut winner = rng.gen_range(0..size);
+            for _ in 1..rounds {
+                let challenger = rng.gen_range(0..size);
+                if scores[challenger] > scores[winner] {
+                    winner = challenger;
+                }
+            }
+            return winner;
+        }
+        Selection::Roulette => {
+            let worst = scores
+                .iter()
+                .copied()
+                .filter(|x| x.is_finite())
+                .fold(f32::INFINITY, f32::min);
+            let weights: Vec<f32> = scores
+                .iter()
+                .map(|x| if x.is_finite() { x - worst } else { 0. })
+                .collect();
+            let total: f32 = weights.iter().sum();
+            if total <= 0. || !total.is_finite() {
+                return rng.gen_range(0..size);
+            }
+            let mut target = rng.gen::<f32>() * total;
+            for (i, weight) in weights.iter().enumerate() {
+                if target < *weight {
+                    return i;
+                }
+                target -= weight;
+            }
+            return order[0];
+        }
+        Selection::Rank => {
+            let total = size * (size + 1) / 2;
+            let mut target = rng.gen_range(0..total);
+            for (position, i) in order.iter().enumerate() {
+                let rank = size - position;
+                if target < rank {
+                    return *i;
+                }
+                target -= rank;
+            }
+            return order[0];
+        }
+    }
+}
@@ -0,0 +1,30 @@
+#![allow(clippy::needless_return)]
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+extern crate nalgebra as na;
+
+/// The type of every weight and bias in a network.
+///
+/// Networks use [`f32`] by default, but any [`na::RealField`] that can be
+/// written and parsed as text (such as [`f64`]) can be used instead.
+/// Randomness for mutation is generated as `f32`, and converted.
+pub trait Scalar: na::RealField + Copy + FromStr + Display {
+    /// Converts an f32, such as an activation parameter or a random value
+    fn of_f32(value: f32) -> Self {
+        return Self::of_f64(value as f64);
+    }
+
+    /// Converts an f64, such as a weight read from a binary file
+    fn of_f64(value: f64) -> Self {
+        return Self::from_subset(&value);
+    }
+
+    /// Converts to an f64, such as to write a weight to a binary file
+    fn as_f64(self) -> f64 {
+        return self.to_subset().unwrap_or(f64::NAN);
+    }
+}
+
+impl<T: na::RealField + Copy + FromStr + Display> Scalar for T {}
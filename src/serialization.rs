@@ -0,0 +1,286 @@
+#![allow(clippy::needless_return)]
+
+//! [`Serialize`] and [`Deserialize`] implementations for [`VAI`] and [`VAID`],
+//! enabled by the `serde` feature.
+//!
+//! Both are stored as their layer sizes, followed by each layer's activation,
+//! bias, and connection matrix (in row-major order). The rng is not stored,
+//! so deserialized networks are seeded randomly, as with [`VAID::read`],
+//! unless the network is wrapped in a [`WithRng`].
+//! Weights are stored as the network's [`Scalar`] type.
+
+extern crate nalgebra as na;
+use na::{DMatrix, DVector, Dim, Matrix, RawStorage, SMatrix};
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::format::{checked_size, RngState};
+use crate::{Activation, CheckpointRng, NetworkRng, Scalar, VaiError, VAI, VAID};
+
+/// A layer of a network, as it is serialized
+#[derive(Serialize, Deserialize)]
+struct LayerData<T> {
+    activation: String,
+    bias: Vec<T>,
+    connections: Vec<T>,
+}
+
+/// A network, as it is serialized
+#[derive(Serialize, Deserialize)]
+struct NetworkData<T> {
+    layers: Vec<usize>,
+    weights: Vec<LayerData<T>>,
+    /// Only stored by a [`WithRng`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rng: Option<RngState>,
+}
+
+/// A deserialized layer: its connection matrix, bias, and activation
+type Layer<T> = (DMatrix<T>, DVector<T>, Activation);
+
+impl<T: Scalar> LayerData<T> {
+    fn new<R: Dim, C: Dim, S: RawStorage<T, R, C>>(
+        connections: &Matrix<T, R, C, S>,
+        bias: &[T],
+        activation: Activation,
+    ) -> Result<Self, VaiError> {
+        if let Activation::Custom(_) = activation {
+            return Err(VaiError::CustomActivation);
+        }
+        let mut values = Vec::with_capacity(connections.len());
+        for row in connections.row_iter() {
+            values.extend(row.iter());
+        }
+        return Ok(Self {
+            activation: activation.to_string(),
+            bias: bias.to_vec(),
+            connections: values,
+        });
+    }
+}
+
+impl<T: Scalar> NetworkData<T> {
+    /// Checks that every layer matches the layer sizes, and converts them
+    fn into_layers(self) -> Result<Vec<Layer<T>>, VaiError> {
+        if self.layers.is_empty() {
+            return Err(VaiError::Format("There must be at least one layer".to_owned()));
+        }
+        let expected = self.layers.len() - 1;
+        if self.weights.len() != expected {
+            return Err(VaiError::Format(format!(
+                "Expected {} layers of weights, found {}",
+                expected,
+                self.weights.len()
+            )));
+        }
+        let mut result = Vec::with_capacity(self.weights.len());
+        for (i, layer) in self.weights.into_iter().enumerate() {
+            let (columns, rows) = (self.layers[i], self.layers[i + 1]);
+            let expected = checked_size(rows, columns)?;
+            if layer.connections.len() != expected {
+                return Err(VaiError::DimensionMismatch {
+                    layer: i,
+                    expected,
+                    found: layer.connections.len(),
+                });
+            }
+            if layer.bias.len() != rows {
+                return Err(VaiError::DimensionMismatch {
+                    layer: i,
+                    expected: rows,
+                    found: layer.bias.len(),
+                });
+            }
+            result.push((
+                DMatrix::from_row_slice(rows, columns, &layer.connections),
+                DVector::from_vec(layer.bias),
+                layer.activation.parse()?,
+            ));
+        }
+        return Ok(result);
+    }
+}
+
+/// A network that is serialized along with the full state of its rng,
+/// so that it continues the exact same sequence of mutations once it is
+/// deserialized. The rng must be a [`CheckpointRng`].
+///
+/// Deserializing fails if the rng state is missing, as it is for networks
+/// that were serialized without this wrapper.
+#[derive(Clone)]
+pub struct WithRng<N>(pub N);
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
+{
+    /// The layers of this vai, as they are serialized
+    fn data(&self) -> Result<NetworkData<T>, VaiError> {
+        let mut weights = vec![LayerData::new(
+            &self.input_connections,
+            self.input_bias.as_slice(),
+            self.input_activation,
+        )?];
+        for i in 0..EXTRA_LAYERS {
+            weights.push(LayerData::new(
+                &self.hidden_connections[i],
+                self.hidden_biases[i].as_slice(),
+                self.hidden_activations[i],
+            )?);
+        }
+        weights.push(LayerData::new(
+            &self.output_connections,
+            self.output_bias.as_slice(),
+            self.output_activation,
+        )?);
+        return Ok(NetworkData {layers: Self::layer_sizes(), weights, rng: None});
+    }
+
+    /// Creates a vai from its serialized layers, checking that they match its shape
+    fn from_data(data: NetworkData<T>) -> Result<Self, VaiError> {
+        let expected = Self::layer_sizes();
+        if data.layers != expected {
+            let found = data.layers;
+            return Err(VaiError::ShapeMismatch {expected, found});
+        }
+        let layers = data.into_layers()?;
+        let mut result = Self::new();
+        let (connections, bias, activation) = &layers[0];
+        result.input_connections = SMatrix::from_column_slice(connections.as_slice());
+        result.input_bias = SMatrix::from_column_slice(bias.as_slice());
+        result.input_activation = *activation;
+        for i in 0..EXTRA_LAYERS {
+            let (connections, bias, activation) = &layers[i + 1];
+            result.hidden_connections[i] = SMatrix::from_column_slice(connections.as_slice());
+            result.hidden_biases[i] = SMatrix::from_column_slice(bias.as_slice());
+            result.hidden_activations[i] = *activation;
+        }
+        let (connections, bias, activation) = &layers[EXTRA_LAYERS + 1];
+        result.output_connections = SMatrix::from_column_slice(connections.as_slice());
+        result.output_bias = SMatrix::from_column_slice(bias.as_slice());
+        result.output_activation = *activation;
+        return Ok(result);
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> VAID<T, R> {
+    /// The layers of this vai, as they are serialized
+    fn data(&self) -> Result<NetworkData<T>, VaiError> {
+        let layers = self.validate()?;
+        if layers.is_empty() {
+            return Err(VaiError::Format("A VAID without connections has no layer sizes".to_owned()));
+        }
+        let mut weights = Vec::with_capacity(self.connections.len());
+        for (i, connections) in self.connections.iter().enumerate() {
+            weights.push(LayerData::new(connections, self.biases[i].as_slice(), self.activations[i])?);
+        }
+        return Ok(NetworkData {layers, weights, rng: None});
+    }
+
+    /// Creates a vai from its serialized layers
+    fn from_data(data: NetworkData<T>) -> Result<Self, VaiError> {
+        let layers = data.into_layers()?;
+        let mut connections = Vec::with_capacity(layers.len());
+        let mut biases = Vec::with_capacity(layers.len());
+        let mut activations = Vec::with_capacity(layers.len());
+        for (matrix, bias, activation) in layers {
+            connections.push(matrix);
+            biases.push(bias);
+            activations.push(activation);
+        }
+        let rng = R::seed_from_u64(rand::random());
+        return Ok(Self {rng, connections, biases, activations});
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R> Serialize
+    for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar + Serialize,
+    R: NetworkRng,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return self.data().map_err(ser::Error::custom)?.serialize(serializer);
+    }
+}
+
+impl<'de, const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    Deserialize<'de> for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar + Deserialize<'de>,
+    R: NetworkRng,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = NetworkData::<T>::deserialize(deserializer)?;
+        return Self::from_data(data).map_err(de::Error::custom);
+    }
+}
+
+impl<T: Scalar + Serialize, R: NetworkRng> Serialize for VAID<T, R> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return self.data().map_err(ser::Error::custom)?.serialize(serializer);
+    }
+}
+
+impl<'de, T: Scalar + Deserialize<'de>, R: NetworkRng> Deserialize<'de> for VAID<T, R> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = NetworkData::<T>::deserialize(deserializer)?;
+        return Self::from_data(data).map_err(de::Error::custom);
+    }
+}
+
+/// Takes the rng state of deserialized data, failing if it is missing
+fn take_rng<T, R: CheckpointRng>(data: &mut NetworkData<T>) -> Result<R, VaiError> {
+    return match data.rng.take() {
+        Some(state) => Ok(R::from_state(&state)),
+        None => Err(VaiError::Format("Missing rng state".to_owned())),
+    };
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R> Serialize
+    for WithRng<VAI<I, O, C, EXTRA_LAYERS, T, R>>
+where
+    T: Scalar + Serialize,
+    R: CheckpointRng,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut data = self.0.data().map_err(ser::Error::custom)?;
+        data.rng = Some(self.0.rng.state());
+        return data.serialize(serializer);
+    }
+}
+
+impl<'de, const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    Deserialize<'de> for WithRng<VAI<I, O, C, EXTRA_LAYERS, T, R>>
+where
+    T: Scalar + Deserialize<'de>,
+    R: CheckpointRng,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut data = NetworkData::<T>::deserialize(deserializer)?;
+        let rng = take_rng(&mut data).map_err(de::Error::custom)?;
+        let mut result = VAI::from_data(data).map_err(de::Error::custom)?;
+        result.rng = rng;
+        return Ok(Self(result));
+    }
+}
+
+impl<T: Scalar + Serialize, R: CheckpointRng> Serialize for WithRng<VAID<T, R>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut data = self.0.data().map_err(ser::Error::custom)?;
+        data.rng = Some(self.0.rng.state());
+        return data.serialize(serializer);
+    }
+}
+
+impl<'de, T: Scalar + Deserialize<'de>, R: CheckpointRng> Deserialize<'de> for WithRng<VAID<T, R>> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut data = NetworkData::<T>::deserialize(deserializer)?;
+        let rng = take_rng(&mut data).map_err(de::Error::custom)?;
+        let mut result = VAID::from_data(data).map_err(de::Error::custom)?;
+        result.rng = rng;
+        return Ok(Self(result));
+    }
+}
@@ -1,57 +1,74 @@
 #![allow(clippy::needless_return)]
 
 use std::cmp::Ordering;
-use std::io::{self, Lines, Write};
-use std::{fmt::Display, fs::File};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
 
 extern crate nalgebra as na;
-use na::SMatrix;
+use na::{DMatrix, DVector, SMatrix};
 extern crate rand;
+use rand::Rng;
 use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
-use crate::{infinite_map, rand_index};
+use crate::format::{self, read_layer_options, BinaryReader, BinaryWriter, Header, LineReader};
+use crate::format::{Encoding, Metadata, NetworkKind};
+use crate::backprop::{self, ForwardCache, Gradients, LayerRef, Loss, Trainable};
+use crate::crossover::{cross_layer, Crossover};
+use crate::error::check_inputs;
+use crate::mutation::{mutate, MutationConfig};
+use crate::{Activation, CheckpointRng, Network, NetworkRng, Parameters, Scalar, VaiError, VAID};
 
-/// Creates a random variation of a matrix
+/// Creates a random variation of a matrix, using the thread rng
 /// * original - The matrix that will be varied
 /// * intensity - The severity to which the matrix will be randomized.
 ///
 /// Intensity affects the random distribution to favor smaller values, but the
 /// resulting matrix can still be changed by an arbitrary amount.
-pub fn create_variant<const R: usize, const C: usize>(
-    original: &na::SMatrix<f32, R, C>,
+///
+/// The result can't be reproduced. Use [`create_variant_with_rng`]
+/// with a seeded rng for deterministic results.
+pub fn create_variant<T: Scalar, const R: usize, const C: usize>(
+    original: &na::SMatrix<T, R, C>,
     intensity: f32,
-) -> na::SMatrix<f32, R, C> {
-    // Adding two variances increases the odds of small changes,
-    // but also makes larger infrequent changes possible
-    let mut variance = na::SMatrix::<f32, R, C>::new_random();
-    variance.apply(|x| *x = infinite_map(*x));
-    return original + variance.scale(intensity);
+) -> na::SMatrix<T, R, C> {
+    return create_variant_with_rng(&mut rand::thread_rng(), original, intensity);
 }
 
-/// Creates a random variation of a matrix using a provided StdRng
+/// Creates a random variation of a matrix using a provided rng
+/// * rng - Any rng, such as a network's rng or a fast PCG or Xoshiro
 /// * original - The matrix that will be varied
 /// * intensity - The severity to which the matrix will be randomized.
 ///
 /// Intensity affects the random distribution to favor smaller values, but the
 /// resulting matrix can still be changed by an arbitrary amount.
-pub fn create_variant_stdrng<const R: usize, const C: usize>(
-    rng: &mut StdRng,
-    original: &na::SMatrix<f32, R, C>,
+pub fn create_variant_with_rng<T: Scalar, G: Rng + ?Sized, const R: usize, const C: usize>(
+    rng: &mut G,
+    original: &na::SMatrix<T, R, C>,
+    intensity: f32,
+) -> na::SMatrix<T, R, C> {
+    return mutate(rng, original, intensity, MutationConfig::default());
+}
+
+/// Creates a random variation of a matrix using a provided rng
+#[deprecated(note = "renamed to create_variant_with_rng, which takes any rng")]
+pub fn create_variant_stdrng<T: Scalar, G: Rng + ?Sized, const R: usize, const C: usize>(
+    rng: &mut G,
+    original: &na::SMatrix<T, R, C>,
     intensity: f32,
-) -> na::SMatrix<f32, R, C> {
-    let mut result = original.clone_owned();
-    result.apply(|x| *x += intensity * infinite_map(rng.gen::<f32>()));
-    return result;
+) -> na::SMatrix<T, R, C> {
+    return create_variant_with_rng(rng, original, intensity);
 }
 
 /// Writes a matrix to a file with space-delimited columns,
 /// newline delimited rows, and a trailing newline.
 /// * matrix - The matrix to write
 /// * file - The file to write to
-pub fn write_matrix<const R: usize, const C: usize>(
-    matrix: &SMatrix<f32, R, C>,
-    file: &mut File,
-) -> std::io::Result<()> {
+pub fn write_matrix<T: Scalar, const R: usize, const C: usize>(
+    matrix: &SMatrix<T, R, C>,
+    file: &mut impl Write,
+) -> Result<(), VaiError> {
     for r in 0..R {
         let row = matrix.row(r);
         for c in 0..C {
@@ -66,86 +83,104 @@ pub fn write_matrix<const R: usize, const C: usize>(
 /// Reads a matrix from lines of a file with space-delimited columns,
 /// and newline delimited rows. Empty (whitespace) lines are ignored.
 /// * lines - A line iterator from which to read the matrix
-/// (generally provided by BufReader::new(file).lines())
-pub fn read_matrix<const R: usize, const C: usize>(
-    lines: &mut Lines<std::io::BufReader<File>>,
-) -> std::io::Result<SMatrix<f32, R, C>> {
-    let mut result = SMatrix::<f32, R, C>::zeros();
-    let mut r = 0;
-    for line in lines {
-        let line = line?;
-        let vals: Vec<&str> = line.split_whitespace().collect();
-        if vals.is_empty() {
-            continue; // Skip empty lines
-        }
-        if vals.len() != C {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Wrong number of columns",
-            ));
-        }
-        for (c, val) in vals.iter().enumerate() {
-            result.row_mut(r)[c] = match val.parse() {
-                Ok(x) => x,
-                Err(_) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        "Invalid number",
-                    ));
-                }
-            };
-        }
-        r += 1;
-        if r == R {
-            break;
-        }
-    }
-    if r != R {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Wrong number of rows",
-        ));
+///   (generally provided by BufReader::new(file).lines())
+pub fn read_matrix<T: Scalar, const R: usize, const C: usize>(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) -> Result<SMatrix<T, R, C>, VaiError> {
+    return read_layer_matrix(&mut LineReader::new(lines), 0);
+}
+
+/// Reads a matrix, as [`read_matrix`] does, reporting errors as part of a layer
+/// * lines - The lines from which to read the matrix
+/// * layer - The index of the layer, for error reporting
+fn read_layer_matrix<T: Scalar, const R: usize, const C: usize, L>(
+    lines: &mut LineReader<L>,
+    layer: usize,
+) -> Result<SMatrix<T, R, C>, VaiError>
+where
+    L: Iterator<Item = io::Result<String>>,
+{
+    let mut result = SMatrix::<T, R, C>::zeros();
+    for r in 0..R {
+        let row = format::read_row::<T, L>(lines, layer, Some(C))?;
+        result.row_mut(r).copy_from_slice(&row);
     }
     return Ok(result);
 }
 
 /// Very Artificial Intelligence.
-/// * I: Number of inputs. You should probably include a constant bias.
+/// * I: Number of inputs
 /// * O: Number of outputs
 /// * C: Complexity of (number of nodes in) hidden layers
 /// * EXTRA_LAYERS: There is always at least one hidden layer. This number adds more.
+///
+/// Each layer of connections has a bias that is added to its result,
+/// and an activation that is then applied.
+/// By default, hidden layers use [`Activation::ReLU`],
+/// and the output layer uses [`Activation::Identity`].
+///
+/// Weights are [`f32`]s by default, but can be any [`Scalar`], and the rng
+/// is a [`StdRng`] by default, but can be any [`NetworkRng`].
 #[derive(Clone)]
-pub struct VAI<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize> {
-    pub rng: StdRng,
-    pub input_connections: na::SMatrix<f32, C, I>,
-    pub hidden_connections: [na::SMatrix<f32, C, C>; EXTRA_LAYERS],
-    pub output_connections: na::SMatrix<f32, O, C>,
+pub struct VAI<
+    const I: usize,
+    const O: usize,
+    const C: usize,
+    const EXTRA_LAYERS: usize,
+    T: Scalar = f32,
+    R: NetworkRng = StdRng,
+> {
+    pub rng: R,
+    pub input_connections: na::SMatrix<T, C, I>,
+    pub hidden_connections: [na::SMatrix<T, C, C>; EXTRA_LAYERS],
+    pub output_connections: na::SMatrix<T, O, C>,
+    /// Added to the result of the input connections
+    pub input_bias: na::SMatrix<T, C, 1>,
+    /// Added to the result of each of the hidden connections
+    pub hidden_biases: [na::SMatrix<T, C, 1>; EXTRA_LAYERS],
+    /// Added to the result of the output connections
+    pub output_bias: na::SMatrix<T, O, 1>,
+    /// Applied to the result of the input connections
+    pub input_activation: Activation,
+    /// Applied to the result of each of the hidden connections
+    pub hidden_activations: [Activation; EXTRA_LAYERS],
+    /// Applied to the result of the output connections
+    pub output_activation: Activation,
 }
 
-impl<const I: usize, const O: usize, const HIDDEN_LAYERS: usize, const LAYER_SIZE: usize> Display
-    for VAI<I, O, HIDDEN_LAYERS, LAYER_SIZE>
+impl<const I: usize, const O: usize, const HIDDEN_LAYERS: usize, const LAYER_SIZE: usize, T, R>
+    Display for VAI<I, O, HIDDEN_LAYERS, LAYER_SIZE, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
 {
     /// Concatenates the string representations of the input,
-    /// hidden layer, and output matricies.
+    /// hidden layer, and output matricies, each followed by its bias.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.input_connections)?;
-        for mat in &self.hidden_connections {
-            write!(f, "{}", mat)?;
+        write!(f, "{}{}", self.input_connections, self.input_bias)?;
+        for (mat, bias) in self.hidden_connections.iter().zip(&self.hidden_biases) {
+            write!(f, "{}{}", mat, bias)?;
         }
-        write!(f, "{}", self.output_connections)
+        write!(f, "{}{}", self.output_connections, self.output_bias)
     }
 }
 
-impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize> Default
-    for VAI<I, O, C, EXTRA_LAYERS>
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    Default for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
 {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize>
-    VAI<I, O, C, EXTRA_LAYERS>
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
 {
     /// Creates a VAI with zeros for all connection weights
     pub fn new() -> Self {
@@ -156,10 +191,16 @@ impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize>
     /// using a specific seed for random number generatoin.
     pub fn new_deterministic(seed: u64) -> Self {
         Self {
-            rng: StdRng::seed_from_u64(seed),
-            input_connections: na::SMatrix::<f32, C, I>::zeros(),
-            hidden_connections: [na::SMatrix::<f32, C, C>::zeros(); EXTRA_LAYERS],
-            output_connections: na::SMatrix::<f32, O, C>::zeros(),
+            rng: R::seed_from_u64(seed),
+            input_connections: na::SMatrix::<T, C, I>::zeros(),
+            hidden_connections: [na::SMatrix::<T, C, C>::zeros(); EXTRA_LAYERS],
+            output_connections: na::SMatrix::<T, O, C>::zeros(),
+            input_bias: na::SMatrix::<T, C, 1>::zeros(),
+            hidden_biases: [na::SMatrix::<T, C, 1>::zeros(); EXTRA_LAYERS],
+            output_bias: na::SMatrix::<T, O, 1>::zeros(),
+            input_activation: Activation::ReLU,
+            hidden_activations: [Activation::ReLU; EXTRA_LAYERS],
+            output_activation: Activation::Identity,
         }
     }
 
@@ -168,26 +209,47 @@ impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize>
     ///
     /// Intensity affects the random distribution to favor low magnitude
     /// values, but the result can still be changed by an arbitrary amount.
-    /// Randomness is applied to each weight of each connection.
+    /// Randomness is applied to each weight of each connection,
+    /// and to each bias.
     ///
     /// In order to keep variation fairly consistent on neural networks
     /// of various sizes, the intensity is scaled down by the number of
     /// connections in the network before being applied.
     ///
     /// see also:
-    ///  * [`create_variant_stdrng`]
+    ///  * [`create_variant_with_rng`]
+    ///  * [`VAI::create_variant_with`]
     ///  * [`VAI::create_layer_variant`]
     pub fn create_variant(&mut self, intensity: f32) -> Self {
+        return self.create_variant_with(intensity, MutationConfig::default());
+    }
+
+    /// Creates a random variant of this VAI, with a chosen distribution and
+    /// probability for the change to each weight
+    /// * intensity - Scaler for the added randomness
+    /// * mutation - How each weight is changed
+    ///
+    /// The intensity is scaled in the same way as [`VAI::create_variant`],
+    /// and weights that the mutation skips are left unchanged.
+    ///
+    /// see also:
+    ///  * [`MutationConfig`]
+    ///  * [`VAI::create_layer_variant_with`]
+    pub fn create_variant_with(&mut self, intensity: f32, mutation: MutationConfig) -> Self {
         let mut result = self.clone();
         let fields = I * C + C * C * EXTRA_LAYERS + C * O;
         let s_intensity = intensity / (1.0 + fields as f32);
         result.input_connections =
-            create_variant_stdrng(&mut self.rng, &result.input_connections, intensity);
-        for mat in &mut result.hidden_connections {
-            *mat = create_variant_stdrng(&mut self.rng, mat, s_intensity);
+            mutate(&mut self.rng, &result.input_connections, intensity, mutation);
+        result.input_bias = mutate(&mut self.rng, &result.input_bias, s_intensity, mutation);
+        for (mat, bias) in result.hidden_connections.iter_mut().zip(&mut result.hidden_biases) {
+            *mat = mutate(&mut self.rng, mat, s_intensity, mutation);
+            *bias = mutate(&mut self.rng, bias, s_intensity, mutation);
         }
         result.output_connections =
-            create_variant_stdrng(&mut self.rng, &result.output_connections, s_intensity);
+            mutate(&mut self.rng, &result.output_connections, s_intensity, mutation);
+        result.output_bias =
+            mutate(&mut self.rng, &result.output_bias, s_intensity, mutation);
         return result;
     }
 
@@ -196,130 +258,874 @@ impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize>
     ///
     /// Intensity affects the random distribution to favor low magnitude
     /// values, but the result can still be changed by an arbitrary amount.
-    /// Randomness is applied to each weight of each connection on a randomly
-    /// chosen layer.
+    /// Randomness is applied to each weight of each connection, and to each
+    /// bias, on a randomly chosen layer.
     ///
     /// In order to keep variation fairly consistent on neural networks
     /// of various sizes, the intensity is scaled down by the number of
     /// connections in the chosen layer before being applied.
     ///
     /// see also:
-    ///  * [`create_variant_stdrng`]
+    ///  * [`create_variant_with_rng`]
+    ///  * [`VAI::create_layer_variant_with`]
     pub fn create_layer_variant(&mut self, intensity: f32) -> Self {
+        return self.create_layer_variant_with(intensity, MutationConfig::default());
+    }
+
+    /// Creates a random variant of this VAI that only changes one layer,
+    /// with a chosen distribution and probability for the change to each weight
+    /// * intensity - Scaler for the added randomness
+    /// * mutation - How each weight is changed
+    ///
+    /// The intensity is scaled in the same way as [`VAI::create_layer_variant`],
+    /// and weights that the mutation skips are left unchanged.
+    ///
+    /// see also:
+    ///  * [`MutationConfig`]
+    pub fn create_layer_variant_with(&mut self, intensity: f32, mutation: MutationConfig) -> Self {
         let mut result = self.clone();
         let hidden_connections = &mut result.hidden_connections;
-        let layer = rand_index(hidden_connections.len() + 2);
+        let layer = self.rng.gen_range(0..hidden_connections.len() + 2);
         let intensity = intensity / (C * C + 1) as f32;
         match layer.cmp(&hidden_connections.len()) {
             Ordering::Less => {
                 let original = &hidden_connections[layer];
                 hidden_connections[layer] =
-                    create_variant_stdrng(&mut self.rng, original, intensity);
+                    mutate(&mut self.rng, original, intensity, mutation);
+                let original = &result.hidden_biases[layer];
+                result.hidden_biases[layer] =
+                    mutate(&mut self.rng, original, intensity, mutation);
             }
             Ordering::Equal => {
                 let original = &result.input_connections;
                 result.input_connections =
-                    create_variant_stdrng(&mut self.rng, original, intensity);
+                    mutate(&mut self.rng, original, intensity, mutation);
+                let original = &result.input_bias;
+                result.input_bias = mutate(&mut self.rng, original, intensity, mutation);
             }
             Ordering::Greater => {
                 let original = &result.output_connections;
                 result.output_connections =
-                    create_variant_stdrng(&mut self.rng, original, intensity);
+                    mutate(&mut self.rng, original, intensity, mutation);
+                let original = &result.output_bias;
+                result.output_bias = mutate(&mut self.rng, original, intensity, mutation);
             }
         }
         return result;
     }
 
+    /// Creates a child of this VAI and another, using [`Crossover::Uniform`]
+    /// * other - The other parent
+    ///
+    /// see also:
+    ///  * [`VAI::crossover_with`]
+    pub fn crossover(&mut self, other: &Self) -> Self {
+        return self.crossover_with(other, Crossover::Uniform);
+    }
+
+    /// Creates a child of this VAI and another.
+    /// The child's random number generator is cloned from this VAI,
+    /// and this VAI's random number generator is used for the crossover.
+    /// * other - The other parent
+    /// * mode - How the parents' weights are combined
+    ///
+    /// see also:
+    ///  * [`VAI::crossover`]
+    pub fn crossover_with(&mut self, other: &Self, mode: Crossover) -> Self {
+        let mut result = self.clone();
+        let rng = &mut self.rng;
+        if mode == Crossover::LayerSwap {
+            if rng.gen::<bool>() {
+                result.input_connections = other.input_connections;
+                result.input_bias = other.input_bias;
+                result.input_activation = other.input_activation;
+            }
+            for i in 0..EXTRA_LAYERS {
+                if rng.gen::<bool>() {
+                    result.hidden_connections[i] = other.hidden_connections[i];
+                    result.hidden_biases[i] = other.hidden_biases[i];
+                    result.hidden_activations[i] = other.hidden_activations[i];
+                }
+            }
+            if rng.gen::<bool>() {
+                result.output_connections = other.output_connections;
+                result.output_bias = other.output_bias;
+                result.output_activation = other.output_activation;
+            }
+            return result;
+        }
+        cross_layer(
+            rng,
+            mode,
+            &mut result.input_connections,
+            &mut result.input_bias,
+            &other.input_connections,
+            &other.input_bias,
+        );
+        for i in 0..EXTRA_LAYERS {
+            cross_layer(
+                rng,
+                mode,
+                &mut result.hidden_connections[i],
+                &mut result.hidden_biases[i],
+                &other.hidden_connections[i],
+                &other.hidden_biases[i],
+            );
+        }
+        cross_layer(
+            rng,
+            mode,
+            &mut result.output_connections,
+            &mut result.output_bias,
+            &other.output_connections,
+            &other.output_bias,
+        );
+        return result;
+    }
+
     /// Runs an input matrix through the neural network to get an output
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// * inputs - The inputs
     ///
     /// see also:
     ///  * [`VAI::process_slice`]
     ///  * [`VAI::process_transparent`]
-    pub fn process(&self, inputs: &na::SMatrix<f32, I, 1>) -> na::SMatrix<f32, O, 1> {
-        let mut intermediate = self.input_connections * inputs;
-        // Apply relu
-        intermediate.apply(|x| *x = x.max(0.));
-        for mat in &self.hidden_connections {
-            intermediate = mat * intermediate;
-            // Apply relu
-            intermediate.apply(|x| *x = x.max(0.));
+    pub fn process(&self, inputs: &na::SMatrix<T, I, 1>) -> na::SMatrix<T, O, 1> {
+        let mut intermediate = self.input_connections * inputs + self.input_bias;
+        intermediate.apply(|x| *x = self.input_activation.apply(*x));
+        for i in 0..EXTRA_LAYERS {
+            intermediate = self.hidden_connections[i] * intermediate + self.hidden_biases[i];
+            intermediate.apply(|x| *x = self.hidden_activations[i].apply(*x));
         }
-        return self.output_connections * intermediate;
+        let mut output = self.output_connections * intermediate + self.output_bias;
+        output.apply(|x| *x = self.output_activation.apply(*x));
+        return output;
     }
 
     /// Runs an input slice through the neural network to get an output
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// * inputs - The inputs
+    ///
+    /// Panics if there are not exactly I inputs.
     ///
     /// see also:
     ///  * [`VAI::process`]
-    pub fn process_slice(&self, inputs: &[f32]) -> Vec<f32> {
-        let matrix_inputs = na::SMatrix::<f32, I, 1>::from_column_slice(inputs);
+    ///  * [`VAI::try_process_slice`]
+    pub fn process_slice(&self, inputs: &[T]) -> Vec<T> {
+        let matrix_inputs = na::SMatrix::<T, I, 1>::from_column_slice(inputs);
         let output = self.process(&matrix_inputs);
         output.iter().map(|x| x.to_owned()).collect()
     }
 
+    /// Runs an input slice through the neural network, writing the result
+    /// to an output slice. Unlike [`VAI::process_slice`], this does not
+    /// allocate, since every layer is kept on the stack.
+    /// * inputs - The inputs
+    /// * outputs - Where the outputs are written
+    ///
+    /// Panics if there are not exactly I inputs and O outputs.
+    pub fn process_into(&self, inputs: &[T], outputs: &mut [T]) {
+        assert_eq!(outputs.len(), O, "wrong number of outputs");
+        let output = self.process(&na::SMatrix::<T, I, 1>::from_column_slice(inputs));
+        outputs.copy_from_slice(output.as_slice());
+    }
+
+    /// Runs a batch of inputs through the neural network to get their outputs,
+    /// running each layer as a single matrix product
+    /// * inputs - The inputs, with one sample per column
+    ///
+    /// Returns one column of outputs per column of inputs.
+    /// Panics if the inputs do not have I rows.
+    ///
+    /// see also:
+    ///  * [`VAI::process_batch_slice`]
+    pub fn process_batch(&self, inputs: &na::DMatrix<T>) -> na::DMatrix<T> {
+        assert_eq!(inputs.nrows(), I, "wrong number of inputs");
+        let mut intermediate = self.input_connections * inputs;
+        apply_layer(&mut intermediate, &self.input_bias, self.input_activation);
+        for i in 0..EXTRA_LAYERS {
+            intermediate = self.hidden_connections[i] * intermediate;
+            apply_layer(&mut intermediate, &self.hidden_biases[i], self.hidden_activations[i]);
+        }
+        let mut output = self.output_connections * intermediate;
+        apply_layer(&mut output, &self.output_bias, self.output_activation);
+        return na::DMatrix::from_column_slice(O, output.ncols(), output.as_slice());
+    }
+
+    /// Runs a batch of input arrays through the neural network,
+    /// as [`VAI::process_batch`] does
+    /// * inputs - The inputs of each sample
+    ///
+    /// Returns the outputs of each sample.
+    pub fn process_batch_slice(&self, inputs: &[[T; I]]) -> Vec<[T; O]> {
+        let matrix_inputs = na::DMatrix::from_column_slice(I, inputs.len(), inputs.as_flattened());
+        let output = self.process_batch(&matrix_inputs);
+        return output
+            .column_iter()
+            .map(|x| std::array::from_fn(|i| x[i]))
+            .collect();
+    }
+
+    /// Runs an input matrix through the neural network to get an output,
+    /// like [`VAI::process`], optionally rejecting NaN and infinite inputs
+    /// * inputs - The inputs
+    /// * require_finite - Whether to fail if any input is NaN or infinite
+    ///
+    /// see also:
+    ///  * [`VAI::try_process_slice`]
+    pub fn try_process(
+        &self,
+        inputs: &na::SMatrix<T, I, 1>,
+        require_finite: bool,
+    ) -> Result<na::SMatrix<T, O, 1>, VaiError> {
+        check_inputs(I, I, inputs.as_slice(), require_finite)?;
+        return Ok(self.process(inputs));
+    }
+
+    /// Runs an input slice through the neural network to get an output,
+    /// like [`VAI::process_slice`], but fails instead of panicking
+    /// if there are not exactly I inputs.
+    /// * inputs - The inputs
+    /// * require_finite - Whether to fail if any input is NaN or infinite
+    ///
+    /// see also:
+    ///  * [`VAI::try_process`]
+    pub fn try_process_slice(
+        &self,
+        inputs: &[T],
+        require_finite: bool,
+    ) -> Result<Vec<T>, VaiError> {
+        check_inputs(inputs.len(), I, inputs, require_finite)?;
+        return Ok(self.process_slice(inputs));
+    }
+
     /// Runs an input matrix through the neural network to get an output
     /// returning the value of all the nodes: input, hidden, and output.
     ///
     /// Note: the value of hidden nodes is supplie *before*
-    /// relu to preserve information
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// their activation to preserve information.
+    /// The output nodes are supplied after the output activation.
+    /// * inputs - The inputs
     ///
     /// see also:
     ///  * [`VAI::process_slice_transparent`]
-    pub fn process_transparent(&self, inputs: &na::SMatrix<f32, I, 1>) -> Vec<Vec<f32>> {
-        let mut output: Vec<Vec<f32>> = vec![inputs.iter().map(|x| x.to_owned()).collect()];
-        let mut intermediate = self.input_connections * inputs;
+    pub fn process_transparent(&self, inputs: &na::SMatrix<T, I, 1>) -> Vec<Vec<T>> {
+        let mut output: Vec<Vec<T>> = vec![inputs.iter().map(|x| x.to_owned()).collect()];
+        let mut intermediate = self.input_connections * inputs + self.input_bias;
         output.push(intermediate.iter().map(|x| x.to_owned()).collect());
-        // Apply relu
-        intermediate.apply(|x| *x = x.max(0.));
-        for mat in &self.hidden_connections {
-            intermediate = mat * intermediate;
+        intermediate.apply(|x| *x = self.input_activation.apply(*x));
+        for i in 0..EXTRA_LAYERS {
+            intermediate = self.hidden_connections[i] * intermediate + self.hidden_biases[i];
             output.push(intermediate.iter().map(|x| x.to_owned()).collect());
-            // Apply relu
-            intermediate.apply(|x| *x = x.max(0.));
+            intermediate.apply(|x| *x = self.hidden_activations[i].apply(*x));
         }
-        let out = self.output_connections * intermediate;
+        let mut out = self.output_connections * intermediate + self.output_bias;
+        out.apply(|x| *x = self.output_activation.apply(*x));
         output.push(out.iter().map(|x| x.to_owned()).collect());
         return output;
     }
 
     /// Runs an input slice through the neural network to get an output
     /// returning the value of all the nodes: input, hidden, and output.
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// * inputs - The inputs
     ///
     /// see also:
     ///  * [`VAI::process_transparent`]
-    pub fn process_slice_transparent(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
-        let matrix_inputs = na::SMatrix::<f32, I, 1>::from_column_slice(inputs);
+    pub fn process_slice_transparent(&self, inputs: &[T]) -> Vec<Vec<T>> {
+        let matrix_inputs = na::SMatrix::<T, I, 1>::from_column_slice(inputs);
         return self.process_transparent(&matrix_inputs);
     }
 
-    /// Writes a vai to a file, writing its input, hidden, and output
-    /// connections in order, as written by [`write_matrix`]
-    /// * lines - A line iterator from which to read the vai
-    /// (generally provided by BufReader::new(file).lines())
-    pub fn write(&self, file: &mut File) -> std::io::Result<()> {
-        write_matrix(&self.input_connections, file)?;
-        for matrix in &self.hidden_connections {
-            write_matrix(matrix, file)?;
+    /// The header describing this vai's shape and activations
+    /// * metadata - Optional information to store alongside the vai
+    fn header(&self, metadata: &Metadata) -> Header {
+        let mut activations = vec![self.input_activation];
+        activations.extend_from_slice(&self.hidden_activations);
+        activations.push(self.output_activation);
+        return Header {
+            version: format::VERSION,
+            kind: NetworkKind::VAI,
+            layers: Self::layer_sizes(),
+            activations,
+            metadata: metadata.clone(),
+            rng: None,
+        };
+    }
+
+    /// The number of neurons in each layer, starting with the inputs
+    pub(crate) fn layer_sizes() -> Vec<usize> {
+        let mut layers = vec![I];
+        layers.extend(std::iter::repeat_n(C, EXTRA_LAYERS + 1));
+        layers.push(O);
+        return layers;
+    }
+
+    /// Writes a vai to a file in the format described in [`crate::format`]:
+    /// a header, followed by its input, hidden, and output layers in order.
+    /// * file - The file to write to
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    ///
+    /// see also:
+    ///  * [`VAI::write_with_metadata`]
+    pub fn write(&self, file: &mut impl Write) -> Result<(), VaiError> {
+        return self.write_with_metadata(file, &Metadata::default());
+    }
+
+    /// Writes a vai to a file, like [`VAI::write`], with metadata in its header
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    pub fn write_with_metadata(
+        &self,
+        file: &mut impl Write,
+        metadata: &Metadata,
+    ) -> Result<(), VaiError> {
+        self.header(metadata).write(file)?;
+        format::write_layer(file, &self.input_connections, self.input_bias.as_slice())?;
+        for i in 0..EXTRA_LAYERS {
+            format::write_layer(file, &self.hidden_connections[i], self.hidden_biases[i].as_slice())?;
         }
-        write_matrix(&self.output_connections, file)?;
+        format::write_layer(file, &self.output_connections, self.output_bias.as_slice())?;
         return Ok(());
     }
 
-    /// Reads a matrix from lines of a file containing its input, hidden, and
-    /// output connections in order, as read by [`read_matrix`]
+    /// Reads a vai from lines of a file, as written by [`VAI::write`].
+    /// Fails if the file holds a different kind or shape of network.
+    ///
+    /// Files without a header are read in the legacy layout: the input,
+    /// hidden, and output connections in order, as read by [`read_matrix`].
+    /// There, layers without an activation line use the default activations,
+    /// and layers without a bias line have no bias.
     /// * lines - A line iterator from which to read the vai
-    /// (generally provided by BufReader::new(file).lines())
-    pub fn read(lines: &mut Lines<std::io::BufReader<File>>) -> std::io::Result<Self> {
+    ///   (generally provided by BufReader::new(file).lines())
+    pub fn read(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Self, VaiError> {
+        return Ok(Self::read_with_metadata(lines)?.0);
+    }
+
+    /// Reads a vai like [`VAI::read`], along with the metadata in its header.
+    /// Files in the legacy layout have no metadata.
+    /// * lines - A line iterator from which to read the vai
+    pub fn read_with_metadata(
+        lines: &mut impl Iterator<Item = io::Result<String>>,
+    ) -> Result<(Self, Metadata), VaiError> {
+        let mut lines = LineReader::new(lines);
+        let header = match Header::read(&mut lines)? {
+            Some(header) => header,
+            None => return Ok((Self::read_legacy(&mut lines)?, Metadata::default())),
+        };
+        header.expect_kind(NetworkKind::VAI)?;
+        header.expect_layers(&Self::layer_sizes())?;
+        let mut result = Self::new();
+        let (connections, bias) = format::read_layer(&mut lines, 0, C, I)?;
+        result.input_connections = SMatrix::from_column_slice(connections.as_slice());
+        result.input_bias = SMatrix::from_column_slice(&bias);
+        for i in 0..EXTRA_LAYERS {
+            let (connections, bias) = format::read_layer(&mut lines, i + 1, C, C)?;
+            result.hidden_connections[i] = SMatrix::from_column_slice(connections.as_slice());
+            result.hidden_biases[i] = SMatrix::from_column_slice(&bias);
+        }
+        let (connections, bias) = format::read_layer(&mut lines, EXTRA_LAYERS + 1, O, C)?;
+        result.output_connections = SMatrix::from_column_slice(connections.as_slice());
+        result.output_bias = SMatrix::from_column_slice(&bias);
+        result.input_activation = header.activations[0];
+        result.hidden_activations.copy_from_slice(&header.activations[1..=EXTRA_LAYERS]);
+        result.output_activation = header.activations[EXTRA_LAYERS + 1];
+        return Ok((result, header.metadata));
+    }
+
+    /// Reads a vai from a reader, as written by [`VAI::write`]
+    /// * reader - The reader from which to read the vai
+    pub fn read_from(reader: &mut impl BufRead) -> Result<Self, VaiError> {
+        return Self::read(&mut reader.lines());
+    }
+
+    /// Writes a vai to a new file at the given path, as [`VAI::write`] does
+    /// * path - The path of the file to create
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), VaiError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()?;
+        return Ok(());
+    }
+
+    /// Reads a vai from the file at the given path, as [`VAI::read`] does
+    /// * path - The path of the file to read
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VaiError> {
+        return Self::read_from(&mut BufReader::new(File::open(path)?));
+    }
+
+    /// Writes a vai to a string, as [`VAI::write`] does
+    ///
+    /// see also:
+    ///  * [`VAI::from_str`]
+    pub fn to_text(&self) -> Result<String, VaiError> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)?;
+        return Ok(String::from_utf8(buffer).expect("numbers and keywords are valid utf-8"));
+    }
+
+    /// Writes a vai in the binary format described in [`crate::format`].
+    /// Unlike the text format, every weight is stored exactly.
+    /// * file - The file to write to
+    /// * checksum - Whether to end the file with a checksum,
+    ///   which is verified when it is read
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    pub fn write_binary(&self, file: &mut impl Write, checksum: bool) -> Result<(), VaiError> {
+        return self.write_binary_with_metadata(file, &Metadata::default(), checksum);
+    }
+
+    /// Writes a vai like [`VAI::write_binary`], with metadata in its header
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    /// * checksum - Whether to end the file with a checksum
+    pub fn write_binary_with_metadata(
+        &self,
+        file: &mut impl Write,
+        metadata: &Metadata,
+        checksum: bool,
+    ) -> Result<(), VaiError> {
+        return self.write_binary_with_header(file, &self.header(metadata), checksum);
+    }
+
+    /// Writes a vai in the binary format, after the given header
+    fn write_binary_with_header(
+        &self,
+        file: &mut impl Write,
+        header: &Header,
+        checksum: bool,
+    ) -> Result<(), VaiError> {
+        let mut writer = BinaryWriter::new(file);
+        let encoding = Encoding::new::<T>(checksum);
+        let wide = encoding.wide;
+        header.write_binary(&mut writer, encoding)?;
+        writer.write_scalars(self.input_connections.as_slice(), wide)?;
+        writer.write_scalars(self.input_bias.as_slice(), wide)?;
+        for i in 0..EXTRA_LAYERS {
+            writer.write_scalars(self.hidden_connections[i].as_slice(), wide)?;
+            writer.write_scalars(self.hidden_biases[i].as_slice(), wide)?;
+        }
+        writer.write_scalars(self.output_connections.as_slice(), wide)?;
+        writer.write_scalars(self.output_bias.as_slice(), wide)?;
+        return writer.finish(checksum);
+    }
+
+    /// Reads a vai as written by [`VAI::write_binary`].
+    /// Fails if the file holds a different kind or shape of network,
+    /// or if its checksum does not match.
+    /// * file - The file from which to read the vai
+    pub fn read_binary(file: &mut impl Read) -> Result<Self, VaiError> {
+        return Ok(Self::read_binary_with_metadata(file)?.0);
+    }
+
+    /// Reads a vai like [`VAI::read_binary`], along with the metadata in its header.
+    /// The rng is seeded randomly, even if the file is a checkpoint.
+    ///
+    /// see also:
+    ///  * [`VAI::resume`]
+    /// * file - The file from which to read the vai
+    pub fn read_binary_with_metadata(file: &mut impl Read) -> Result<(Self, Metadata), VaiError> {
+        let (result, header) = Self::read_binary_with_header(file)?;
+        return Ok((result, header.metadata));
+    }
+
+    /// Reads a vai in the binary format, along with its header
+    fn read_binary_with_header(file: &mut impl Read) -> Result<(Self, Header), VaiError> {
+        let mut reader = BinaryReader::new(file);
+        let (header, encoding) = Header::read_binary(&mut reader)?;
+        header.expect_kind(NetworkKind::VAI)?;
+        header.expect_layers(&Self::layer_sizes())?;
+        let wide = encoding.wide;
         let mut result = Self::new();
-        result.input_connections = read_matrix(lines)?;
-        for matrix in &mut result.hidden_connections {
-            *matrix = read_matrix(lines)?;
+        result.input_connections = SMatrix::from_column_slice(&reader.read_scalars(C * I, wide)?);
+        result.input_bias = SMatrix::from_column_slice(&reader.read_scalars(C, wide)?);
+        for i in 0..EXTRA_LAYERS {
+            let hidden = reader.read_scalars(C * C, wide)?;
+            result.hidden_connections[i] = SMatrix::from_column_slice(&hidden);
+            result.hidden_biases[i] = SMatrix::from_column_slice(&reader.read_scalars(C, wide)?);
         }
-        result.output_connections = read_matrix(lines)?;
+        result.output_connections = SMatrix::from_column_slice(&reader.read_scalars(O * C, wide)?);
+        result.output_bias = SMatrix::from_column_slice(&reader.read_scalars(O, wide)?);
+        reader.finish(encoding.checksum)?;
+        result.input_activation = header.activations[0];
+        result.hidden_activations.copy_from_slice(&header.activations[1..=EXTRA_LAYERS]);
+        result.output_activation = header.activations[EXTRA_LAYERS + 1];
+        return Ok((result, header));
+    }
+
+    /// Reads a vai written without a header
+    /// * lines - A peekable line iterator from which to read the vai
+    fn read_legacy<L: Iterator<Item = io::Result<String>>>(
+        lines: &mut LineReader<L>,
+    ) -> Result<Self, VaiError> {
+        let mut result = Self::new();
+        let mut options = read_layer_options(lines)?;
+        result.input_activation = options.activation.unwrap_or(result.input_activation);
+        result.input_bias = SMatrix::from_column_slice(&options.take_bias(0, C)?);
+        result.input_connections = read_layer_matrix(lines, 0)?;
+        for i in 0..EXTRA_LAYERS {
+            let mut options = read_layer_options(lines)?;
+            let activation = &mut result.hidden_activations[i];
+            *activation = options.activation.unwrap_or(*activation);
+            result.hidden_biases[i] = SMatrix::from_column_slice(&options.take_bias(i + 1, C)?);
+            result.hidden_connections[i] = read_layer_matrix(lines, i + 1)?;
+        }
+        let mut options = read_layer_options(lines)?;
+        result.output_activation = options.activation.unwrap_or(result.output_activation);
+        result.output_bias = SMatrix::from_column_slice(&options.take_bias(EXTRA_LAYERS + 1, O)?);
+        result.output_connections = read_layer_matrix(lines, EXTRA_LAYERS + 1)?;
         return Ok(result);
     }
 }
+
+// Checkpoints store the state of the rng, which must be a CheckpointRng
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: CheckpointRng,
+{
+    /// Writes a checkpoint: the vai in the binary format, with a checksum,
+    /// along with the full state of its rng, so that [`VAI::resume`]
+    /// continues the exact same sequence of mutations.
+    /// The rng must be a [`CheckpointRng`], such as a [`rand_chacha::ChaCha12Rng`].
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    ///
+    /// see also:
+    ///  * [`VAI::save_checkpoint`]
+    pub fn write_checkpoint(&self, file: &mut impl Write, metadata: &Metadata) -> Result<(), VaiError> {
+        let mut header = self.header(metadata);
+        header.rng = Some(self.rng.state());
+        return self.write_binary_with_header(file, &header, true);
+    }
+
+    /// Writes a checkpoint to a new file at the given path,
+    /// as [`VAI::write_checkpoint`] does
+    /// * path - The path of the file to create
+    /// * metadata - Optional information to store alongside the vai
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>, metadata: &Metadata) -> Result<(), VaiError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_checkpoint(&mut file, metadata)?;
+        file.flush()?;
+        return Ok(());
+    }
+
+    /// Reads a checkpoint written by [`VAI::write_checkpoint`], restoring the
+    /// vai's rng, so that it continues the exact same sequence of mutations.
+    /// Fails if the file holds no rng state.
+    /// * file - The file from which to read the vai
+    ///
+    /// see also:
+    ///  * [`VAI::load_checkpoint`]
+    pub fn resume(file: &mut impl Read) -> Result<(Self, Metadata), VaiError> {
+        let (mut result, header) = Self::read_binary_with_header(file)?;
+        match &header.rng {
+            Some(state) => result.rng = R::from_state(state),
+            None => return Err(VaiError::Format("Missing rng state".to_owned())),
+        }
+        return Ok((result, header.metadata));
+    }
+
+    /// Reads a checkpoint from the file at the given path, as [`VAI::resume`] does
+    /// * path - The path of the file to read
+    pub fn load_checkpoint(path: impl AsRef<Path>) -> Result<(Self, Metadata), VaiError> {
+        return Self::resume(&mut BufReader::new(File::open(path)?));
+    }
+}
+
+// Backpropagation uses f32 weights
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, R: NetworkRng>
+    VAI<I, O, C, EXTRA_LAYERS, f32, R>
+{
+    /// Runs an input matrix through the neural network, keeping the value of
+    /// every layer for use by [`VAI::backward`]
+    /// * inputs - The inputs, with I rows, and one sample per column
+    ///
+    /// see also:
+    ///  * [`VAI::train_step`]
+    pub fn forward(&self, inputs: &na::DMatrix<f32>) -> ForwardCache {
+        return backprop::forward(&self.layer_refs(), inputs);
+    }
+
+    /// Calculates the gradient of a loss with respect to every connection
+    /// and bias, by propagating the error backwards through the network.
+    /// Gradients are ordered input, hidden, then output.
+    /// * cache - The result of [`VAI::forward`]
+    /// * targets - The desired outputs, with O rows, and one sample per column
+    /// * loss - How the error is measured
+    ///
+    /// Returns the loss, and its gradients.
+    pub fn backward(
+        &self,
+        cache: &ForwardCache,
+        targets: &na::DMatrix<f32>,
+        loss: Loss,
+    ) -> (f32, Gradients) {
+        return backprop::backward(&self.layer_refs(), cache, targets, loss);
+    }
+
+    /// Trains the network by one step of gradient descent,
+    /// using [`Loss::MeanSquaredError`]
+    /// * inputs - The inputs, with I rows, and one sample per column
+    /// * targets - The desired outputs, with O rows, and one sample per column
+    /// * learning_rate - Scaler for the gradient
+    ///
+    /// Returns the loss from before the step.
+    ///
+    /// see also:
+    ///  * [`VAI::train_step_with`]
+    pub fn train_step(
+        &mut self,
+        inputs: &na::DMatrix<f32>,
+        targets: &na::DMatrix<f32>,
+        learning_rate: f32,
+    ) -> f32 {
+        return self.train_step_with(inputs, targets, learning_rate, Loss::MeanSquaredError);
+    }
+
+    /// Trains the network by one step of gradient descent
+    /// * inputs - The inputs, with I rows, and one sample per column
+    /// * targets - The desired outputs, with O rows, and one sample per column
+    /// * learning_rate - Scaler for the gradient
+    /// * loss - How the error is measured
+    ///
+    /// Returns the loss from before the step.
+    pub fn train_step_with(
+        &mut self,
+        inputs: &na::DMatrix<f32>,
+        targets: &na::DMatrix<f32>,
+        learning_rate: f32,
+        loss: Loss,
+    ) -> f32 {
+        return Trainable::descend(self, inputs, targets, learning_rate, loss);
+    }
+
+    /// Borrows each layer for the forward and backward passes
+    fn layer_refs(&self) -> Vec<LayerRef<'_>> {
+        fn layer<'a>(
+            connections: &'a [f32],
+            rows: usize,
+            bias: &'a [f32],
+            activation: Activation,
+        ) -> LayerRef<'a> {
+            return LayerRef {
+                connections: na::DMatrixView::from_slice(connections, rows, connections.len() / rows.max(1)),
+                bias: na::DVectorView::from_slice(bias, rows),
+                activation,
+            };
+        }
+        let mut layers = vec![layer(
+            self.input_connections.as_slice(),
+            C,
+            self.input_bias.as_slice(),
+            self.input_activation,
+        )];
+        for i in 0..EXTRA_LAYERS {
+            layers.push(layer(
+                self.hidden_connections[i].as_slice(),
+                C,
+                self.hidden_biases[i].as_slice(),
+                self.hidden_activations[i],
+            ));
+        }
+        layers.push(layer(
+            self.output_connections.as_slice(),
+            O,
+            self.output_bias.as_slice(),
+            self.output_activation,
+        ));
+        return layers;
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    FromStr for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
+{
+    type Err = VaiError;
+
+    /// Reads a vai from a string, as [`VAI::read`] does
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        return Self::read(&mut text.lines().map(|x| Ok(x.to_owned())));
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    From<&VAI<I, O, C, EXTRA_LAYERS, T, R>> for VAID<T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
+{
+    /// Copies the weights, biases, activations and rng of a vai
+    /// into a VAID with the same layers
+    fn from(ai: &VAI<I, O, C, EXTRA_LAYERS, T, R>) -> Self {
+        let mut connections = vec![DMatrix::from_column_slice(C, I, ai.input_connections.as_slice())];
+        let mut biases = vec![DVector::from_column_slice(ai.input_bias.as_slice())];
+        for i in 0..EXTRA_LAYERS {
+            let hidden = ai.hidden_connections[i].as_slice();
+            connections.push(DMatrix::from_column_slice(C, C, hidden));
+            biases.push(DVector::from_column_slice(ai.hidden_biases[i].as_slice()));
+        }
+        let output = ai.output_connections.as_slice();
+        connections.push(DMatrix::from_column_slice(O, C, output));
+        biases.push(DVector::from_column_slice(ai.output_bias.as_slice()));
+        let mut activations = vec![ai.input_activation];
+        activations.extend_from_slice(&ai.hidden_activations);
+        activations.push(ai.output_activation);
+        return VAID {rng: ai.rng.clone(), connections, biases, activations};
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    TryFrom<&VAID<T, R>> for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
+{
+    type Error = VaiError;
+
+    /// Copies the weights, biases, activations and rng of a VAID into a vai.
+    /// Fails if the VAID's layers do not match the vai's.
+    fn try_from(ai: &VAID<T, R>) -> Result<Self, Self::Error> {
+        let expected = Self::layer_sizes();
+        let found = ai.validate()?;
+        if found != expected {
+            return Err(VaiError::ShapeMismatch {expected, found});
+        }
+        let mut result = Self::new();
+        result.rng = ai.rng.clone();
+        result.input_connections = SMatrix::from_column_slice(ai.connections[0].as_slice());
+        result.input_bias = SMatrix::from_column_slice(ai.biases[0].as_slice());
+        result.input_activation = ai.activations[0];
+        for i in 0..EXTRA_LAYERS {
+            let hidden = ai.connections[i + 1].as_slice();
+            result.hidden_connections[i] = SMatrix::from_column_slice(hidden);
+            result.hidden_biases[i] = SMatrix::from_column_slice(ai.biases[i + 1].as_slice());
+            result.hidden_activations[i] = ai.activations[i + 1];
+        }
+        result.output_connections = SMatrix::from_column_slice(ai.connections[EXTRA_LAYERS + 1].as_slice());
+        result.output_bias = SMatrix::from_column_slice(ai.biases[EXTRA_LAYERS + 1].as_slice());
+        result.output_activation = ai.activations[EXTRA_LAYERS + 1];
+        return Ok(result);
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    Network for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
+{
+    type Scalar = T;
+
+    fn process_slice(&self, inputs: &[T]) -> Vec<T> {
+        return VAI::process_slice(self, inputs);
+    }
+
+    fn create_variant(&mut self, intensity: f32) -> Self {
+        return VAI::create_variant(self, intensity);
+    }
+
+    fn create_layer_variant(&mut self, intensity: f32) -> Self {
+        return VAI::create_layer_variant(self, intensity);
+    }
+
+    fn crossover_with(&mut self, other: &Self, mode: Crossover) -> Self {
+        return VAI::crossover_with(self, other, mode);
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, T, R>
+    Parameters for VAI<I, O, C, EXTRA_LAYERS, T, R>
+where
+    T: Scalar,
+    R: NetworkRng,
+{
+    fn layer_parameter_counts(&self) -> Vec<usize> {
+        let mut counts = vec![I * C + C];
+        counts.extend(std::iter::repeat_n(C * C + C, EXTRA_LAYERS));
+        counts.push(C * O + O);
+        return counts;
+    }
+
+    fn parameters(&self) -> Vec<T> {
+        let mut parameters = Vec::with_capacity(self.parameter_count());
+        parameters.extend_from_slice(self.input_connections.as_slice());
+        parameters.extend_from_slice(self.input_bias.as_slice());
+        for (mat, bias) in self.hidden_connections.iter().zip(&self.hidden_biases) {
+            parameters.extend_from_slice(mat.as_slice());
+            parameters.extend_from_slice(bias.as_slice());
+        }
+        parameters.extend_from_slice(self.output_connections.as_slice());
+        parameters.extend_from_slice(self.output_bias.as_slice());
+        return parameters;
+    }
+
+    fn set_parameters(&mut self, parameters: &[T]) {
+        assert_eq!(parameters.len(), self.parameter_count(), "Wrong number of parameters");
+        let mut rest = parameters;
+        let mut take = |len: usize| {
+            let (values, remaining) = rest.split_at(len);
+            rest = remaining;
+            return values;
+        };
+        self.input_connections.copy_from_slice(take(I * C));
+        self.input_bias.copy_from_slice(take(C));
+        for (mat, bias) in self.hidden_connections.iter_mut().zip(&mut self.hidden_biases) {
+            mat.copy_from_slice(take(C * C));
+            bias.copy_from_slice(take(C));
+        }
+        self.output_connections.copy_from_slice(take(C * O));
+        self.output_bias.copy_from_slice(take(O));
+    }
+}
+
+impl<const I: usize, const O: usize, const C: usize, const EXTRA_LAYERS: usize, R: NetworkRng>
+    Trainable for VAI<I, O, C, EXTRA_LAYERS, f32, R>
+{
+    fn forward(&self, inputs: &na::DMatrix<f32>) -> ForwardCache {
+        return VAI::forward(self, inputs);
+    }
+
+    fn backward(
+        &self,
+        cache: &ForwardCache,
+        targets: &na::DMatrix<f32>,
+        loss: Loss,
+    ) -> (f32, Gradients) {
+        return VAI::backward(self, cache, targets, loss);
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut [f32]> {
+        let mut parameters = vec![self.input_connections.as_mut_slice()];
+        for mat in &mut self.hidden_connections {
+            parameters.push(mat.as_mut_slice());
+        }
+        parameters.push(self.output_connections.as_mut_slice());
+        parameters.push(self.input_bias.as_mut_slice());
+        for bias in &mut self.hidden_biases {
+            parameters.push(bias.as_mut_slice());
+        }
+        parameters.push(self.output_bias.as_mut_slice());
+        return parameters;
+    }
+}
+
+/// Adds a bias to every column of a matrix, then applies an activation
+fn apply_layer<T, R, S, B>(
+    matrix: &mut na::Matrix<T, R, na::Dyn, S>,
+    bias: &na::Vector<T, R, B>,
+    activation: Activation,
+) where
+    T: Scalar,
+    R: na::Dim,
+    S: na::RawStorageMut<T, R, na::Dyn>,
+    B: na::RawStorage<T, R>,
+{
+    for mut column in matrix.column_iter_mut() {
+        for (x, b) in column.iter_mut().zip(bias.iter()) {
+            *x = activation.apply(*x + *b);
+        }
+    }
+}
@@ -1,57 +1,80 @@
 #![allow(clippy::needless_return)]
 
-use crate::{infinite_map, rand_index};
+use crate::format::{self, default_activations, read_layer_options, BinaryReader, BinaryWriter};
+use crate::format::{checked_size, Encoding, Header, LineReader, Metadata, NetworkKind};
+use crate::backprop::{self, ForwardCache, Gradients, LayerRef, Loss, Trainable};
+use crate::crossover::{cross_layer, Crossover};
+use crate::error::check_inputs;
+use crate::mutation::{mutate, MutationConfig};
+use crate::{infinite_map, Activation, CheckpointRng, Network, NetworkRng, Parameters, Scalar, VaiError};
 
-use std::io::{Lines, Write};
-use std::{fmt::Display, fs::File};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
 
 extern crate nalgebra as na;
-use na::{DMatrix, RowDVector};
+use na::{DMatrix, DVector, RowDVector};
 extern crate rand;
+use rand::Rng;
 use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
 
-/// Creates a random variation of a matrix
+/// Creates a random variation of a matrix, using the thread rng
 /// * original - The matrix that will be varied
 /// * intensity - The severity to which the matrix will be randomized.
 ///
 /// Intensity affects the random distribution to favor smaller values, but the
 /// resulting matrix can still be changed by an arbitrary amount.
-pub fn create_variant(
-    original: &na::DMatrix<f32>,
+///
+/// The result can't be reproduced. Use [`create_variant_with_rng`]
+/// with a seeded rng for deterministic results.
+pub fn create_variant<T: Scalar>(
+    original: &na::DMatrix<T>,
     intensity: f32
-) -> na::DMatrix<f32> {
-    // Adding two variances increases the odds of small changes,
-    // but also makes larger infrequent changes possible
-    let mut variance = DMatrix::<f32>::new_random(original.shape().0, original.shape().1);
-    variance.apply(|x| *x = infinite_map(*x));
-    return original + variance.scale(intensity);
+) -> na::DMatrix<T> {
+    return create_variant_with_rng(&mut rand::thread_rng(), original, intensity);
 }
 
-/// Creates a random variation of a matrix using a provided StdRng
+/// Creates a random variation of a matrix (or vector) using a provided rng
+/// * rng - Any rng, such as a network's rng or a fast PCG or Xoshiro
 /// * original - The matrix that will be varied
 /// * intensity - The severity to which the matrix will be randomized.
 ///
 /// Intensity affects the random distribution to favor smaller values, but the
 /// resulting matrix can still be changed by an arbitrary amount.
-pub fn create_variant_stdrng(
-    rng: &mut StdRng,
-    original: &na::DMatrix<f32>,
+pub fn create_variant_with_rng<T: Scalar, G: Rng + ?Sized, R: na::Dim, C: na::Dim>(
+    rng: &mut G,
+    original: &na::OMatrix<T, R, C>,
+    intensity: f32,
+) -> na::OMatrix<T, R, C>
+where
+    na::DefaultAllocator: na::allocator::Allocator<T, R, C>,
+{
+    return mutate(rng, original, intensity, MutationConfig::default());
+}
+
+/// Creates a random variation of a matrix (or vector) using a provided rng
+#[deprecated(note = "renamed to create_variant_with_rng, which takes any rng")]
+pub fn create_variant_stdrng<T: Scalar, G: Rng + ?Sized, R: na::Dim, C: na::Dim>(
+    rng: &mut G,
+    original: &na::OMatrix<T, R, C>,
     intensity: f32,
-) -> na::DMatrix<f32> {
-    let mut result = original.clone_owned();
-    result.apply(|x| *x += intensity * infinite_map(rng.gen::<f32>()));
-    return result;
+) -> na::OMatrix<T, R, C>
+where
+    na::DefaultAllocator: na::allocator::Allocator<T, R, C>,
+{
+    return create_variant_with_rng(rng, original, intensity);
 }
 
 /// Writes a matrix to a file with space-delimited columns,
 /// newline delimited rows, and a trailing newline.
 /// * matrix - The matrix to write
 /// * file - The file to write to
-pub fn write_matrix(
-    matrix: &DMatrix<f32>,
-    file: &mut File,
-) -> std::io::Result<()> {
+pub fn write_matrix<T: Scalar>(
+    matrix: &DMatrix<T>,
+    file: &mut impl Write,
+) -> Result<(), VaiError> {
     writeln!(file, "{}", matrix.shape().0)?;
     for row in matrix.row_iter() {
         for val in &row {
@@ -62,74 +85,110 @@ pub fn write_matrix(
     return Ok(());
 }
 
-/// Reads a matrix from lines of a file with space-delimited columns,
-/// and newline delimited rows. Empty (whitespace) lines are ignored.
+/// Reads a matrix from lines of a file, as written by [`write_matrix`]:
+/// a line with the number of rows, followed by the rows, with space-delimited
+/// columns. Empty (whitespace) lines are ignored.
 /// * lines - A line iterator from which to read the matrix
-/// (generally provided by BufReader::new(file).lines())
-pub fn read_matrix(
-    lines: &mut Lines<std::io::BufReader<File>>,
-) -> std::io::Result<DMatrix<f32>> {
-    use std::io::{Error, Result, ErrorKind};
-    let row_line_error = || Error::new(ErrorKind::Other, "Bad Row Count");
-    let parse_float_error = || Error::new(ErrorKind::Other, "Error parsing float");
-    let row_line_error_result = || Result::Err(row_line_error());
-
-    let first_line = lines.next().unwrap_or(row_line_error_result())?;
-    let num_rows: usize = first_line.parse().map_err(|_| row_line_error())?;
-    let mut row_count = 0;
-    let mut rows = Vec::<RowDVector<f32>>::new();
-    for line in lines {
-        let line = line?;
-        let vals: Vec<f32> = line
-            .split_whitespace()
-            .map(|val| {
-                val.parse::<f32>().map_err(|_| parse_float_error())
-            })
-            .collect::<Result<Vec<f32>>>()?;
-        let row = RowDVector::from_row_slice(&vals);
+///   (generally provided by BufReader::new(file).lines())
+pub fn read_matrix<T: Scalar>(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) -> Result<DMatrix<T>, VaiError> {
+    return read_layer_matrix(&mut LineReader::new(lines), 0);
+}
 
-        //skip empty rows
-        if !row.is_empty() {
-            rows.push(row);
-            row_count += 1;
-        }
+/// Reads a matrix, as [`read_matrix`] does, reporting errors as part of a layer
+/// * lines - The lines from which to read the matrix
+/// * layer - The index of the layer, for error reporting
+pub(crate) fn read_layer_matrix<T: Scalar, L>(
+    lines: &mut LineReader<L>,
+    layer: usize,
+) -> Result<DMatrix<T>, VaiError>
+where
+    L: Iterator<Item = io::Result<String>>,
+{
+    let first_line = lines.expect_nonempty_line()?;
+    let num_rows: usize = lines.parse(&first_line, first_line.trim())?;
+    let mut rows = Vec::<RowDVector<T>>::new();
+    for _ in 0..num_rows {
+        let columns = rows.first().map(|x| x.len());
+        let row = format::read_row::<T, L>(lines, layer, columns)?;
+        rows.push(RowDVector::from_row_slice(&row));
+    }
+    if rows.is_empty() {
+        return Ok(DMatrix::zeros(0, 0));
+    }
+    return Ok(DMatrix::<T>::from_rows(&rows));
+}
 
-        if row_count == num_rows {
-            break;
-        }
+/// Reusable buffers for [`VAID::process_with`], holding the value of each
+/// layer, so that inference does not allocate once they fit the network
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workspace<T: Scalar = f32> {
+    layers: Vec<DVector<T>>,
+}
+
+impl<T: Scalar> Default for Workspace<T> {
+    fn default() -> Self {
+        return Self {layers: Vec::new()};
+    }
+}
+
+impl<T: Scalar> Workspace<T> {
+    /// Creates an empty workspace, which grows to fit the first network
+    /// it is used with
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Creates a workspace that already fits a network
+    /// * ai - The network the workspace will be used with
+    pub fn for_network<R: NetworkRng>(ai: &VAID<T, R>) -> Self {
+        let layers = ai.connections.iter().map(|x| DVector::zeros(x.nrows())).collect();
+        return Self {layers};
     }
-    return Ok(DMatrix::<f32>::from_rows(&rows));
 }
 
 /// Very Artificial Intelligence Dynamic
 ///
 /// Much like VAI, but with dynamically allocated layers
+///
+/// Each matrix of connections has a bias that is added to its result,
+/// and an activation that is then applied, so `biases` and `activations`
+/// should be the same length as `connections`.
+/// By default, hidden layers use [`Activation::ReLU`],
+/// and the output layer uses [`Activation::Identity`].
+///
+/// Weights are [`f32`]s by default, but can be any [`Scalar`], and the rng
+/// is a [`StdRng`] by default, but can be any [`NetworkRng`].
 #[derive(Clone, PartialEq)]
-pub struct VAID {
-    pub rng: StdRng,
-    pub connections: Vec<DMatrix<f32>>
+pub struct VAID<T: Scalar = f32, R: NetworkRng = StdRng> {
+    pub rng: R,
+    pub connections: Vec<DMatrix<T>>,
+    pub biases: Vec<DVector<T>>,
+    pub activations: Vec<Activation>,
 }
 
-impl Display for VAID
+impl<T: Scalar, R: NetworkRng> Display for VAID<T, R>
 {
     /// Concatenates the string representations of the input,
-    /// hidden layer, and output matricies.
+    /// hidden layer, and output matricies, each followed by its bias.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for mat in &self.connections {
-            write!(f, "{}", mat)?;
+        for (mat, bias) in self.connections.iter().zip(&self.biases) {
+            write!(f, "{}{}", mat, bias)?;
         }
         write!(f, "")
     }
 }
 
+// Constructors without a scalar type use f32 weights and the default rng
 impl VAID {
     /// Creates a VAID with zeros for all connection weights,
     /// using a random seed for random number generatoin.
     ///
     /// * layers - The number of neurons in each layer, starting with
-    /// the number of input nodes, and ending with the number of output nodes.
-    /// If this does not have at least two layers, the inputs will
-    /// be mapped directly to outputs.
+    ///   the number of input nodes, and ending with the number of output nodes.
+    ///   If this does not have at least two layers, the inputs will
+    ///   be mapped directly to outputs.
     pub fn new(layers: &[usize]) -> Self {
         Self::new_deterministic(rand::random(), layers)
     }
@@ -138,15 +197,108 @@ impl VAID {
     /// using a specific seed for random number generatoin.
     ///
     /// * layers - The number of neurons in each layer, starting with
-    /// the number of input nodes, and ending with the number of output nodes.
-    /// If this does not have at least two layers, the inputs will
-    /// be mapped directly to outputs.
+    ///   the number of input nodes, and ending with the number of output nodes.
+    ///   If this does not have at least two layers, the inputs will
+    ///   be mapped directly to outputs.
     pub fn new_deterministic(seed: u64, layers: &[usize]) -> Self {
-        let mut connections = Vec::<DMatrix<f32>>::new();
+        return Self::with_seed(seed, layers);
+    }
+}
+
+// Backpropagation uses f32 weights
+impl<R: NetworkRng> VAID<f32, R> {
+    /// Runs an input matrix through the neural network, keeping the value of
+    /// every layer for use by [`VAID::backward`]
+    /// * inputs - The inputs, with one sample per column
+    ///
+    /// see also:
+    ///  * [`VAID::train_step`]
+    pub fn forward(&self, inputs: &na::DMatrix<f32>) -> ForwardCache {
+        return backprop::forward(&self.layer_refs(), inputs);
+    }
+
+    /// Calculates the gradient of a loss with respect to every connection
+    /// and bias, by propagating the error backwards through the network
+    /// * cache - The result of [`VAID::forward`]
+    /// * targets - The desired outputs, with one sample per column
+    /// * loss - How the error is measured
+    ///
+    /// Returns the loss, and its gradients.
+    pub fn backward(
+        &self,
+        cache: &ForwardCache,
+        targets: &na::DMatrix<f32>,
+        loss: Loss,
+    ) -> (f32, Gradients) {
+        return backprop::backward(&self.layer_refs(), cache, targets, loss);
+    }
+
+    /// Trains the network by one step of gradient descent,
+    /// using [`Loss::MeanSquaredError`]
+    /// * inputs - The inputs, with one sample per column
+    /// * targets - The desired outputs, with one sample per column
+    /// * learning_rate - Scaler for the gradient
+    ///
+    /// Returns the loss from before the step.
+    ///
+    /// see also:
+    ///  * [`VAID::train_step_with`]
+    pub fn train_step(
+        &mut self,
+        inputs: &na::DMatrix<f32>,
+        targets: &na::DMatrix<f32>,
+        learning_rate: f32,
+    ) -> f32 {
+        return self.train_step_with(inputs, targets, learning_rate, Loss::MeanSquaredError);
+    }
+
+    /// Trains the network by one step of gradient descent
+    /// * inputs - The inputs, with one sample per column
+    /// * targets - The desired outputs, with one sample per column
+    /// * learning_rate - Scaler for the gradient
+    /// * loss - How the error is measured
+    ///
+    /// Returns the loss from before the step.
+    pub fn train_step_with(
+        &mut self,
+        inputs: &na::DMatrix<f32>,
+        targets: &na::DMatrix<f32>,
+        learning_rate: f32,
+        loss: Loss,
+    ) -> f32 {
+        return Trainable::descend(self, inputs, targets, learning_rate, loss);
+    }
+
+    /// Borrows each layer for the forward and backward passes
+    fn layer_refs(&self) -> Vec<LayerRef<'_>> {
+        return (0..self.connections.len())
+            .map(|i| LayerRef {
+                connections: self.connections[i].as_view(),
+                bias: self.biases[i].as_view(),
+                activation: self.activations[i],
+            })
+            .collect();
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> VAID<T, R> {
+    /// Creates a VAID with zeros for all connection weights, using a specific
+    /// seed for random number generatoin, for any scalar type.
+    /// For f32 weights, this is the same as [`VAID::new_deterministic`].
+    ///
+    /// * layers - The number of neurons in each layer, starting with
+    ///   the number of input nodes, and ending with the number of output nodes.
+    ///   If this does not have at least two layers, the inputs will
+    ///   be mapped directly to outputs.
+    pub fn with_seed(seed: u64, layers: &[usize]) -> Self {
+        let mut connections = Vec::<DMatrix<T>>::new();
+        let mut biases = Vec::<DVector<T>>::new();
         for i in 0..layers.len()-1 {
-            connections.push(DMatrix::<f32>::zeros(layers[i + 1], layers[i]));
+            connections.push(DMatrix::<T>::zeros(layers[i + 1], layers[i]));
+            biases.push(DVector::<T>::zeros(layers[i + 1]));
         }
-        return Self {rng: StdRng::seed_from_u64(seed), connections}
+        let activations = default_activations(connections.len());
+        return Self {rng: R::seed_from_u64(seed), connections, biases, activations}
     }
 
     /// Creates a random variant of this VAI
@@ -154,21 +306,39 @@ impl VAID {
     ///
     /// Intensity affects the random distribution to favor low magnitude
     /// values, but the result can still be changed by an arbitrary amount.
-    /// Randomness is applied to each weight of each connection.
+    /// Randomness is applied to each weight of each connection,
+    /// and to each bias.
     ///
     /// In order to keep variation fairly consistent on neural networks
     /// of various sizes, the intensity is scaled down by the number of
     /// connections in the network before being applied.
     ///
     /// see also:
-    ///  * [`create_variant_stdrng`]
+    ///  * [`create_variant_with_rng`]
+    ///  * [`VAID::create_variant_with`]
     ///  * [`VAID::create_layer_variant`]
     pub fn create_variant(&mut self, intensity: f32) -> Self {
+        return self.create_variant_with(intensity, MutationConfig::default());
+    }
+
+    /// Creates a random variant of this VAID, with a chosen distribution and
+    /// probability for the change to each weight
+    /// * intensity - Scaler for the added randomness
+    /// * mutation - How each weight is changed
+    ///
+    /// The intensity is scaled in the same way as [`VAID::create_variant`],
+    /// and weights that the mutation skips are left unchanged.
+    ///
+    /// see also:
+    ///  * [`MutationConfig`]
+    ///  * [`VAID::create_layer_variant_with`]
+    pub fn create_variant_with(&mut self, intensity: f32, mutation: MutationConfig) -> Self {
         let mut result = self.clone();
         let fields: usize = self.connections.iter().map(|x| x.len()).sum();
         let s_intensity = intensity / (1.0 + fields as f32);
-        for mat in &mut result.connections {
-            *mat = create_variant_stdrng(&mut self.rng, mat, s_intensity);
+        for (mat, bias) in result.connections.iter_mut().zip(&mut result.biases) {
+            *mat = mutate(&mut self.rng, mat, s_intensity, mutation);
+            *bias = mutate(&mut self.rng, bias, s_intensity, mutation);
         }
         return result;
     }
@@ -178,120 +348,790 @@ impl VAID {
     ///
     /// Intensity affects the random distribution to favor low magnitude
     /// values, but the result can still be changed by an arbitrary amount.
-    /// Randomness is applied to each weight of each connection on a randomly
-    /// chosen layer.
+    /// Randomness is applied to each weight of each connection, and to each
+    /// bias, on a randomly chosen layer.
     ///
     /// In order to keep variation fairly consistent on neural networks
     /// of various sizes, the intensity is scaled down by the number of
     /// connections in the chosen layer before being applied.
     ///
     /// see also:
-    ///  * [`create_variant_stdrng`]
+    ///  * [`create_variant_with_rng`]
+    ///  * [`VAID::create_layer_variant_with`]
     pub fn create_layer_variant(&mut self, intensity: f32) -> Self {
+        return self.create_layer_variant_with(intensity, MutationConfig::default());
+    }
+
+    /// Creates a random variant of this VAID that only changes one layer,
+    /// with a chosen distribution and probability for the change to each weight
+    /// * intensity - Scaler for the added randomness
+    /// * mutation - How each weight is changed
+    ///
+    /// The intensity is scaled in the same way as [`VAID::create_layer_variant`],
+    /// and weights that the mutation skips are left unchanged.
+    /// A VAID with no layers is returned unchanged.
+    ///
+    /// see also:
+    ///  * [`MutationConfig`]
+    pub fn create_layer_variant_with(&mut self, intensity: f32, mutation: MutationConfig) -> Self {
         let mut result = self.clone();
-        let layer = rand_index(result.connections.len());
+        if self.connections.is_empty() {
+            return result;
+        }
+        let layer = self.rng.gen_range(0..self.connections.len());
         let original = &self.connections[layer];
         let intensity = intensity / (original.len() + 1) as f32;
         result.connections[layer] =
-            create_variant_stdrng(&mut self.rng, original, intensity);
+            mutate(&mut self.rng, original, intensity, mutation);
+        result.biases[layer] =
+            mutate(&mut self.rng, &self.biases[layer], intensity, mutation);
+        return result;
+    }
+
+    /// Creates a variant of this VAID with a different structure, by applying
+    /// one randomly chosen structural mutation:
+    /// adding or removing a neuron, or inserting or removing a hidden layer.
+    ///
+    /// Each mutation preserves the function of the network as closely as
+    /// it can, so evolution can grow or shrink the network gradually.
+    ///
+    /// see also:
+    ///  * [`VAID::add_neuron`]
+    ///  * [`VAID::remove_neuron`]
+    ///  * [`VAID::insert_layer`]
+    ///  * [`VAID::remove_layer`]
+    pub fn create_structural_variant(&mut self) -> Self {
+        let mut result = self.clone();
+        let hidden_layers = self.connections.len().saturating_sub(1);
+        let shrinkable: Vec<usize> =
+            (0..hidden_layers).filter(|&i| self.connections[i].nrows() > 1).collect();
+        enum StructuralChange {
+            AddNeuron,
+            RemoveNeuron,
+            InsertLayer,
+            RemoveLayer,
+        }
+        let mut changes = Vec::new();
+        if hidden_layers > 0 {
+            changes.extend([StructuralChange::AddNeuron, StructuralChange::RemoveLayer]);
+        }
+        if !shrinkable.is_empty() {
+            changes.push(StructuralChange::RemoveNeuron);
+        }
+        if !self.connections.is_empty() {
+            changes.push(StructuralChange::InsertLayer);
+        }
+        if changes.is_empty() {
+            return result;
+        }
+        match changes[self.rng.gen_range(0..changes.len())] {
+            StructuralChange::AddNeuron => result.add_neuron(self.rng.gen_range(0..hidden_layers)),
+            StructuralChange::RemoveNeuron => {
+                let layer = shrinkable[self.rng.gen_range(0..shrinkable.len())];
+                let neuron = self.rng.gen_range(0..self.connections[layer].nrows());
+                result.remove_neuron(layer, neuron);
+            }
+            StructuralChange::InsertLayer => result.insert_layer(self.rng.gen_range(0..self.connections.len())),
+            StructuralChange::RemoveLayer => result.remove_layer(self.rng.gen_range(0..hidden_layers)),
+        }
+        return result;
+    }
+
+    /// Adds a neuron to the end of a hidden layer.
+    /// The new neuron gets small random input weights, but its output
+    /// weights are zero, so the network's outputs are unchanged.
+    /// * layer - The hidden layer, where 0 is the output of the first
+    ///   connection matrix
+    ///
+    /// Panics if there is no such hidden layer.
+    pub fn add_neuron(&mut self, layer: usize) {
+        assert!(layer + 1 < self.connections.len(), "no hidden layer {}", layer);
+        let incoming = &self.connections[layer];
+        let (rows, columns) = incoming.shape();
+        let scale = 1. / (1 + columns) as f32;
+        let row = na::RowDVector::<T>::from_fn(columns, |_, _| {
+            T::of_f32(scale * infinite_map(self.rng.gen::<f32>()))
+        });
+        let mut incoming = incoming.clone().insert_row(rows, T::zero());
+        incoming.set_row(rows, &row);
+        self.connections[layer] = incoming;
+        self.biases[layer] = self.biases[layer].clone().insert_row(rows, T::zero());
+        let outgoing = self.connections[layer + 1].clone();
+        self.connections[layer + 1] = outgoing.insert_column(rows, T::zero());
+    }
+
+    /// Removes a neuron from a hidden layer.
+    /// The neuron's output is approximated by its value when its inputs are
+    /// all zero, which is added to the bias of the next layer. This is exact
+    /// if the neuron's input weights are all zero.
+    /// * layer - The hidden layer, where 0 is the output of the first
+    ///   connection matrix
+    /// * neuron - The index of the neuron within the layer
+    ///
+    /// Panics if there is no such neuron, or if it is the only neuron in its layer.
+    pub fn remove_neuron(&mut self, layer: usize, neuron: usize) {
+        assert!(layer + 1 < self.connections.len(), "no hidden layer {}", layer);
+        assert!(self.connections[layer].nrows() > 1, "can not remove the only neuron of a layer");
+        let constant = self.activations[layer].apply(self.biases[layer][neuron]);
+        let outgoing = self.connections[layer + 1].column(neuron) * constant;
+        self.biases[layer + 1] += outgoing;
+        self.connections[layer] = self.connections[layer].clone().remove_row(neuron);
+        self.biases[layer] = self.biases[layer].clone().remove_row(neuron);
+        self.connections[layer + 1] = self.connections[layer + 1].clone().remove_column(neuron);
+    }
+
+    /// Inserts a hidden layer with identity connections and no bias,
+    /// the same size as the layer before it.
+    /// If the layer before it uses [`Activation::ReLU`], so does the new
+    /// layer, otherwise it uses [`Activation::Identity`]. Either way,
+    /// the network's outputs are unchanged.
+    /// * layer - The index the new hidden layer will have,
+    ///   where 0 is the output of the first connection matrix
+    ///
+    /// Panics if `layer` is not less than the number of connection matrices.
+    pub fn insert_layer(&mut self, layer: usize) {
+        assert!(layer < self.connections.len(), "can not insert hidden layer {}", layer);
+        let (size, activation) = match layer {
+            0 => (self.connections[0].ncols(), Activation::Identity),
+            _ => {
+                let activation = match self.activations[layer - 1] {
+                    Activation::ReLU => Activation::ReLU,
+                    _ => Activation::Identity,
+                };
+                (self.connections[layer - 1].nrows(), activation)
+            }
+        };
+        self.connections.insert(layer, DMatrix::identity(size, size));
+        self.biases.insert(layer, DVector::zeros(size));
+        self.activations.insert(layer, activation);
+    }
+
+    /// Removes a hidden layer, by merging the connections into and out of it.
+    /// The layer's activation is dropped, so this is exact if it uses
+    /// [`Activation::Identity`] (or [`Activation::ReLU`] on non-negative values).
+    /// * layer - The hidden layer, where 0 is the output of the first
+    ///   connection matrix
+    ///
+    /// Panics if there is no such hidden layer.
+    pub fn remove_layer(&mut self, layer: usize) {
+        assert!(layer + 1 < self.connections.len(), "no hidden layer {}", layer);
+        let incoming = self.connections.remove(layer);
+        let bias = self.biases.remove(layer);
+        self.activations.remove(layer);
+        self.biases[layer] += &self.connections[layer] * bias;
+        self.connections[layer] = &self.connections[layer] * incoming;
+    }
+
+    /// Creates a child of this VAID and another, using [`Crossover::Uniform`]
+    /// * other - The other parent
+    ///
+    /// see also:
+    ///  * [`VAID::crossover_with`]
+    pub fn crossover(&mut self, other: &Self) -> Self {
+        return self.crossover_with(other, Crossover::Uniform);
+    }
+
+    /// Creates a child of this VAID and another.
+    /// The child's random number generator is cloned from this VAID,
+    /// and this VAID's random number generator is used for the crossover.
+    ///
+    /// Layers that don't have the same shape in both parents
+    /// (or only exist in this parent) are copied from this parent.
+    /// * other - The other parent
+    /// * mode - How the parents' weights are combined
+    ///
+    /// see also:
+    ///  * [`VAID::crossover`]
+    pub fn crossover_with(&mut self, other: &Self, mode: Crossover) -> Self {
+        let mut result = self.clone();
+        let rng = &mut self.rng;
+        let layers = result.connections.len().min(other.connections.len());
+        for i in 0..layers {
+            if result.connections[i].shape() != other.connections[i].shape() {
+                continue;
+            }
+            if mode == Crossover::LayerSwap {
+                if rng.gen::<bool>() {
+                    result.connections[i] = other.connections[i].clone();
+                    result.biases[i] = other.biases[i].clone();
+                    result.activations[i] = other.activations[i];
+                }
+                continue;
+            }
+            cross_layer(
+                rng,
+                mode,
+                &mut result.connections[i],
+                &mut result.biases[i],
+                &other.connections[i],
+                &other.biases[i],
+            );
+        }
         return result;
     }
 
     /// Runs an input matrix through the neural network to get an output
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// * inputs - The inputs, with one sample per column
+    ///
+    /// Returns one column of outputs per column of inputs.
     ///
     /// see also:
     ///  * [`VAIN::process_slice`]
     ///  * [`VAIN::process_transparent`]
-    pub fn process(&self, inputs: &na::DMatrix<f32>) -> na::DMatrix<f32> {
+    ///  * [`VAID::process_batch`]
+    pub fn process(&self, inputs: &na::DMatrix<T>) -> na::DMatrix<T> {
         let mut intermediate = inputs.clone();
-        if let Some((last, first)) = self.connections.split_last() {
-            for mat in first {
-                intermediate = mat * intermediate;
-                // Apply relu
-                intermediate.apply(|x| *x = x.max(0.));
-            }
-            return last * intermediate;
+        for (i, mat) in self.connections.iter().enumerate() {
+            let activation = &self.activations[i];
+            intermediate = mat * intermediate;
+            add_bias(&mut intermediate, &self.biases[i]);
+            intermediate.apply(|x| *x = activation.apply(*x));
         }
-        return inputs.clone();
+        return intermediate;
     }
 
     /// Runs an input slice through the neural network to get an output
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// * inputs - The inputs of a single sample
+    ///
+    /// Panics if the number of inputs does not match the first layer.
     ///
     /// see also:
     ///  * [`VAIN::process`]
-    pub fn process_slice(&self, inputs: &[f32]) -> Vec<f32> {
-        let matrix_inputs = na::DMatrix::<f32>::from_columns(&[
-            na::DVector::<f32>::from_column_slice(inputs)]);
+    ///  * [`VAID::try_process_slice`]
+    pub fn process_slice(&self, inputs: &[T]) -> Vec<T> {
+        let matrix_inputs = na::DMatrix::<T>::from_columns(&[
+            na::DVector::<T>::from_column_slice(inputs)]);
         let output = self.process(&matrix_inputs);
         output.iter().map(|x| x.to_owned()).collect()
     }
 
+    /// Runs a batch of inputs through the neural network to get their outputs,
+    /// running each layer as a single matrix product
+    /// * inputs - The inputs, with one sample per column
+    ///
+    /// Returns one column of outputs per column of inputs.
+    /// Panics if the number of rows does not match the first layer.
+    ///
+    /// see also:
+    ///  * [`VAID::process_batch_slice`]
+    pub fn process_batch(&self, inputs: &na::DMatrix<T>) -> na::DMatrix<T> {
+        if let Some(first) = self.connections.first() {
+            assert_eq!(inputs.nrows(), first.ncols(), "wrong number of inputs");
+        }
+        return self.process(inputs);
+    }
+
+    /// Runs a batch of input slices through the neural network,
+    /// as [`VAID::process_batch`] does
+    /// * inputs - The inputs of each sample, which must all be the same length
+    ///
+    /// Returns the outputs of each sample.
+    pub fn process_batch_slice(&self, inputs: &[&[T]]) -> Vec<Vec<T>> {
+        let rows = inputs.first().map_or(0, |x| x.len());
+        let values: Vec<T> = inputs.iter().flat_map(|x| {
+            assert_eq!(x.len(), rows, "every sample must have the same number of inputs");
+            x.iter().copied()
+        }).collect();
+        let matrix_inputs = na::DMatrix::from_vec(rows, inputs.len(), values);
+        let output = self.process_batch(&matrix_inputs);
+        return output.column_iter().map(|x| x.iter().copied().collect()).collect();
+    }
+
+    /// Runs an input slice through the neural network to get an output,
+    /// like [`VAID::process_slice`], but keeps the value of each layer in
+    /// a workspace, so nothing is allocated once the workspace fits the network.
+    /// * inputs - The inputs of a single sample
+    /// * workspace - The buffers used for each layer
+    ///
+    /// Returns the outputs, which are kept in the workspace.
+    /// Panics if the number of inputs does not match the first layer.
+    pub fn process_with<'a>(&self, inputs: &[T], workspace: &'a mut Workspace<T>) -> &'a [T] {
+        let layers = &mut workspace.layers;
+        if self.connections.is_empty() {
+            // The inputs are mapped directly to outputs
+            layers.resize_with(1, || DVector::zeros(0));
+            if layers[0].len() != inputs.len() {
+                layers[0] = DVector::zeros(inputs.len());
+            }
+            layers[0].copy_from_slice(inputs);
+            return layers[0].as_slice();
+        }
+        assert_eq!(inputs.len(), self.connections[0].ncols(), "wrong number of inputs");
+        layers.resize_with(self.connections.len(), || DVector::zeros(0));
+        for (i, mat) in self.connections.iter().enumerate() {
+            let (previous, rest) = layers.split_at_mut(i);
+            let output = &mut rest[0];
+            if output.len() != mat.nrows() {
+                *output = DVector::zeros(mat.nrows());
+            }
+            match previous.last() {
+                Some(input) => output.gemv(T::one(), mat, input, T::zero()),
+                None => {
+                    let input = na::DVectorView::from_slice(inputs, inputs.len());
+                    output.gemv(T::one(), mat, &input, T::zero());
+                }
+            }
+            let activation = self.activations[i];
+            for (x, bias) in output.iter_mut().zip(self.biases[i].iter()) {
+                *x = activation.apply(*x + *bias);
+            }
+        }
+        return layers.last().unwrap().as_slice();
+    }
+
+    /// Runs an input matrix through the neural network to get an output,
+    /// like [`VAID::process`], but fails instead of panicking if the inputs
+    /// do not match the first layer, or the layers do not line up.
+    /// * inputs - The inputs, with one sample per column
+    /// * require_finite - Whether to fail if any input is NaN or infinite
+    ///
+    /// see also:
+    ///  * [`VAID::try_process_slice`]
+    pub fn try_process(
+        &self,
+        inputs: &na::DMatrix<T>,
+        require_finite: bool,
+    ) -> Result<na::DMatrix<T>, VaiError> {
+        let expected = self.validate()?.first().copied().unwrap_or(inputs.nrows());
+        check_inputs(inputs.nrows(), expected, inputs.as_slice(), require_finite)?;
+        return Ok(self.process(inputs));
+    }
+
+    /// Runs an input slice through the neural network to get an output,
+    /// like [`VAID::process_slice`], but fails instead of panicking.
+    /// * inputs - The inputs
+    /// * require_finite - Whether to fail if any input is NaN or infinite
+    ///
+    /// see also:
+    ///  * [`VAID::try_process`]
+    pub fn try_process_slice(
+        &self,
+        inputs: &[T],
+        require_finite: bool,
+    ) -> Result<Vec<T>, VaiError> {
+        let matrix_inputs = na::DMatrix::<T>::from_column_slice(inputs.len(), 1, inputs);
+        let output = self.try_process(&matrix_inputs, require_finite)?;
+        return Ok(output.as_slice().to_vec());
+    }
+
     /// Runs an input matrix through the neural network to get an output
     /// returning the value of all the nodes: input, hidden, and output.
     ///
     /// Note: the value of hidden nodes is supplie *before*
-    /// relu to preserve information
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// their activation to preserve information.
+    /// The output nodes are supplied after the output activation.
+    /// * inputs - The inputs, with one sample per column
     ///
     /// see also:
     ///  * [`VAIN::process_slice_transparent`]
-    pub fn process_transparent(&self, inputs: &na::DMatrix<f32>) -> Vec<Vec<f32>> {
-        let mut output: Vec<Vec<f32>> = vec![inputs.iter().map(|x| x.to_owned()).collect()];
+    pub fn process_transparent(&self, inputs: &na::DMatrix<T>) -> Vec<Vec<T>> {
+        let mut output: Vec<Vec<T>> = vec![inputs.iter().map(|x| x.to_owned()).collect()];
         let mut intermediate = inputs.clone();
-        if let Some((last, first)) = self.connections.split_last() {
-            for mat in first {
-                intermediate = mat * intermediate;
+        let layers = self.connections.len();
+        for (i, mat) in self.connections.iter().enumerate() {
+            let activation = &self.activations[i];
+            intermediate = mat * intermediate;
+            add_bias(&mut intermediate, &self.biases[i]);
+            if i + 1 < layers {
                 output.push(intermediate.iter().map(|x| x.to_owned()).collect());
-                // Apply relu
-                intermediate.apply(|x| *x = x.max(0.));
             }
-            let out = last * intermediate;
-            output.push(out.iter().map(|x| x.to_owned()).collect());
+            intermediate.apply(|x| *x = activation.apply(*x));
+        }
+        if layers > 0 {
+            output.push(intermediate.iter().map(|x| x.to_owned()).collect());
         }
         return output;
     }
 
     /// Runs an input slice through the neural network to get an output
     /// returning the value of all the nodes: input, hidden, and output.
-    /// * inputs - The inputs. One of them should be a constant for a bias.
+    /// * inputs - The inputs, with one sample per column
     ///
     /// see also:
     ///  * [`VAIN::process_transparent`]
-    pub fn process_slice_transparent(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
-        let matrix_inputs = na::DMatrix::<f32>::from_columns(&[
-            na::DVector::<f32>::from_column_slice(inputs)]);
+    pub fn process_slice_transparent(&self, inputs: &[T]) -> Vec<Vec<T>> {
+        let matrix_inputs = na::DMatrix::<T>::from_columns(&[
+            na::DVector::<T>::from_column_slice(inputs)]);
         return self.process_transparent(&matrix_inputs);
     }
 
-    /// Writes a vai to a file, writing its input, hidden, and output
-    /// connections in order, as written by [`write_matrix`]
-    /// * lines - A line iterator from which to read the vai
-    /// (generally provided by BufReader::new(file).lines())
-    pub fn write(&self, file: &mut File) -> std::io::Result<()> {
-        writeln!(file, "{}", self.connections.len())?;
-        for matrix in &self.connections {
-            write_matrix(matrix, file)?;
+    /// Checks that the layers line up, and that there is a bias and activation
+    /// for each of them. Returns the number of neurons in each layer.
+    pub(crate) fn validate(&self) -> Result<Vec<usize>, VaiError> {
+        let layers = self.layer_sizes()?;
+        if self.biases.len() != self.connections.len()
+            || self.activations.len() != self.connections.len()
+        {
+            return Err(VaiError::Format(
+                "There must be one bias and activation per connection matrix".to_owned(),
+            ));
+        }
+        for (i, (matrix, bias)) in self.connections.iter().zip(&self.biases).enumerate() {
+            if bias.len() != matrix.nrows() {
+                let (expected, found) = (matrix.nrows(), bias.len());
+                return Err(VaiError::DimensionMismatch { layer: i, expected, found });
+            }
+        }
+        return Ok(layers);
+    }
+
+    /// The header describing this vai's shape and activations.
+    /// Fails if the connection matrices do not line up.
+    /// * metadata - Optional information to store alongside the vai
+    fn header(&self, metadata: &Metadata) -> Result<Header, VaiError> {
+        return Ok(Header {
+            version: format::VERSION,
+            kind: NetworkKind::VAID,
+            layers: self.layer_sizes()?,
+            activations: self.activations.clone(),
+            metadata: metadata.clone(),
+            rng: None,
+        });
+    }
+
+    /// The number of neurons in each layer, starting with the inputs.
+    /// Fails if the connection matrices do not line up.
+    pub(crate) fn layer_sizes(&self) -> Result<Vec<usize>, VaiError> {
+        let mut layers: Vec<usize> = self.connections.first().map(|x| x.ncols()).into_iter().collect();
+        for (i, matrix) in self.connections.iter().enumerate() {
+            let expected = *layers.last().unwrap();
+            if matrix.ncols() != expected {
+                return Err(VaiError::DimensionMismatch { layer: i, expected, found: matrix.ncols() });
+            }
+            layers.push(matrix.nrows());
+        }
+        return Ok(layers);
+    }
+
+    /// Writes a vai to a file in the format described in [`crate::format`]:
+    /// a header, followed by each layer in order.
+    /// * file - The file to write to
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    ///
+    /// see also:
+    ///  * [`VAID::write_with_metadata`]
+    pub fn write(&self, file: &mut impl Write) -> Result<(), VaiError> {
+        return self.write_with_metadata(file, &Metadata::default());
+    }
+
+    /// Writes a vai to a file, like [`VAID::write`], with metadata in its header
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    pub fn write_with_metadata(
+        &self,
+        file: &mut impl Write,
+        metadata: &Metadata,
+    ) -> Result<(), VaiError> {
+        self.header(metadata)?.write(file)?;
+        for (matrix, bias) in self.connections.iter().zip(&self.biases) {
+            format::write_layer(file, matrix, bias.as_slice())?;
         }
         return Ok(());
     }
 
-    /// Reads a matrix from lines of a file containing its input, hidden, and
-    /// output connections in order, as read by [`read_matrix`]
+    /// Reads a vai from lines of a file, as written by [`VAID::write`].
+    /// Fails if the file holds a different kind of network.
+    ///
+    /// Files without a header are read in the legacy layout: the number of
+    /// matrices, followed by each matrix in order, as read by [`read_matrix`].
+    /// There, layers without an activation line use the default activations,
+    /// and layers without a bias line have no bias.
+    /// * lines - A line iterator from which to read the vai
+    ///   (generally provided by BufReader::new(file).lines())
+    pub fn read(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Self, VaiError> {
+        return Ok(Self::read_with_metadata(lines)?.0);
+    }
+
+    /// Reads a vai like [`VAID::read`], along with the metadata in its header.
+    /// Files in the legacy layout have no metadata.
     /// * lines - A line iterator from which to read the vai
-    /// (generally provided by BufReader::new(file).lines())
-    pub fn read(lines: &mut Lines<std::io::BufReader<File>>) -> std::io::Result<Self> {
-        use std::io::{Error, Result, ErrorKind};
-        let row_line_error = || Error::new(ErrorKind::Other, "Bad Row Count");
-        let row_line_error_result = || Result::Err(row_line_error());
-        let mut connections = Vec::<DMatrix<f32>>::new();
-
-        let first_line = lines.next().unwrap_or(row_line_error_result())?;
-        let num_matrices: usize = first_line.parse().map_err(|_| row_line_error())?;
-        for _ in 0..num_matrices {
-            connections.push(read_matrix(lines)?);
+    pub fn read_with_metadata(
+        lines: &mut impl Iterator<Item = io::Result<String>>,
+    ) -> Result<(Self, Metadata), VaiError> {
+        let mut lines = LineReader::new(lines);
+        let header = match Header::read(&mut lines)? {
+            Some(header) => header,
+            None => return Ok((Self::read_legacy(&mut lines)?, Metadata::default())),
+        };
+        header.expect_kind(NetworkKind::VAID)?;
+        let mut connections = Vec::<DMatrix<T>>::new();
+        let mut biases = Vec::<DVector<T>>::new();
+        for (i, sizes) in header.layers.windows(2).enumerate() {
+            let (matrix, bias) = format::read_layer(&mut lines, i, sizes[1], sizes[0])?;
+            connections.push(matrix);
+            biases.push(DVector::from_vec(bias));
+        }
+        let rng = R::seed_from_u64(rand::random());
+        let activations = header.activations;
+        return Ok((Self {rng, connections, biases, activations}, header.metadata));
+    }
+
+    /// Reads a vai from a reader, as written by [`VAID::write`]
+    /// * reader - The reader from which to read the vai
+    pub fn read_from(reader: &mut impl BufRead) -> Result<Self, VaiError> {
+        return Self::read(&mut reader.lines());
+    }
+
+    /// Writes a vai to a new file at the given path, as [`VAID::write`] does
+    /// * path - The path of the file to create
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), VaiError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()?;
+        return Ok(());
+    }
+
+    /// Reads a vai from the file at the given path, as [`VAID::read`] does
+    /// * path - The path of the file to read
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VaiError> {
+        return Self::read_from(&mut BufReader::new(File::open(path)?));
+    }
+
+    /// Writes a vai to a string, as [`VAID::write`] does
+    ///
+    /// see also:
+    ///  * [`VAID::from_str`]
+    pub fn to_text(&self) -> Result<String, VaiError> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)?;
+        return Ok(String::from_utf8(buffer).expect("numbers and keywords are valid utf-8"));
+    }
+
+    /// Writes a vai in the binary format described in [`crate::format`].
+    /// Unlike the text format, every weight is stored exactly.
+    /// * file - The file to write to
+    /// * checksum - Whether to end the file with a checksum,
+    ///   which is verified when it is read
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    pub fn write_binary(&self, file: &mut impl Write, checksum: bool) -> Result<(), VaiError> {
+        return self.write_binary_with_metadata(file, &Metadata::default(), checksum);
+    }
+
+    /// Writes a vai like [`VAID::write_binary`], with metadata in its header
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    /// * checksum - Whether to end the file with a checksum
+    pub fn write_binary_with_metadata(
+        &self,
+        file: &mut impl Write,
+        metadata: &Metadata,
+        checksum: bool,
+    ) -> Result<(), VaiError> {
+        return self.write_binary_with_header(file, &self.header(metadata)?, checksum);
+    }
+
+    /// Writes a vai in the binary format, after the given header
+    fn write_binary_with_header(
+        &self,
+        file: &mut impl Write,
+        header: &Header,
+        checksum: bool,
+    ) -> Result<(), VaiError> {
+        let mut writer = BinaryWriter::new(file);
+        let encoding = Encoding::new::<T>(checksum);
+        header.write_binary(&mut writer, encoding)?;
+        for (matrix, bias) in self.connections.iter().zip(&self.biases) {
+            writer.write_scalars(matrix.as_slice(), encoding.wide)?;
+            writer.write_scalars(bias.as_slice(), encoding.wide)?;
+        }
+        return writer.finish(checksum);
+    }
+
+    /// Reads a vai as written by [`VAID::write_binary`].
+    /// Fails if the file holds a different kind of network,
+    /// or if its checksum does not match.
+    /// * file - The file from which to read the vai
+    pub fn read_binary(file: &mut impl Read) -> Result<Self, VaiError> {
+        return Ok(Self::read_binary_with_metadata(file)?.0);
+    }
+
+    /// Reads a vai like [`VAID::read_binary`], along with the metadata in its header.
+    /// The rng is seeded randomly, even if the file is a checkpoint.
+    ///
+    /// see also:
+    ///  * [`VAID::resume`]
+    /// * file - The file from which to read the vai
+    pub fn read_binary_with_metadata(file: &mut impl Read) -> Result<(Self, Metadata), VaiError> {
+        let (result, header) = Self::read_binary_with_header(file)?;
+        return Ok((result, header.metadata));
+    }
+
+    /// Reads a vai in the binary format, along with its header
+    fn read_binary_with_header(file: &mut impl Read) -> Result<(Self, Header), VaiError> {
+        let mut reader = BinaryReader::new(file);
+        let (header, encoding) = Header::read_binary(&mut reader)?;
+        header.expect_kind(NetworkKind::VAID)?;
+        let mut connections = Vec::<DMatrix<T>>::new();
+        let mut biases = Vec::<DVector<T>>::new();
+        for sizes in header.layers.windows(2) {
+            let values = reader.read_scalars(checked_size(sizes[1], sizes[0])?, encoding.wide)?;
+            connections.push(DMatrix::from_vec(sizes[1], sizes[0], values));
+            biases.push(DVector::from_vec(reader.read_scalars(sizes[1], encoding.wide)?));
+        }
+        reader.finish(encoding.checksum)?;
+        let rng = R::seed_from_u64(rand::random());
+        let activations = header.activations.clone();
+        return Ok((Self {rng, connections, biases, activations}, header));
+    }
+
+    /// Reads a vai written without a header
+    /// * lines - A peekable line iterator from which to read the vai
+    fn read_legacy<L: Iterator<Item = io::Result<String>>>(
+        lines: &mut LineReader<L>,
+    ) -> Result<Self, VaiError> {
+        let mut connections = Vec::<DMatrix<T>>::new();
+        let mut biases = Vec::<DVector<T>>::new();
+
+        let first_line = lines.expect_nonempty_line()?;
+        let num_matrices: usize = lines.parse(&first_line, first_line.trim())?;
+        let mut activations = default_activations(num_matrices);
+        for (i, activation) in activations.iter_mut().enumerate() {
+            let mut options = read_layer_options(lines)?;
+            *activation = options.activation.unwrap_or(*activation);
+            let matrix = read_layer_matrix(lines, i)?;
+            biases.push(DVector::from_vec(options.take_bias(i, matrix.nrows())?));
+            connections.push(matrix);
+        }
+        let rng = R::seed_from_u64(rand::random());
+        return Ok(Self {rng, connections, biases, activations});
+    }
+}
+
+// Checkpoints store the state of the rng, which must be a CheckpointRng
+impl<T: Scalar, R: CheckpointRng> VAID<T, R> {
+    /// Writes a checkpoint: the vai in the binary format, with a checksum,
+    /// along with the full state of its rng, so that [`VAID::resume`]
+    /// continues the exact same sequence of mutations.
+    /// The rng must be a [`CheckpointRng`], such as a [`rand_chacha::ChaCha12Rng`].
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    ///
+    /// see also:
+    ///  * [`VAID::save_checkpoint`]
+    pub fn write_checkpoint(&self, file: &mut impl Write, metadata: &Metadata) -> Result<(), VaiError> {
+        let mut header = self.header(metadata)?;
+        header.rng = Some(self.rng.state());
+        return self.write_binary_with_header(file, &header, true);
+    }
+
+    /// Writes a checkpoint to a new file at the given path,
+    /// as [`VAID::write_checkpoint`] does
+    /// * path - The path of the file to create
+    /// * metadata - Optional information to store alongside the vai
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>, metadata: &Metadata) -> Result<(), VaiError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_checkpoint(&mut file, metadata)?;
+        file.flush()?;
+        return Ok(());
+    }
+
+    /// Reads a checkpoint written by [`VAID::write_checkpoint`], restoring the
+    /// vai's rng, so that it continues the exact same sequence of mutations.
+    /// Fails if the file holds no rng state.
+    /// * file - The file from which to read the vai
+    ///
+    /// see also:
+    ///  * [`VAID::load_checkpoint`]
+    pub fn resume(file: &mut impl Read) -> Result<(Self, Metadata), VaiError> {
+        let (mut result, header) = Self::read_binary_with_header(file)?;
+        match &header.rng {
+            Some(state) => result.rng = R::from_state(state),
+            None => return Err(VaiError::Format("Missing rng state".to_owned())),
+        }
+        return Ok((result, header.metadata));
+    }
+
+    /// Reads a checkpoint from the file at the given path, as [`VAID::resume`] does
+    /// * path - The path of the file to read
+    pub fn load_checkpoint(path: impl AsRef<Path>) -> Result<(Self, Metadata), VaiError> {
+        return Self::resume(&mut BufReader::new(File::open(path)?));
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> FromStr for VAID<T, R> {
+    type Err = VaiError;
+
+    /// Reads a vai from a string, as [`VAID::read`] does
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        return Self::read(&mut text.lines().map(|x| Ok(x.to_owned())));
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> Network for VAID<T, R> {
+    type Scalar = T;
+
+    fn process_slice(&self, inputs: &[T]) -> Vec<T> {
+        return VAID::process_slice(self, inputs);
+    }
+
+    fn create_variant(&mut self, intensity: f32) -> Self {
+        return VAID::create_variant(self, intensity);
+    }
+
+    fn create_layer_variant(&mut self, intensity: f32) -> Self {
+        return VAID::create_layer_variant(self, intensity);
+    }
+
+    fn crossover_with(&mut self, other: &Self, mode: Crossover) -> Self {
+        return VAID::crossover_with(self, other, mode);
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> Parameters for VAID<T, R> {
+    fn layer_parameter_counts(&self) -> Vec<usize> {
+        let layers = self.connections.iter().zip(&self.biases);
+        return layers.map(|(mat, bias)| mat.len() + bias.len()).collect();
+    }
+
+    fn parameters(&self) -> Vec<T> {
+        let mut parameters = Vec::with_capacity(self.parameter_count());
+        for (mat, bias) in self.connections.iter().zip(&self.biases) {
+            parameters.extend_from_slice(mat.as_slice());
+            parameters.extend_from_slice(bias.as_slice());
         }
-        return Ok(Self {rng: StdRng::seed_from_u64(rand::random()), connections});
+        return parameters;
     }
-}
\ No newline at end of file
+
+    fn set_parameters(&mut self, parameters: &[T]) {
+        assert_eq!(parameters.len(), self.parameter_count(), "Wrong number of parameters");
+        let mut rest = parameters;
+        for (mat, bias) in self.connections.iter_mut().zip(&mut self.biases) {
+            let (values, remaining) = rest.split_at(mat.len());
+            mat.copy_from_slice(values);
+            let (values, remaining) = remaining.split_at(bias.len());
+            bias.copy_from_slice(values);
+            rest = remaining;
+        }
+    }
+}
+
+impl<R: NetworkRng> Trainable for VAID<f32, R> {
+    fn forward(&self, inputs: &na::DMatrix<f32>) -> ForwardCache {
+        return VAID::forward(self, inputs);
+    }
+
+    fn backward(
+        &self,
+        cache: &ForwardCache,
+        targets: &na::DMatrix<f32>,
+        loss: Loss,
+    ) -> (f32, Gradients) {
+        return VAID::backward(self, cache, targets, loss);
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut [f32]> {
+        let connections = self.connections.iter_mut().map(|x| x.as_mut_slice());
+        return connections
+            .chain(self.biases.iter_mut().map(|x| x.as_mut_slice()))
+            .collect();
+    }
+}
+
+/// Adds a bias to every column of a matrix
+fn add_bias<T: Scalar>(matrix: &mut DMatrix<T>, bias: &DVector<T>) {
+    for mut column in matrix.column_iter_mut() {
+        column += bias;
+    }
+}
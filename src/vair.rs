@@ -0,0 +1,566 @@
+#![allow(clippy::needless_return)]
+
+use crate::format::{self, BinaryReader, BinaryWriter, Encoding, Header, LineReader, Metadata};
+use crate::format::{checked_size, NetworkKind};
+use crate::crossover::{cross_layer, Crossover};
+use crate::vaid::create_variant_with_rng;
+use crate::{Activation, NetworkRng, Scalar, VaiError};
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+extern crate nalgebra as na;
+use na::{DMatrix, DVector};
+extern crate rand;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+/// The kind of recurrent cell used by a [`VAIR`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cell {
+    /// The new state is the activation of the inputs and the previous state
+    #[default]
+    Elman,
+    /// A gated recurrent unit, with update and reset gates
+    GRU,
+    /// A long short-term memory cell, with input, forget and output gates,
+    /// and a separate cell state
+    LSTM,
+}
+
+impl Cell {
+    /// The number of gates (including the candidate state),
+    /// each of which has one row per hidden neuron
+    pub fn gates(&self) -> usize {
+        return match self {
+            Cell::Elman => 1,
+            Cell::GRU => 3,
+            Cell::LSTM => 4,
+        };
+    }
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Elman => write!(f, "elman"),
+            Cell::GRU => write!(f, "gru"),
+            Cell::LSTM => write!(f, "lstm"),
+        }
+    }
+}
+
+impl FromStr for Cell {
+    type Err = VaiError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        return match name {
+            "elman" => Ok(Cell::Elman),
+            "gru" => Ok(Cell::GRU),
+            "lstm" => Ok(Cell::LSTM),
+            _ => Err(VaiError::Format(format!("Unknown cell: {}", name))),
+        };
+    }
+}
+
+/// Very Artificial Intelligence Recurrent
+///
+/// A network with one recurrent hidden layer, whose state is kept between
+/// calls to [`VAIR::step`], followed by an output layer.
+///
+/// The recurrent layer's connection matrix reads the inputs, followed by the
+/// previous hidden state. It has one block of rows per gate of its [`Cell`]:
+///  * Elman: the candidate state
+///  * GRU: the update gate, the reset gate, then the candidate state
+///  * LSTM: the input gate, the forget gate, the candidate state,
+///    then the output gate
+///
+/// Gates use [`Activation::Sigmoid`], and the candidate state uses
+/// `hidden_activation`, which is [`Activation::Tanh`] by default.
+///
+/// Weights are [`f32`]s by default, but can be any [`Scalar`], and the rng
+/// is a [`StdRng`] by default, but can be any [`NetworkRng`].
+#[derive(Clone, PartialEq)]
+pub struct VAIR<T: Scalar = f32, R: NetworkRng = StdRng> {
+    pub rng: R,
+    pub cell: Cell,
+    pub hidden_connections: DMatrix<T>,
+    pub hidden_bias: DVector<T>,
+    pub hidden_activation: Activation,
+    pub output_connections: DMatrix<T>,
+    pub output_bias: DVector<T>,
+    pub output_activation: Activation,
+    /// The hidden state, carried between calls to [`VAIR::step`]
+    pub state: DVector<T>,
+    /// The cell state of an LSTM, carried between calls to [`VAIR::step`].
+    /// Unused by other cells.
+    pub cell_state: DVector<T>,
+}
+
+impl<T: Scalar, R: NetworkRng> Display for VAIR<T, R>
+{
+    /// Concatenates the string representations of the recurrent
+    /// and output matricies, each followed by its bias.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.hidden_connections, self.hidden_bias)?;
+        write!(f, "{}{}", self.output_connections, self.output_bias)
+    }
+}
+
+// Constructors without a scalar type use f32 weights and the default rng
+impl VAIR {
+    /// Creates a VAIR with zeros for all connection weights and state,
+    /// using a random seed for random number generatoin.
+    /// * inputs - The number of inputs
+    /// * hidden - The number of neurons in the hidden state
+    /// * outputs - The number of outputs
+    /// * cell - The kind of recurrent cell
+    pub fn new(inputs: usize, hidden: usize, outputs: usize, cell: Cell) -> Self {
+        return Self::new_deterministic(rand::random(), inputs, hidden, outputs, cell);
+    }
+
+    /// Creates a VAIR with zeros for all connection weights and state,
+    /// using a specific seed for random number generatoin.
+    /// * seed - The seed for random number generation
+    /// * inputs - The number of inputs
+    /// * hidden - The number of neurons in the hidden state
+    /// * outputs - The number of outputs
+    /// * cell - The kind of recurrent cell
+    pub fn new_deterministic(
+        seed: u64,
+        inputs: usize,
+        hidden: usize,
+        outputs: usize,
+        cell: Cell,
+    ) -> Self {
+        return Self::with_seed(seed, inputs, hidden, outputs, cell);
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> VAIR<T, R> {
+    /// Creates a VAIR with zeros for all connection weights and state,
+    /// using a specific seed for random number generatoin.
+    /// Unlike [`VAIR::new_deterministic`], the weights can be any [`Scalar`],
+    /// and the rng any [`NetworkRng`].
+    /// * seed - The seed for random number generation
+    /// * inputs - The number of inputs
+    /// * hidden - The number of neurons in the hidden state
+    /// * outputs - The number of outputs
+    /// * cell - The kind of recurrent cell
+    pub fn with_seed(
+        seed: u64,
+        inputs: usize,
+        hidden: usize,
+        outputs: usize,
+        cell: Cell,
+    ) -> Self {
+        let rows = cell.gates() * hidden;
+        return Self {
+            rng: R::seed_from_u64(seed),
+            cell,
+            hidden_connections: DMatrix::zeros(rows, inputs + hidden),
+            hidden_bias: DVector::zeros(rows),
+            hidden_activation: Activation::Tanh,
+            output_connections: DMatrix::zeros(outputs, hidden),
+            output_bias: DVector::zeros(outputs),
+            output_activation: Activation::Identity,
+            state: DVector::zeros(hidden),
+            cell_state: DVector::zeros(hidden),
+        };
+    }
+
+    /// The number of inputs, hidden neurons, and outputs
+    pub fn layer_sizes(&self) -> Vec<usize> {
+        let hidden = self.output_connections.ncols();
+        let inputs = self.hidden_connections.ncols().saturating_sub(hidden);
+        return vec![inputs, hidden, self.output_connections.nrows()];
+    }
+
+    /// Clears the hidden state (and cell state), as if no inputs had been seen
+    pub fn reset_state(&mut self) {
+        self.state.fill(T::zero());
+        self.cell_state.fill(T::zero());
+    }
+
+    /// Runs one time step of inputs through the network, updating its state,
+    /// to get an output
+    /// * inputs - The inputs
+    ///
+    /// Panics if the number of inputs does not match the network.
+    pub fn step(&mut self, inputs: &[T]) -> Vec<T> {
+        let sizes = self.layer_sizes();
+        let (input_count, hidden) = (sizes[0], sizes[1]);
+        assert_eq!(inputs.len(), input_count, "wrong number of inputs");
+        let x = DVector::from_column_slice(inputs);
+        let from_inputs =
+            self.hidden_connections.columns(0, input_count) * x + &self.hidden_bias;
+        let from_state = self.hidden_connections.columns(input_count, hidden) * &self.state;
+        let gate = |i: usize, values: &DVector<T>| values.rows(i * hidden, hidden).into_owned();
+        let sigmoid = |x: T| Activation::Sigmoid.apply(x);
+        let activation = self.hidden_activation;
+        match self.cell {
+            Cell::Elman => {
+                self.state = (from_inputs + from_state).map(|x| activation.apply(x));
+            }
+            Cell::GRU => {
+                let update = (gate(0, &from_inputs) + gate(0, &from_state)).map(sigmoid);
+                let reset = (gate(1, &from_inputs) + gate(1, &from_state)).map(sigmoid);
+                let candidate = (gate(2, &from_inputs) + reset.component_mul(&gate(2, &from_state)))
+                    .map(|x| activation.apply(x));
+                self.state = candidate.zip_zip_map(&update, &self.state, |n, z, h| (T::one() - z) * n + z * h);
+            }
+            Cell::LSTM => {
+                let pre = from_inputs + from_state;
+                let input = gate(0, &pre).map(sigmoid);
+                let forget = gate(1, &pre).map(sigmoid);
+                let candidate = gate(2, &pre).map(|x| activation.apply(x));
+                let output = gate(3, &pre).map(sigmoid);
+                self.cell_state = forget.component_mul(&self.cell_state) + input.component_mul(&candidate);
+                self.state = output.component_mul(&self.cell_state.map(|x| activation.apply(x)));
+            }
+        }
+        let mut output = &self.output_connections * &self.state + &self.output_bias;
+        output.apply(|x| *x = self.output_activation.apply(*x));
+        return output.as_slice().to_vec();
+    }
+
+    /// Runs a sequence of inputs through the network, one step at a time,
+    /// starting from its current state.
+    /// Returns the output of each step.
+    /// * inputs - The inputs of each time step
+    ///
+    /// see also:
+    ///  * [`VAIR::step`]
+    pub fn process_sequence(&mut self, inputs: &[Vec<T>]) -> Vec<Vec<T>> {
+        return inputs.iter().map(|x| self.step(x)).collect();
+    }
+
+    /// Creates a random variant of this VAIR, with the same state
+    /// * intensity - Scaler for the added randomness
+    ///
+    /// Intensity affects the random distribution to favor low magnitude
+    /// values, but the result can still be changed by an arbitrary amount.
+    /// Randomness is applied to each weight of each connection,
+    /// and to each bias.
+    ///
+    /// In order to keep variation fairly consistent on neural networks
+    /// of various sizes, the intensity is scaled down by the number of
+    /// connections in the network before being applied.
+    ///
+    /// see also:
+    ///  * [`create_variant_with_rng`]
+    ///  * [`VAIR::create_layer_variant`]
+    pub fn create_variant(&mut self, intensity: f32) -> Self {
+        let mut result = self.clone();
+        let fields = self.hidden_connections.len() + self.output_connections.len();
+        let s_intensity = intensity / (1.0 + fields as f32);
+        let rng = &mut self.rng;
+        result.hidden_connections = create_variant_with_rng(rng, &self.hidden_connections, s_intensity);
+        result.hidden_bias = create_variant_with_rng(rng, &self.hidden_bias, s_intensity);
+        result.output_connections = create_variant_with_rng(rng, &self.output_connections, s_intensity);
+        result.output_bias = create_variant_with_rng(rng, &self.output_bias, s_intensity);
+        return result;
+    }
+
+    /// Creates a random variant of this VAIR that only changes one layer:
+    /// either the recurrent layer or the output layer
+    /// * intensity - Scaler for the added randomness
+    ///
+    /// The intensity is scaled down by the number of connections in the
+    /// chosen layer before being applied.
+    ///
+    /// see also:
+    ///  * [`create_variant_with_rng`]
+    pub fn create_layer_variant(&mut self, intensity: f32) -> Self {
+        let mut result = self.clone();
+        let rng = &mut self.rng;
+        if rng.gen::<bool>() {
+            let intensity = intensity / (self.hidden_connections.len() + 1) as f32;
+            result.hidden_connections = create_variant_with_rng(rng, &self.hidden_connections, intensity);
+            result.hidden_bias = create_variant_with_rng(rng, &self.hidden_bias, intensity);
+        } else {
+            let intensity = intensity / (self.output_connections.len() + 1) as f32;
+            result.output_connections = create_variant_with_rng(rng, &self.output_connections, intensity);
+            result.output_bias = create_variant_with_rng(rng, &self.output_bias, intensity);
+        }
+        return result;
+    }
+
+    /// Creates a child of this VAIR and another, using [`Crossover::Uniform`]
+    /// * other - The other parent
+    ///
+    /// see also:
+    ///  * [`VAIR::crossover_with`]
+    pub fn crossover(&mut self, other: &Self) -> Self {
+        return self.crossover_with(other, Crossover::Uniform);
+    }
+
+    /// Creates a child of this VAIR and another, with this VAIR's state.
+    /// The child's random number generator is cloned from this VAIR,
+    /// and this VAIR's random number generator is used for the crossover.
+    ///
+    /// Layers that don't have the same shape in both parents
+    /// (or whose cells differ, for the recurrent layer)
+    /// are copied from this parent.
+    /// * other - The other parent
+    /// * mode - How the parents' weights are combined
+    pub fn crossover_with(&mut self, other: &Self, mode: Crossover) -> Self {
+        let mut result = self.clone();
+        let rng = &mut self.rng;
+        let hidden_matches = self.cell == other.cell
+            && self.hidden_connections.shape() == other.hidden_connections.shape();
+        if hidden_matches && mode == Crossover::LayerSwap {
+            if rng.gen::<bool>() {
+                result.hidden_connections = other.hidden_connections.clone();
+                result.hidden_bias = other.hidden_bias.clone();
+                result.hidden_activation = other.hidden_activation;
+            }
+        } else if hidden_matches {
+            cross_layer(
+                rng,
+                mode,
+                &mut result.hidden_connections,
+                &mut result.hidden_bias,
+                &other.hidden_connections,
+                &other.hidden_bias,
+            );
+        }
+        if self.output_connections.shape() != other.output_connections.shape() {
+            return result;
+        }
+        if mode == Crossover::LayerSwap {
+            if rng.gen::<bool>() {
+                result.output_connections = other.output_connections.clone();
+                result.output_bias = other.output_bias.clone();
+                result.output_activation = other.output_activation;
+            }
+            return result;
+        }
+        cross_layer(
+            rng,
+            mode,
+            &mut result.output_connections,
+            &mut result.output_bias,
+            &other.output_connections,
+            &other.output_bias,
+        );
+        return result;
+    }
+
+    /// The header describing this vai's shape and activations
+    /// * metadata - Optional information to store alongside the vai
+    fn header(&self, metadata: &Metadata) -> Header {
+        return Header {
+            version: format::VERSION,
+            kind: NetworkKind::VAIR,
+            layers: self.layer_sizes(),
+            activations: vec![self.hidden_activation, self.output_activation],
+            metadata: metadata.clone(),
+            rng: None,
+        };
+    }
+
+    /// Creates a VAIR from the layers read from a file, with no state
+    fn from_layers(
+        header: Header,
+        cell: Cell,
+        hidden: (DMatrix<T>, Vec<T>),
+        output: (DMatrix<T>, Vec<T>),
+    ) -> Self {
+        let size = header.layers[1];
+        return Self {
+            rng: R::seed_from_u64(rand::random()),
+            cell,
+            hidden_connections: hidden.0,
+            hidden_bias: DVector::from_vec(hidden.1),
+            hidden_activation: header.activations[0],
+            output_connections: output.0,
+            output_bias: DVector::from_vec(output.1),
+            output_activation: header.activations[1],
+            state: DVector::zeros(size),
+            cell_state: DVector::zeros(size),
+        };
+    }
+
+    /// Writes a vai to a file in the format described in [`crate::format`].
+    /// The state is not written.
+    /// * file - The file to write to
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    ///
+    /// see also:
+    ///  * [`VAIR::write_with_metadata`]
+    pub fn write(&self, file: &mut impl Write) -> Result<(), VaiError> {
+        return self.write_with_metadata(file, &Metadata::default());
+    }
+
+    /// Writes a vai to a file, like [`VAIR::write`], with metadata in its header
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    pub fn write_with_metadata(
+        &self,
+        file: &mut impl Write,
+        metadata: &Metadata,
+    ) -> Result<(), VaiError> {
+        self.header(metadata).write(file)?;
+        writeln!(file, "cell {}", self.cell)?;
+        format::write_layer(file, &self.hidden_connections, self.hidden_bias.as_slice())?;
+        format::write_layer(file, &self.output_connections, self.output_bias.as_slice())?;
+        return Ok(());
+    }
+
+    /// Reads a vai from lines of a file, as written by [`VAIR::write`],
+    /// with a cleared state.
+    /// Fails if the file holds a different kind of network.
+    /// * lines - A line iterator from which to read the vai
+    ///   (generally provided by BufReader::new(file).lines())
+    pub fn read(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Self, VaiError> {
+        return Ok(Self::read_with_metadata(lines)?.0);
+    }
+
+    /// Reads a vai like [`VAIR::read`], along with the metadata in its header
+    /// * lines - A line iterator from which to read the vai
+    pub fn read_with_metadata(
+        lines: &mut impl Iterator<Item = io::Result<String>>,
+    ) -> Result<(Self, Metadata), VaiError> {
+        let mut lines = LineReader::new(lines);
+        let header = match Header::read(&mut lines)? {
+            Some(header) => header,
+            None => return Err(VaiError::Format("Missing header".to_owned())),
+        };
+        header.expect_kind(NetworkKind::VAIR)?;
+        let (inputs, hidden, outputs) = Self::expect_sizes(&header)?;
+        let line = lines.expect_nonempty_line()?;
+        let mut tokens = line.split_whitespace();
+        let cell: Cell = match (tokens.next(), tokens.next()) {
+            (Some("cell"), Some(name)) => lines.parse(&line, name)?,
+            _ => return Err(VaiError::Format("Missing cell".to_owned())),
+        };
+        let rows = checked_size(cell.gates(), hidden)?;
+        let recurrent = format::read_layer(&mut lines, 0, rows, inputs + hidden)?;
+        let output = format::read_layer(&mut lines, 1, outputs, hidden)?;
+        let metadata = header.metadata.clone();
+        return Ok((Self::from_layers(header, cell, recurrent, output), metadata));
+    }
+
+    /// The input, hidden, and output sizes of a header.
+    /// Fails if there are not exactly three.
+    fn expect_sizes(header: &Header) -> Result<(usize, usize, usize), VaiError> {
+        return match header.layers[..] {
+            [inputs, hidden, outputs] => Ok((inputs, hidden, outputs)),
+            _ => Err(VaiError::Format("A VAIR must have exactly three layer sizes".to_owned())),
+        };
+    }
+
+    /// Reads a vai from a reader, as written by [`VAIR::write`]
+    /// * reader - The reader from which to read the vai
+    pub fn read_from(reader: &mut impl BufRead) -> Result<Self, VaiError> {
+        return Self::read(&mut reader.lines());
+    }
+
+    /// Writes a vai to a new file at the given path, as [`VAIR::write`] does
+    /// * path - The path of the file to create
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), VaiError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()?;
+        return Ok(());
+    }
+
+    /// Reads a vai from the file at the given path, as [`VAIR::read`] does
+    /// * path - The path of the file to read
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, VaiError> {
+        return Self::read_from(&mut BufReader::new(File::open(path)?));
+    }
+
+    /// Writes a vai to a string, as [`VAIR::write`] does
+    ///
+    /// see also:
+    ///  * [`VAIR::from_str`]
+    pub fn to_text(&self) -> Result<String, VaiError> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)?;
+        return Ok(String::from_utf8(buffer).expect("numbers and keywords are valid utf-8"));
+    }
+
+    /// Writes a vai in the binary format described in [`crate::format`].
+    /// The state is not written.
+    /// * file - The file to write to
+    /// * checksum - Whether to end the file with a checksum,
+    ///   which is verified when it is read
+    ///
+    /// Fails if any layer uses an [`Activation::Custom`].
+    pub fn write_binary(&self, file: &mut impl Write, checksum: bool) -> Result<(), VaiError> {
+        return self.write_binary_with_metadata(file, &Metadata::default(), checksum);
+    }
+
+    /// Writes a vai like [`VAIR::write_binary`], with metadata in its header
+    /// * file - The file to write to
+    /// * metadata - Optional information to store alongside the vai
+    /// * checksum - Whether to end the file with a checksum
+    pub fn write_binary_with_metadata(
+        &self,
+        file: &mut impl Write,
+        metadata: &Metadata,
+        checksum: bool,
+    ) -> Result<(), VaiError> {
+        let mut writer = BinaryWriter::new(file);
+        let encoding = Encoding::new::<T>(checksum);
+        self.header(metadata).write_binary(&mut writer, encoding)?;
+        writer.write_u8(match self.cell {
+            Cell::Elman => 0,
+            Cell::GRU => 1,
+            Cell::LSTM => 2,
+        })?;
+        writer.write_scalars(self.hidden_connections.as_slice(), encoding.wide)?;
+        writer.write_scalars(self.hidden_bias.as_slice(), encoding.wide)?;
+        writer.write_scalars(self.output_connections.as_slice(), encoding.wide)?;
+        writer.write_scalars(self.output_bias.as_slice(), encoding.wide)?;
+        return writer.finish(checksum);
+    }
+
+    /// Reads a vai as written by [`VAIR::write_binary`], with a cleared state.
+    /// Fails if the file holds a different kind of network,
+    /// or if its checksum does not match.
+    /// * file - The file from which to read the vai
+    pub fn read_binary(file: &mut impl Read) -> Result<Self, VaiError> {
+        return Ok(Self::read_binary_with_metadata(file)?.0);
+    }
+
+    /// Reads a vai like [`VAIR::read_binary`], along with the metadata in its header
+    /// * file - The file from which to read the vai
+    pub fn read_binary_with_metadata(file: &mut impl Read) -> Result<(Self, Metadata), VaiError> {
+        let mut reader = BinaryReader::new(file);
+        let (header, encoding) = Header::read_binary(&mut reader)?;
+        let wide = encoding.wide;
+        header.expect_kind(NetworkKind::VAIR)?;
+        let (inputs, hidden, outputs) = Self::expect_sizes(&header)?;
+        let cell = match reader.read_u8()? {
+            0 => Cell::Elman,
+            1 => Cell::GRU,
+            2 => Cell::LSTM,
+            _ => return Err(VaiError::Format("Unknown cell".to_owned())),
+        };
+        let rows = checked_size(cell.gates(), hidden)?;
+        let columns = inputs + hidden;
+        let values = reader.read_scalars(checked_size(rows, columns)?, wide)?;
+        let recurrent = (DMatrix::from_vec(rows, columns, values), reader.read_scalars(rows, wide)?);
+        let values = reader.read_scalars(checked_size(outputs, hidden)?, wide)?;
+        let output = (DMatrix::from_vec(outputs, hidden, values), reader.read_scalars(outputs, wide)?);
+        reader.finish(encoding.checksum)?;
+        let metadata = header.metadata.clone();
+        return Ok((Self::from_layers(header, cell, recurrent, output), metadata));
+    }
+}
+
+impl<T: Scalar, R: NetworkRng> FromStr for VAIR<T, R> {
+    type Err = VaiError;
+
+    /// Reads a vai from a string, as [`VAIR::read`] does
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        return Self::read(&mut text.lines().map(|x| Ok(x.to_owned())));
+    }
+}
@@ -0,0 +1,96 @@
+#![allow(clippy::needless_return)]
+
+use std::io::BufRead;
+
+use nalgebra as na;
+use vai::Activation;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    return std::env::temp_dir().join(format!("vai-{}-{}", std::process::id(), name));
+}
+
+#[test]
+fn activation_names() {
+    let activations = [
+        Activation::ReLU,
+        Activation::LeakyReLU(0.01),
+        Activation::Tanh,
+        Activation::Sigmoid,
+        Activation::Softsign,
+        Activation::Identity,
+    ];
+    for activation in activations {
+        let parsed: Activation = activation.to_string().parse().unwrap();
+        assert_eq!(parsed, activation);
+    }
+    assert!("custom".parse::<Activation>().is_err());
+    assert!("leaky_relu(x)".parse::<Activation>().is_err());
+}
+
+#[test]
+fn output_activation() {
+    let mut ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0);
+    for _ in 0..10 {
+        ai = ai.create_variant(10.0);
+    }
+    ai.output_activation = Activation::Sigmoid;
+    for i in 0..=10 {
+        let x = 0.1 * i as f32;
+        let y = ai.process(&na::SMatrix::<f32, 2, 1>::new(x, 1.0))[0];
+        assert!((0. ..=1.).contains(&y));
+    }
+
+    let mut aid = vai::VAID::new_deterministic(0, &[2, 4, 1]);
+    aid.activations = vec![Activation::Custom(|x| x * 2.), Activation::Tanh];
+    for _ in 0..10 {
+        aid = aid.create_variant(10.0);
+    }
+    for i in 0..=10 {
+        let y = aid.process_slice(&[0.1 * i as f32, 1.0])[0];
+        assert!((-1. ..=1.).contains(&y));
+    }
+}
+
+#[test]
+fn activation_read_write() {
+    let mut ai = vai::VAI::<2, 1, 3, 1>::new_deterministic(0).create_variant(1.0);
+    ai.hidden_activations = [Activation::LeakyReLU(0.1)];
+    ai.output_activation = Activation::Tanh;
+    let path = temp_path("activation.vai");
+    ai.write(&mut std::fs::File::create(&path).unwrap()).unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    let read = vai::VAI::<2, 1, 3, 1>::read(&mut std::io::BufReader::new(file).lines()).unwrap();
+    assert_eq!(read.input_activation, Activation::ReLU);
+    assert_eq!(read.hidden_activations, [Activation::LeakyReLU(0.1)]);
+    assert_eq!(read.output_activation, Activation::Tanh);
+    assert_eq!(read.input_bias, ai.input_bias);
+    assert_eq!(read.hidden_biases, ai.hidden_biases);
+    assert_eq!(read.output_bias, ai.output_bias);
+
+    let mut aid = vai::VAID::new_deterministic(0, &[2, 3, 1]).create_variant(1.0);
+    aid.activations = vec![Activation::Softsign, Activation::Sigmoid];
+    let path = temp_path("activation.vaid");
+    aid.write(&mut std::fs::File::create(&path).unwrap()).unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    let read = vai::VAID::<f32>::read(&mut std::io::BufReader::new(file).lines()).unwrap();
+    assert_eq!(read.activations, aid.activations);
+    assert_eq!(read.connections, aid.connections);
+    assert_eq!(read.biases, aid.biases);
+
+    aid.activations[0] = Activation::Custom(|x| x);
+    assert!(aid.write(&mut std::fs::File::create(&path).unwrap()).is_err());
+}
+
+#[test]
+fn read_without_activations() {
+    let vai_text = "1 2 \n3 4 \n\n5 6 \n";
+    let ai = vai::VAI::<2, 1, 2, 0>::read(&mut vai_text.lines().map(|x| Ok(x.to_owned()))).unwrap();
+    assert_eq!(ai.input_activation, Activation::ReLU);
+    assert_eq!(ai.output_activation, Activation::Identity);
+    assert_eq!(ai.process_slice(&[1., -1.]), vec![0.]);
+
+    let vaid_text = "2\n2\n1 2 \n3 4 \n1\n5 6 \n";
+    let aid = vai::VAID::<f32>::read(&mut vaid_text.lines().map(|x| Ok(x.to_owned()))).unwrap();
+    assert_eq!(aid.activations, vec![Activation::ReLU, Activation::Identity]);
+    assert_eq!(aid.process_slice(&[1., -1.]), vec![0.]);
+}
@@ -0,0 +1,88 @@
+#![allow(clippy::needless_return)]
+
+use nalgebra as na;
+use vai::{Activation, Loss};
+
+fn loss(ai: &vai::VAID, inputs: &na::DMatrix<f32>, targets: &na::DMatrix<f32>, loss: Loss) -> f32 {
+    return loss.evaluate(&ai.process(inputs), targets).0;
+}
+
+fn gradient_check(loss_type: Loss) {
+    let mut ai = vai::VAID::new_deterministic(0, &[3, 5, 4, 2]);
+    ai.activations = vec![Activation::Tanh, Activation::Sigmoid, Activation::Identity];
+    let ai = ai.create_variant(20.0);
+    let inputs = na::DMatrix::<f32>::from_fn(3, 4, |r, c| (r as f32 - c as f32) * 0.3);
+    let targets = na::DMatrix::<f32>::from_fn(2, 4, |r, c| ((r + c) % 2) as f32);
+
+    let cache = ai.forward(&inputs);
+    assert_eq!(cache.result(), &ai.process(&inputs));
+    let (_, gradients) = ai.backward(&cache, &targets, loss_type);
+
+    let epsilon = 1e-2;
+    for layer in 0..ai.connections.len() {
+        for i in 0..ai.connections[layer].len() {
+            let mut plus = ai.clone();
+            plus.connections[layer][i] += epsilon;
+            let mut minus = ai.clone();
+            minus.connections[layer][i] -= epsilon;
+            let numeric = (loss(&plus, &inputs, &targets, loss_type)
+                - loss(&minus, &inputs, &targets, loss_type))
+                / (2. * epsilon);
+            let analytic = gradients.connections[layer][i];
+            assert!((numeric - analytic).abs() < 1e-3 + 0.05 * numeric.abs());
+        }
+        for i in 0..ai.biases[layer].len() {
+            let mut plus = ai.clone();
+            plus.biases[layer][i] += epsilon;
+            let mut minus = ai.clone();
+            minus.biases[layer][i] -= epsilon;
+            let numeric = (loss(&plus, &inputs, &targets, loss_type)
+                - loss(&minus, &inputs, &targets, loss_type))
+                / (2. * epsilon);
+            let analytic = gradients.biases[layer][i];
+            assert!((numeric - analytic).abs() < 1e-3 + 0.05 * numeric.abs());
+        }
+    }
+}
+
+#[test]
+fn mean_squared_error_gradients() {
+    gradient_check(Loss::MeanSquaredError);
+}
+
+#[test]
+fn cross_entropy_gradients() {
+    gradient_check(Loss::CrossEntropy);
+}
+
+#[test]
+fn train_non_linear() {
+    let inputs = na::DMatrix::<f32>::from_fn(1, 11, |_, c| 0.1 * c as f32);
+    let targets = inputs.map(|x| 2. * (x - 0.5) * (x - 0.5));
+    let mut ai = vai::VAID::new_deterministic(0, &[1, 8, 1]).create_variant(10.0);
+    let initial_loss = ai.train_step(&inputs, &targets, 0.1);
+    let mut loss = initial_loss;
+    for _ in 0..5000 {
+        loss = ai.train_step(&inputs, &targets, 0.1);
+    }
+    println!("Loss: {} -> {}", initial_loss, loss);
+    assert!(loss < initial_loss * 0.1);
+}
+
+#[test]
+fn train_classifier() {
+    let inputs = na::DMatrix::<f32>::from_fn(1, 20, |_, c| 0.05 * c as f32);
+    let targets = na::DMatrix::<f32>::from_fn(2, 20, |r, c| {
+        let class = if c < 10 { 0 } else { 1 };
+        if r == class { 1. } else { 0. }
+    });
+    let mut ai = vai::VAID::new_deterministic(0, &[1, 8, 2]).create_variant(10.0);
+    for _ in 0..2000 {
+        ai.train_step_with(&inputs, &targets, 0.5, Loss::CrossEntropy);
+    }
+    let outputs = ai.process(&inputs);
+    for c in 0..20 {
+        let class = if outputs[(0, c)] > outputs[(1, c)] { 0 } else { 1 };
+        assert_eq!(targets[(class, c)], 1.);
+    }
+}
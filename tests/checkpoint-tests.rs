@@ -0,0 +1,125 @@
+#![allow(clippy::needless_return)]
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::{ChaCha12Rng, ChaCha8Rng};
+use vai::format::{Metadata, RngState};
+use vai::{CheckpointRng, Crossover, VAI, VAID};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    return std::env::temp_dir().join(format!("vai-{}-{}", std::process::id(), name));
+}
+
+/// A VAID whose rng can be stored in checkpoints
+type CheckpointVAID = VAID<f32, ChaCha12Rng>;
+
+/// Continues evolution from a network, returning the result
+fn evolve_vaid(mut ai: CheckpointVAID, generations: usize) -> CheckpointVAID {
+    for _ in 0..generations {
+        let mut variant = ai.create_layer_variant(1.0);
+        if variant.rng.gen::<bool>() {
+            variant.add_neuron(0);
+        }
+        ai = ai.crossover_with(&variant, Crossover::Blend).create_variant(1.0);
+    }
+    return ai;
+}
+
+#[test]
+fn vaid_resume() {
+    let ai = evolve_vaid(CheckpointVAID::with_seed(0, &[2, 4, 1]), 10);
+    let metadata = Metadata { generation: Some(10), fitness: None, timestamp: None };
+    let mut buffer = Vec::new();
+    ai.write_checkpoint(&mut buffer, &metadata).unwrap();
+    let written = ai.to_string();
+    let expected = evolve_vaid(ai, 10);
+
+    let (resumed, read_metadata) = CheckpointVAID::resume(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read_metadata, metadata);
+    assert!(evolve_vaid(resumed, 10) == expected);
+
+    // read_binary reads the weights of a checkpoint, but not its rng
+    let read_ai = VAID::<f32>::read_binary(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read_ai.to_string(), written);
+
+    // A file without an rng state can not be resumed
+    let mut buffer = Vec::new();
+    expected.write_binary(&mut buffer, true).unwrap();
+    assert!(CheckpointVAID::resume(&mut buffer.as_slice()).is_err());
+}
+
+#[test]
+fn vai_resume() {
+    let mut ai = VAI::<2, 1, 3, 1, f32, ChaCha12Rng>::new_deterministic(0);
+    for _ in 0..5 {
+        ai = ai.create_layer_variant(1.0);
+    }
+    let path = temp_path("checkpoint.vai");
+    ai.save_checkpoint(&path, &Metadata::default()).unwrap();
+    let (mut resumed, _) = VAI::<2, 1, 3, 1, f32, ChaCha12Rng>::load_checkpoint(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    for _ in 0..5 {
+        ai = ai.create_layer_variant(1.0).create_variant(1.0);
+        resumed = resumed.create_layer_variant(1.0).create_variant(1.0);
+        assert_eq!(resumed.to_string(), ai.to_string());
+    }
+
+    let ai = VAI::<2, 1, 3, 1, f64, ChaCha12Rng>::new_deterministic(1).create_variant(1.0);
+    let mut buffer = Vec::new();
+    ai.write_checkpoint(&mut buffer, &Metadata::default()).unwrap();
+    let (mut resumed, _) = VAI::<2, 1, 3, 1, f64, ChaCha12Rng>::resume(&mut buffer.as_slice()).unwrap();
+    assert_eq!(resumed.rng.gen::<u64>(), ai.clone().rng.gen::<u64>());
+}
+
+/// An rng outside of the crate, which stores its state in checkpoints
+#[derive(Clone)]
+struct CustomRng(ChaCha8Rng);
+
+impl RngCore for CustomRng {
+    fn next_u32(&mut self) -> u32 {
+        return self.0.next_u32();
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return self.0.next_u64();
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        return self.0.try_fill_bytes(dest);
+    }
+}
+
+impl SeedableRng for CustomRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        return Self(ChaCha8Rng::from_seed(seed));
+    }
+}
+
+impl CheckpointRng for CustomRng {
+    fn state(&self) -> RngState {
+        return RngState {seed: self.0.get_seed(), stream: self.0.get_stream(), word_pos: self.0.get_word_pos()};
+    }
+
+    fn from_state(state: &RngState) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(state.seed);
+        rng.set_stream(state.stream);
+        rng.set_word_pos(state.word_pos);
+        return Self(rng);
+    }
+}
+
+#[test]
+fn custom_rng_resume() {
+    let mut ai = VAID::<f32, CustomRng>::with_seed(3, &[2, 3, 1]).create_variant(1.0);
+    let mut buffer = Vec::new();
+    ai.write_checkpoint(&mut buffer, &Metadata::default()).unwrap();
+    let (mut resumed, _) = VAID::<f32, CustomRng>::resume(&mut buffer.as_slice()).unwrap();
+    for _ in 0..10 {
+        assert_eq!(resumed.rng.gen::<u64>(), ai.rng.gen::<u64>());
+    }
+}
@@ -0,0 +1,81 @@
+#![allow(clippy::needless_return)]
+
+use vai::cmaes::CmaEs;
+use vai::{Parameters, VAI, VAID};
+
+/// Higher when every parameter is closer to 1
+fn sphere<N: Parameters<Scalar = f32>>(network: &N) -> f32 {
+    return -network.parameters().iter().map(|x| (x - 1.0) * (x - 1.0)).sum::<f32>();
+}
+
+/// An ill-conditioned function, whose axes are scaled by up to 1000
+fn ellipsoid(network: &VAID<f64>) -> f32 {
+    let parameters = network.parameters();
+    let n = parameters.len() as f64;
+    let sum: f64 = parameters
+        .iter()
+        .enumerate()
+        .map(|(i, x)| 1e6f64.powf(i as f64 / (n - 1.0)) * x * x)
+        .sum();
+    return -sum as f32;
+}
+
+#[test]
+fn ask_tell() {
+    let ai = VAID::new_deterministic(0, &[2, 3, 1]);
+    let start = sphere(&ai);
+    let mut cmaes = CmaEs::new_deterministic(0, ai, 0.5);
+    assert_eq!(cmaes.population_size(), 4 + (3.0 * 13f64.ln()) as usize);
+    for _ in 0..200 {
+        let candidates = cmaes.ask();
+        assert_eq!(candidates.len(), cmaes.population_size());
+        let scores: Vec<f32> = candidates.iter().map(sphere).collect();
+        cmaes.tell(&scores);
+    }
+    assert_eq!(cmaes.generation, 200);
+    let (_, best) = cmaes.best().unwrap();
+    assert!(best > -1e-4, "{} {}", best, start);
+    assert!(sphere(&cmaes.mean_network()) > -1e-4);
+    assert!(cmaes.sigma < 0.5);
+}
+
+#[test]
+fn ill_conditioned() {
+    let ai = VAID::<f64>::with_seed(0, &[2, 2, 1]).create_variant(10.0);
+    let start = ellipsoid(&ai);
+    let mut cmaes = CmaEs::new_deterministic(0, ai, 1.0);
+    let stats = cmaes.run(600, ellipsoid);
+    assert!(stats[599].best > -1e-6, "{} {}", stats[599].best, start);
+    // The covariance has learned the scaling of the axes
+    let covariance = &cmaes.covariance;
+    assert!(covariance[(0, 0)] > covariance[(8, 8)] * 100.0);
+}
+
+#[test]
+fn deterministic() {
+    let run = || {
+        let mut cmaes = CmaEs::new_deterministic(3, VAI::<2, 1, 3, 0>::new_deterministic(0), 0.3);
+        cmaes.set_population_size(20);
+        return cmaes.run(20, sphere);
+    };
+    let stats = run();
+    assert_eq!(stats, run());
+    assert!(stats[19].best > stats[0].best);
+}
+
+#[test]
+#[should_panic]
+fn wrong_fitness_count() {
+    let mut cmaes = CmaEs::new_deterministic(0, VAID::new_deterministic(0, &[2, 3, 1]), 0.5);
+    let candidates = cmaes.ask();
+    cmaes.tell(&vec![0.0; candidates.len() - 1]);
+}
+
+#[test]
+#[should_panic(expected = "tell called without ask")]
+fn tell_without_ask() {
+    let mut cmaes = CmaEs::new_deterministic(0, VAID::new_deterministic(0, &[2, 3, 1]), 0.5);
+    let scores: Vec<f32> = cmaes.ask().iter().map(sphere).collect();
+    cmaes.tell(&scores);
+    cmaes.tell(&scores);
+}
@@ -0,0 +1,71 @@
+#![allow(clippy::needless_return)]
+
+use vai::Crossover;
+
+type AI = vai::VAI<2, 1, 4, 1>;
+
+fn parents() -> (AI, AI) {
+    let a = AI::new_deterministic(0).create_variant(10.0);
+    let b = AI::new_deterministic(1).create_variant(10.0);
+    return (a, b);
+}
+
+fn parent_weights(ai: &AI) -> Vec<f32> {
+    let mut weights: Vec<f32> = ai.input_connections.iter().copied().collect();
+    weights.extend(ai.hidden_connections[0].iter());
+    weights.extend(ai.output_connections.iter());
+    return weights;
+}
+
+#[test]
+fn crossover_deterministic() {
+    let modes = [
+        Crossover::Uniform,
+        Crossover::SinglePoint,
+        Crossover::LayerSwap,
+        Crossover::Blend,
+    ];
+    for mode in modes {
+        let (mut a1, b1) = parents();
+        let (mut a2, b2) = parents();
+        let child1 = a1.crossover_with(&b1, mode);
+        let child2 = a2.crossover_with(&b2, mode);
+        assert_eq!(parent_weights(&child1), parent_weights(&child2));
+    }
+}
+
+#[test]
+fn crossover_genes_from_parents() {
+    let (mut a, b) = parents();
+    let (weights_a, weights_b) = (parent_weights(&a), parent_weights(&b));
+    for mode in [Crossover::Uniform, Crossover::SinglePoint, Crossover::LayerSwap] {
+        let child = parent_weights(&a.crossover_with(&b, mode));
+        for i in 0..child.len() {
+            assert!(child[i] == weights_a[i] || child[i] == weights_b[i]);
+        }
+    }
+    let child = parent_weights(&a.crossover_with(&b, Crossover::Blend));
+    for i in 0..child.len() {
+        let low = weights_a[i].min(weights_b[i]) - 1e-6;
+        let high = weights_a[i].max(weights_b[i]) + 1e-6;
+        assert!(child[i] >= low && child[i] <= high);
+    }
+}
+
+#[test]
+fn vaid_crossover() {
+    let mut a = vai::VAID::new_deterministic(0, &[2, 4, 1]).create_variant(10.0);
+    let b = vai::VAID::new_deterministic(1, &[2, 4, 1]).create_variant(10.0);
+    let child = a.crossover(&b);
+    for (i, mat) in child.connections.iter().enumerate() {
+        for (j, x) in mat.iter().enumerate() {
+            assert!(*x == a.connections[i][j] || *x == b.connections[i][j]);
+        }
+    }
+
+    // Layers with mismatched shapes are copied from the first parent
+    let c = vai::VAID::new_deterministic(2, &[2, 3, 1]).create_variant(10.0);
+    let child = a.crossover_with(&c, Crossover::Blend);
+    assert_eq!(child.connections, a.connections);
+    assert_eq!(child.biases, a.biases);
+}
@@ -0,0 +1,93 @@
+#![allow(clippy::needless_return)]
+
+use vai::es::{Adaptive, EvolutionStrategy, OnePlusOne, StepSizes, Strategy};
+use vai::{Parameters, VAI, VAID};
+
+/// Higher when every parameter is closer to 1
+fn sphere<N: Parameters<Scalar = f32>>(network: &N) -> f32 {
+    return -network.parameters().iter().map(|x| (x - 1.0) * (x - 1.0)).sum::<f32>();
+}
+
+#[test]
+fn parameters() {
+    let mut ai = VAID::new_deterministic(0, &[3, 4, 2]).create_variant(1.0);
+    assert_eq!(ai.layer_parameter_counts(), vec![16, 10]);
+    assert_eq!(ai.parameter_count(), 26);
+    let parameters = ai.parameters();
+    assert_eq!(parameters[0], ai.connections[0][(0, 0)]);
+    assert_eq!(parameters[1], ai.connections[0][(1, 0)]);
+    assert_eq!(parameters[12], ai.biases[0][0]);
+    let inputs = [0.1, 0.2, 0.3];
+    let expected = ai.process_slice(&inputs);
+    ai.set_parameters(&[0.0; 26]);
+    assert_eq!(ai.process_slice(&inputs), vec![0.0, 0.0]);
+    ai.set_parameters(&parameters);
+    assert_eq!(ai.process_slice(&inputs), expected);
+
+    let mut ai = VAI::<3, 2, 4, 1>::new_deterministic(0).create_variant(1.0);
+    assert_eq!(ai.layer_parameter_counts(), vec![16, 20, 10]);
+    let parameters = ai.parameters();
+    let vaid = VAID::from(&ai);
+    assert_eq!(vaid.parameters(), parameters);
+    let expected = ai.process_slice(&inputs);
+    ai.set_parameters(&[0.0; 46]);
+    ai.set_parameters(&parameters);
+    assert_eq!(ai.process_slice(&inputs), expected);
+}
+
+#[test]
+#[should_panic]
+fn wrong_parameter_count() {
+    VAID::new_deterministic(0, &[3, 4, 2]).set_parameters(&[0.0; 3]);
+}
+
+#[test]
+fn adaptive_step_sizes() {
+    let ai = VAID::new_deterministic(0, &[3, 4, 2]);
+    let mut rng = rand::thread_rng();
+    let step_sizes = [(StepSizes::Network, 1), (StepSizes::Layer, 2), (StepSizes::Weight, 26)];
+    for (step_sizes, count) in step_sizes {
+        let adaptive = Adaptive::new(ai.clone(), 0.5, step_sizes);
+        let variant = adaptive.create_variant(&mut rng);
+        assert_eq!(variant.sigmas.len(), count);
+        assert!(variant.sigmas.iter().all(|x| *x > 0. && *x != 0.5));
+        assert_ne!(variant.network.connections, ai.connections);
+    }
+}
+
+#[test]
+fn one_plus_one() {
+    let ai = VAID::new_deterministic(0, &[3, 4, 2]);
+    let start = sphere(&ai);
+    let mut es = OnePlusOne::new_deterministic(0, ai, 0.5, sphere);
+    let score = es.run(400);
+    assert!(score > start * 0.01, "{} {}", score, start);
+    assert_eq!(es.score(), Some(score));
+    // The step size shrinks as the parent approaches the optimum
+    assert!(es.sigma < 0.5);
+
+    let ai = VAID::new_deterministic(0, &[3, 4, 2]);
+    let mut other = OnePlusOne::new_deterministic(0, ai, 0.5, sphere);
+    assert_eq!(other.run(400), score);
+}
+
+#[test]
+fn evolution_strategies() {
+    for strategy in [Strategy::Comma, Strategy::Plus] {
+        let parents: Vec<_> = (0..3)
+            .map(|i| Adaptive::new(VAI::<3, 2, 4, 1>::new_deterministic(i), 0.5, StepSizes::Layer))
+            .collect();
+        let start = sphere(&parents[0].network);
+        let mut es = EvolutionStrategy::new_deterministic(0, parents, 12, strategy, sphere);
+        let stats = es.run(150);
+        assert_eq!(stats.len(), 150);
+        assert_eq!(es.parents.len(), 3);
+        let (_, best) = es.best().unwrap();
+        assert!(best > start * 0.2, "{:?} {} {}", strategy, best, start);
+        assert!(stats[149].best > stats[0].best);
+        if strategy == Strategy::Plus {
+            // The best network is never replaced by a worse one
+            assert_eq!(es.parent_scores[0], best);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+#![allow(clippy::needless_return)]
+
+use std::io::BufRead;
+
+use vai::format::Metadata;
+use vai::Activation;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    return std::env::temp_dir().join(format!("vai-{}-{}", std::process::id(), name));
+}
+
+fn lines(text: &str) -> impl Iterator<Item = std::io::Result<String>> + '_ {
+    return text.lines().map(|x| Ok(x.to_owned()));
+}
+
+#[test]
+fn metadata_round_trip() {
+    let mut ai = vai::VAID::new_deterministic(0, &[3, 5, 2]).create_variant(10.0);
+    ai.activations[0] = Activation::Tanh;
+    let metadata = Metadata {
+        generation: Some(42),
+        fitness: Some(-1.5),
+        timestamp: Some(1_700_000_000),
+    };
+    let path = temp_path("metadata.vaid");
+    ai.write_with_metadata(&mut std::fs::File::create(&path).unwrap(), &metadata).unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("VAI-MODEL 1\nkind VAID\nlayers 3 5 2\n"));
+    let mut file_lines = std::io::BufReader::new(std::fs::File::open(&path).unwrap()).lines();
+    let (read_ai, read_metadata) = vai::VAID::<f32>::read_with_metadata(&mut file_lines).unwrap();
+    assert_eq!(read_metadata, metadata);
+    assert_eq!(read_ai.connections, ai.connections);
+    assert_eq!(read_ai.biases, ai.biases);
+    assert_eq!(read_ai.activations, ai.activations);
+
+    let mut ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0).create_variant(10.0);
+    ai.hidden_activations[0] = Activation::Sigmoid;
+    let path = temp_path("metadata.vai");
+    ai.write_with_metadata(&mut std::fs::File::create(&path).unwrap(), &metadata).unwrap();
+    let mut file_lines = std::io::BufReader::new(std::fs::File::open(&path).unwrap()).lines();
+    let (read_ai, read_metadata) = vai::VAI::<2, 1, 4, 1>::read_with_metadata(&mut file_lines).unwrap();
+    assert_eq!(read_metadata, metadata);
+    assert_eq!(read_ai.to_string(), ai.to_string());
+    assert_eq!(read_ai.hidden_activations, ai.hidden_activations);
+}
+
+#[test]
+fn mismatched_models() {
+    let path = temp_path("mismatched.vai");
+    let ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0);
+    ai.write(&mut std::fs::File::create(&path).unwrap()).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(vai::VAI::<2, 1, 4, 1>::read(&mut lines(&text)).is_ok());
+    assert!(vai::VAI::<2, 1, 4, 2>::read(&mut lines(&text)).is_err());
+    assert!(vai::VAI::<3, 1, 4, 1>::read(&mut lines(&text)).is_err());
+    assert!(vai::VAID::<f32>::read(&mut lines(&text)).is_err());
+
+    let ai = vai::VAID::new_deterministic(0, &[2, 4, 4, 1]);
+    ai.write(&mut std::fs::File::create(&path).unwrap()).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(vai::VAI::<2, 1, 4, 1>::read(&mut lines(&text)).is_err());
+    let wrong_layers = text.replace("layers 2 4 4 1", "layers 2 4 4 2");
+    assert!(vai::VAID::<f32>::read(&mut lines(&wrong_layers)).is_err());
+}
+
+#[test]
+fn unsupported_version() {
+    let text = "VAI-MODEL 2\nkind VAID\nlayers 1 1\nweights\nbias 0\n1\n";
+    assert!(vai::VAID::<f32>::read(&mut lines(text)).is_err());
+    let text = "VAI-MODEL 1\nkind VAID\nlayers 1 1\nweights\nbias 0.5\n2\n";
+    let ai = vai::VAID::<f32>::read(&mut lines(text)).unwrap();
+    assert_eq!(ai.connections[0][0], 2.);
+    assert_eq!(ai.biases[0][0], 0.5);
+    assert_eq!(ai.activations, vec![Activation::Identity]);
+}
+
+#[test]
+fn generic_io() {
+    let ai = vai::VAID::new_deterministic(0, &[2, 3, 1]).create_variant(10.0);
+    let mut buffer = Vec::new();
+    ai.write(&mut buffer).unwrap();
+    let read_ai = vai::VAID::<f32>::read_from(&mut std::io::Cursor::new(&buffer)).unwrap();
+    assert_eq!(read_ai.connections, ai.connections);
+
+    let text = ai.to_text().unwrap();
+    assert_eq!(text.as_bytes(), buffer.as_slice());
+    let parsed: vai::VAID = text.parse().unwrap();
+    assert_eq!(parsed.biases, ai.biases);
+
+    let path = temp_path("saved.vaid");
+    ai.save(&path).unwrap();
+    assert_eq!(vai::VAID::<f32>::load(&path).unwrap().connections, ai.connections);
+
+    let ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0).create_variant(10.0);
+    let path = temp_path("saved.vai");
+    ai.save(&path).unwrap();
+    let loaded = vai::VAI::<2, 1, 4, 1>::load(&path).unwrap();
+    assert_eq!(loaded.to_text().unwrap(), ai.to_text().unwrap());
+    let parsed: vai::VAI<2, 1, 4, 1> = ai.to_text().unwrap().parse().unwrap();
+    assert_eq!(parsed.to_string(), ai.to_string());
+    assert!("not a network".parse::<vai::VAI<2, 1, 4, 1>>().is_err());
+}
+
+fn bits(values: &[f32]) -> Vec<u32> {
+    return values.iter().map(|x| x.to_bits()).collect();
+}
+
+fn assert_same_vaid(a: &vai::VAID, b: &vai::VAID) {
+    assert_eq!(a.connections.len(), b.connections.len());
+    for (x, y) in a.connections.iter().zip(&b.connections) {
+        assert_eq!(x.shape(), y.shape());
+        assert_eq!(bits(x.as_slice()), bits(y.as_slice()));
+    }
+    for (x, y) in a.biases.iter().zip(&b.biases) {
+        assert_eq!(bits(x.as_slice()), bits(y.as_slice()));
+    }
+    assert_eq!(a.activations, b.activations);
+}
+
+#[test]
+fn random_round_trips() {
+    for seed in 0..10 {
+        let mut ai = vai::VAID::new_deterministic(seed, &[1 + seed as usize, 7, 3, 2]);
+        for _ in 0..5 {
+            ai = ai.create_variant(100.0);
+        }
+        ai.activations[1] = Activation::LeakyReLU(0.1 * seed as f32);
+
+        let text: vai::VAID = ai.to_text().unwrap().parse().unwrap();
+        assert_same_vaid(&text, &ai);
+        for checksum in [false, true] {
+            let mut buffer = Vec::new();
+            ai.write_binary(&mut buffer, checksum).unwrap();
+            let binary = vai::VAID::<f32>::read_binary(&mut buffer.as_slice()).unwrap();
+            assert_same_vaid(&binary, &ai);
+        }
+
+        let mut ai = vai::VAI::<3, 2, 5, 2>::new_deterministic(seed);
+        for _ in 0..5 {
+            ai = ai.create_variant(100.0);
+        }
+        let text: vai::VAI<3, 2, 5, 2> = ai.to_text().unwrap().parse().unwrap();
+        let mut buffer = Vec::new();
+        ai.write_binary(&mut buffer, true).unwrap();
+        let binary = vai::VAI::<3, 2, 5, 2>::read_binary(&mut buffer.as_slice()).unwrap();
+        for read in [text, binary] {
+            assert_eq!(bits(read.input_connections.as_slice()), bits(ai.input_connections.as_slice()));
+            for i in 0..2 {
+                let expected = bits(ai.hidden_connections[i].as_slice());
+                assert_eq!(bits(read.hidden_connections[i].as_slice()), expected);
+                let expected = bits(ai.hidden_biases[i].as_slice());
+                assert_eq!(bits(read.hidden_biases[i].as_slice()), expected);
+            }
+            assert_eq!(bits(read.output_connections.as_slice()), bits(ai.output_connections.as_slice()));
+            assert_eq!(bits(read.output_bias.as_slice()), bits(ai.output_bias.as_slice()));
+        }
+    }
+}
+
+#[test]
+fn binary_checksum() {
+    let ai = vai::VAID::new_deterministic(0, &[2, 3, 1]).create_variant(10.0);
+    let metadata = Metadata { generation: Some(3), fitness: None, timestamp: Some(5) };
+    let mut buffer = Vec::new();
+    ai.write_binary_with_metadata(&mut buffer, &metadata, true).unwrap();
+    let (_, read_metadata) = vai::VAID::<f32>::read_binary_with_metadata(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read_metadata, metadata);
+
+    let last = buffer.len() - 5;
+    buffer[last] ^= 1;
+    assert!(vai::VAID::<f32>::read_binary(&mut buffer.as_slice()).is_err());
+    assert!(vai::VAI::<2, 1, 3, 0>::read_binary(&mut buffer.as_slice()).is_err());
+    assert!(vai::VAID::<f32>::read_binary(&mut &buffer[..10]).is_err());
+}
+
+#[test]
+fn typed_errors() {
+    use vai::format::NetworkKind;
+    use vai::VaiError;
+
+    let text = "VAI-MODEL 1\nkind VAID\nlayers 1 2\nweights\nbias 0 0\n1\n0.5 3\n";
+    match vai::VAID::<f32>::read(&mut lines(text)) {
+        Err(VaiError::DimensionMismatch { layer, expected, found }) => {
+            assert_eq!((layer, expected, found), (0, 1, 2));
+        }
+        other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+    }
+    let text = "VAI-MODEL 1\nkind VAID\nlayers 1 2\nweights\nbias 0 0\n1\n";
+    let err = vai::VAID::<f32>::read(&mut lines(text)).err().unwrap();
+    assert!(matches!(err, VaiError::UnexpectedEnd { line: 6 }));
+    let text = "VAI-MODEL 1\nkind VAID\nlayers 1 1\nweights\nbias 0\n  1.x\n";
+    match vai::VAID::<f32>::read(&mut lines(text)) {
+        Err(VaiError::Parse { line, column, token }) => {
+            assert_eq!((line, column, token.as_str()), (6, 3, "1.x"));
+        }
+        other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+    }
+    let text = "VAI-MODEL 9\nkind VAID\nlayers 1 1\nweights\n";
+    let err = vai::VAID::<f32>::read(&mut lines(text)).err().unwrap();
+    assert!(matches!(err, VaiError::UnsupportedVersion(9)));
+    let text = "VAI-MODEL 1\nkind VAID\nlayers 1 1\nweights\nbias 0\n1\n";
+    let err = vai::VAI::<1, 1, 1, 0>::read(&mut lines(text)).err().unwrap();
+    let expected = VaiError::WrongKind { expected: NetworkKind::VAI, found: NetworkKind::VAID };
+    assert_eq!(err.to_string(), expected.to_string());
+
+    // Legacy files report the layer that failed
+    let text = "2\n1\n1 \n2\n1 \n1 2 \n";
+    let err = vai::VAID::<f32>::read(&mut lines(text)).err().unwrap();
+    assert!(matches!(err, VaiError::DimensionMismatch { layer: 1, expected: 1, found: 2 }));
+    let err = vai::vaid::read_matrix::<f32>(&mut lines("2\n1 2\nx 4\n")).err().unwrap();
+    assert!(matches!(err, VaiError::Parse { line: 3, column: 1, .. }));
+
+    let mut ai = vai::VAID::new_deterministic(0, &[1, 1]);
+    ai.activations[0] = Activation::Custom(f32::sin);
+    assert!(matches!(ai.to_text(), Err(VaiError::CustomActivation)));
+}
+
+#[test]
+fn hostile_binary_sizes() {
+    use vai::vair::{Cell, VAIR};
+    use vai::VaiError;
+
+    /// Replaces the layer sizes after the magic, version, kind, flags and count
+    fn with_sizes(buffer: &[u8], sizes: &[u32]) -> Vec<u8> {
+        let mut buffer = buffer.to_vec();
+        for (i, size) in sizes.iter().enumerate() {
+            buffer[14 + i * 4..18 + i * 4].copy_from_slice(&size.to_le_bytes());
+        }
+        return buffer;
+    }
+
+    let ai = vai::VAID::new_deterministic(0, &[2, 3, 1]);
+    let mut buffer = Vec::new();
+    ai.write_binary(&mut buffer, true).unwrap();
+    // Sizes whose product overflows
+    let hostile = with_sizes(&buffer, &[u32::MAX, u32::MAX]);
+    let err = vai::VAID::<f32>::read_binary(&mut hostile.as_slice()).err().unwrap();
+    assert!(matches!(err, VaiError::Format(_)));
+    // Sizes that would need many gigabytes, but aren't backed by the file
+    let hostile = with_sizes(&buffer, &[1 << 20, 1 << 20]);
+    let err = vai::VAID::<f64>::read_binary(&mut hostile.as_slice()).err().unwrap();
+    assert!(matches!(err, VaiError::Io(_)));
+
+    let ai = VAIR::new_deterministic(0, 2, 3, 1, Cell::LSTM);
+    let mut buffer = Vec::new();
+    ai.write_binary(&mut buffer, true).unwrap();
+    let hostile = with_sizes(&buffer, &[u32::MAX, u32::MAX, 1]);
+    let err = VAIR::<f32>::read_binary(&mut hostile.as_slice()).err().unwrap();
+    assert!(matches!(err, VaiError::Format(_)));
+    let hostile = with_sizes(&buffer, &[1 << 20, 1 << 20, 1]);
+    assert!(matches!(VAIR::<f32>::read_binary(&mut hostile.as_slice()), Err(VaiError::Io(_))));
+}
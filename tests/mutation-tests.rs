@@ -0,0 +1,93 @@
+#![allow(clippy::needless_return)]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use vai::{MutationConfig, MutationDistribution, VAI, VAID};
+
+const DISTRIBUTIONS: [MutationDistribution; 5] = [
+    MutationDistribution::InfiniteMap,
+    MutationDistribution::Gaussian,
+    MutationDistribution::Cauchy,
+    MutationDistribution::Uniform,
+    MutationDistribution::Laplace,
+];
+
+fn samples(distribution: MutationDistribution) -> Vec<f32> {
+    let mut rng = StdRng::seed_from_u64(0);
+    return (0..20000).map(|_| distribution.sample(&mut rng)).collect();
+}
+
+fn variance(values: &[f32]) -> f32 {
+    return values.iter().map(|x| x * x).sum::<f32>() / values.len() as f32;
+}
+
+#[test]
+fn distributions() {
+    for distribution in DISTRIBUTIONS {
+        let values = samples(distribution);
+        assert!(values.iter().all(|x| x.is_finite()), "{:?}", distribution);
+        let positive = values.iter().filter(|x| **x > 0.).count();
+        assert!((9000..11000).contains(&positive), "{:?}", distribution);
+    }
+    let uniform = samples(MutationDistribution::Uniform);
+    assert!(uniform.iter().all(|x| (-1.0..=1.0).contains(x)));
+    assert!((variance(&uniform) - 1.0 / 3.0).abs() < 0.02);
+    assert!((variance(&samples(MutationDistribution::Gaussian)) - 1.0).abs() < 0.05);
+    assert!((variance(&samples(MutationDistribution::Laplace)) - 2.0).abs() < 0.15);
+    // Half of a Cauchy distribution with a scale of 1 lies within +-1
+    let cauchy = samples(MutationDistribution::Cauchy);
+    let within = cauchy.iter().filter(|x| x.abs() < 1.0).count();
+    assert!((9500..10500).contains(&within));
+}
+
+#[test]
+fn default_matches_create_variant() {
+    let mut a = VAID::new_deterministic(0, &[3, 4, 2]);
+    let mut b = a.clone();
+    assert!(a.create_variant(1.0) == b.create_variant_with(1.0, MutationConfig::default()));
+    assert!(a.create_layer_variant(1.0) == b.create_layer_variant_with(1.0, MutationConfig::default()));
+
+    let mut a = VAI::<3, 2, 4, 1>::new_deterministic(0);
+    let mut b = a.clone();
+    let dense = MutationConfig::dense(MutationDistribution::InfiniteMap);
+    assert_eq!(a.create_variant(1.0).to_string(), b.create_variant_with(1.0, dense).to_string());
+    assert_eq!(
+        a.create_layer_variant(1.0).to_string(),
+        b.create_layer_variant_with(1.0, dense).to_string()
+    );
+}
+
+#[test]
+fn sparse_mutation() {
+    let mut ai = VAID::new_deterministic(0, &[20, 20, 20]);
+    let none = MutationConfig::new(MutationDistribution::Gaussian, 0.0);
+    assert!(ai.create_variant_with(1.0, none).connections == ai.connections);
+
+    let sparse = MutationConfig::new(MutationDistribution::Gaussian, 0.1);
+    let variant = ai.create_variant_with(100.0, sparse);
+    let mut changed = 0;
+    for (a, b) in variant.connections.iter().zip(&ai.connections) {
+        changed += a.iter().zip(b.iter()).filter(|(x, y)| x != y).count();
+    }
+    // 800 weights, each changed with a probability of 0.1
+    assert!((40..120).contains(&changed), "{}", changed);
+
+    let variant = ai.create_layer_variant_with(100.0, sparse);
+    let changed_layers =
+        variant.connections.iter().zip(&ai.connections).filter(|(a, b)| a != b).count();
+    assert_eq!(changed_layers, 1);
+}
+
+#[test]
+fn vai_distributions() {
+    for distribution in DISTRIBUTIONS {
+        let mut a = VAI::<2, 1, 4, 1, f64>::new_deterministic(0);
+        let mut b = a.clone();
+        let mutation = MutationConfig::dense(distribution);
+        let variant = a.create_variant_with(1.0, mutation);
+        assert_eq!(variant.to_string(), b.create_variant_with(1.0, mutation).to_string());
+        assert_ne!(variant.to_string(), a.to_string());
+        let variant = a.create_layer_variant_with(1.0, mutation);
+        assert_ne!(variant.to_string(), a.to_string());
+    }
+}
@@ -0,0 +1,123 @@
+#![allow(clippy::needless_return)]
+
+use vai::neat::{speciate, shared_fitness, Compatibility, Genome, InnovationTracker, MutationRates};
+
+fn xor_score(genome: &Genome) -> f32 {
+    let mut score = 0.0;
+    for (a, b) in [(0., 0.), (0., 1.), (1., 0.), (1., 1.)] {
+        let target = if a != b { 1. } else { 0. };
+        let output = genome.process_slice(&[a, b])[0];
+        score += (target - output) * (target - output);
+    }
+    return score;
+}
+
+#[test]
+fn evaluation_test() {
+    let mut tracker = InnovationTracker::new(2, 1);
+    let mut genome = Genome::new_deterministic(0, 2, 1, &mut tracker);
+    assert_eq!(genome.connections.len(), 2);
+    genome.connections[0].weight = 2.;
+    genome.connections[1].weight = -1.;
+    genome.nodes[2].bias = 0.5;
+    assert_eq!(genome.process_slice(&[1., 3.]), vec![-0.5]);
+
+    // Splitting a connection keeps its weight on the far side
+    assert!(genome.add_node(&mut tracker));
+    assert_eq!(genome.nodes.len(), 4);
+    assert_eq!(genome.connections.iter().filter(|x| x.enabled).count(), 3);
+    let layers = genome.process_slice_transparent(&[1., 3.]);
+    assert_eq!(layers[0], vec![1., 3.]);
+    assert_eq!(layers[1].len(), 1);
+    assert_eq!(layers[2], genome.process_slice(&[1., 3.]));
+    assert_eq!(layers[2], vec![-0.5]);
+
+    // New connections start with no effect, and never form cycles
+    for _ in 0..10 {
+        genome.add_connection(&mut tracker);
+        assert_eq!(genome.process_slice(&[1., 3.]), vec![-0.5]);
+    }
+    assert!(!genome.add_connection(&mut tracker));
+    let innovations: Vec<usize> = genome.connections.iter().map(|x| x.innovation).collect();
+    assert!(innovations.windows(2).all(|x| x[0] < x[1]));
+}
+
+#[test]
+fn innovation_test() {
+    let mut tracker = InnovationTracker::new(3, 2);
+    let a = Genome::new_deterministic(0, 3, 2, &mut tracker);
+    let b = Genome::new_deterministic(1, 3, 2, &mut tracker);
+    assert_eq!(a.connections, b.connections);
+    assert_eq!(tracker.connection(0, 3), 0);
+    assert_eq!(tracker.split(0), tracker.split(0));
+    assert_ne!(tracker.split(0), tracker.split(1));
+
+    let coefficients = Compatibility::default();
+    assert_eq!(a.compatibility(&b, &coefficients), 0.);
+    let mut c = a.clone();
+    c.add_node(&mut tracker);
+    assert!(a.compatibility(&c, &coefficients) > 0.);
+    assert_eq!(a.compatibility(&c, &coefficients), c.compatibility(&a, &coefficients));
+
+    let genomes = vec![a.clone(), c.clone(), b.clone()];
+    let species = speciate(&genomes, 0.1, &coefficients);
+    assert_eq!(species, vec![vec![0, 2], vec![1]]);
+    assert_eq!(shared_fitness(&species, &[2., 3., 4.]), vec![1., 3., 2.]);
+    assert_eq!(speciate(&genomes, 10., &coefficients), vec![vec![0, 1, 2]]);
+}
+
+#[test]
+fn crossover_test() {
+    let mut tracker = InnovationTracker::new(2, 1);
+    let mut a = Genome::new_deterministic(0, 2, 1, &mut tracker).create_variant(10.);
+    let mut b = Genome::new_deterministic(1, 2, 1, &mut tracker).create_variant(10.);
+    a.add_node(&mut tracker);
+    b.add_connection(&mut tracker);
+    for _ in 0..10 {
+        let child = a.crossover(&b);
+        // The child has the structure of the fitter parent
+        assert_eq!(child.nodes.len(), a.nodes.len());
+        let innovations = |g: &Genome| g.connections.iter().map(|x| x.innovation).collect::<Vec<_>>();
+        assert_eq!(innovations(&child), innovations(&a));
+        for (connection, parent) in child.connections.iter().zip(&a.connections) {
+            let other = b.connections.iter().find(|x| x.innovation == parent.innovation);
+            assert!(connection.weight == parent.weight || other.map(|x| x.weight) == Some(connection.weight));
+        }
+        child.process_slice(&[1., 0.]);
+    }
+}
+
+#[test]
+fn xor_test() {
+    let mut tracker = InnovationTracker::new(2, 1);
+    let mut best = Genome::new_deterministic(0, 2, 1, &mut tracker);
+    let mut best_score = xor_score(&best);
+    let initial_score = best_score;
+    let rates = MutationRates {add_connection: 0.2, add_node: 0.1, ..Default::default()};
+    for _ in 0..3000 {
+        let test = best.mutate(&mut tracker, &rates);
+        let test_score = xor_score(&test);
+        if test_score <= best_score {
+            best = test;
+            best_score = test_score;
+        }
+    }
+    println!("Initial Score: {}, Final Score: {}", initial_score, best_score);
+    assert!(best_score < initial_score);
+    assert!(best.nodes.len() > 3);
+}
+
+#[test]
+fn repeated_split_leaves_genome_unchanged() {
+    let mut tracker = InnovationTracker::new(1, 1);
+    let mut genome = Genome::new_deterministic(0, 1, 1, &mut tracker);
+    assert!(genome.add_node(&mut tracker));
+    // Re-enable the split connection, and disable the new ones,
+    // so the only connection that can be chosen was already split
+    for connection in genome.connections.iter_mut() {
+        connection.enabled = !connection.enabled;
+    }
+    let before = genome.connections.clone();
+    assert!(!genome.add_node(&mut tracker));
+    assert_eq!(genome.connections, before);
+}
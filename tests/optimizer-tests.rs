@@ -0,0 +1,82 @@
+#![allow(clippy::needless_return)]
+
+use std::io::BufRead;
+
+use nalgebra as na;
+use vai::optimizer::Schedule;
+use vai::{Loss, Optimizer};
+
+fn data() -> (na::DMatrix<f32>, na::DMatrix<f32>) {
+    let inputs = na::DMatrix::<f32>::from_fn(1, 11, |_, c| 0.1 * c as f32);
+    let targets = inputs.map(|x| 2. * (x - 0.5) * (x - 0.5));
+    return (inputs, targets);
+}
+
+#[test]
+fn optimizers_train() {
+    let (inputs, targets) = data();
+    let optimizers = [
+        Optimizer::sgd(0.05, 0.9),
+        Optimizer::rms_prop(0.01),
+        Optimizer::adam(0.01),
+    ];
+    for mut optimizer in optimizers {
+        let mut ai = vai::VAI::<1, 1, 8, 0>::new_deterministic(0).create_variant(10.0);
+        let initial_loss = optimizer.train_step(&mut ai, &inputs, &targets, Loss::MeanSquaredError);
+        let mut loss = initial_loss;
+        for _ in 0..2000 {
+            loss = optimizer.train_step(&mut ai, &inputs, &targets, Loss::MeanSquaredError);
+        }
+        println!("{:?}: {} -> {}", optimizer.method, initial_loss, loss);
+        assert!(loss < initial_loss * 0.1);
+    }
+}
+
+#[test]
+fn optimizer_resume() {
+    let (inputs, targets) = data();
+    let start = vai::VAID::new_deterministic(0, &[1, 8, 1]).create_variant(10.0);
+
+    let mut uninterrupted = start.clone();
+    let mut optimizer = Optimizer::adam(0.01);
+    optimizer.weight_decay = 0.01;
+    optimizer.schedule = Schedule::Cosine { period: 100, min_rate: 0.001 };
+    let mut paused = uninterrupted.clone();
+    let mut paused_optimizer = optimizer.clone();
+    for _ in 0..100 {
+        optimizer.train_step(&mut uninterrupted, &inputs, &targets, Loss::MeanSquaredError);
+    }
+
+    for _ in 0..50 {
+        paused_optimizer.train_step(&mut paused, &inputs, &targets, Loss::MeanSquaredError);
+    }
+    let path = std::env::temp_dir().join(format!("vai-{}-optimizer.vaid", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    paused.write(&mut file).unwrap();
+    paused_optimizer.write(&mut file).unwrap();
+    drop(file);
+
+    let mut lines = std::io::BufReader::new(std::fs::File::open(&path).unwrap()).lines();
+    let mut resumed = vai::VAID::<f32>::read(&mut lines).unwrap();
+    let mut resumed_optimizer = Optimizer::read(&mut lines).unwrap();
+    assert_eq!(resumed_optimizer, paused_optimizer);
+    for _ in 0..50 {
+        resumed_optimizer.train_step(&mut resumed, &inputs, &targets, Loss::MeanSquaredError);
+    }
+    assert_eq!(resumed.connections, uninterrupted.connections);
+    assert_eq!(resumed.biases, uninterrupted.biases);
+}
+
+#[test]
+fn learning_rate_schedules() {
+    let mut optimizer = Optimizer::sgd(1.0, 0.);
+    optimizer.steps = 10;
+    optimizer.schedule = Schedule::Step { step_size: 4, factor: 0.5 };
+    assert_eq!(optimizer.current_learning_rate(), 0.25);
+    optimizer.schedule = Schedule::Exponential { factor: 0.5 };
+    assert_eq!(optimizer.current_learning_rate(), 0.5f32.powi(10));
+    optimizer.schedule = Schedule::Cosine { period: 20, min_rate: 0.1 };
+    assert!((optimizer.current_learning_rate() - 0.55).abs() < 1e-6);
+    optimizer.steps = 30;
+    assert_eq!(optimizer.current_learning_rate(), 0.1);
+}
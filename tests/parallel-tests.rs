@@ -0,0 +1,69 @@
+#![cfg(feature = "parallel")]
+#![allow(clippy::needless_return)]
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use vai::parallel::{evaluate, evaluate_mut, stream};
+use vai::{Population, VAI, VAID};
+
+/// Negative squared error from a line at randomly chosen points
+fn random_fitness<G: Rng>(ai: &VAID, rng: &mut G) -> f32 {
+    let mut score = 0.0;
+    for _ in 0..20 {
+        let x = rng.gen::<f32>();
+        let error = ai.process_slice(&[x])[0] - (0.5 * x - 1.0);
+        score -= error * error;
+    }
+    return score;
+}
+
+#[test]
+fn deterministic_streams() {
+    let mut parent = VAID::new_deterministic(0, &[1, 4, 1]);
+    let individuals: Vec<VAID> = (0..64).map(|_| parent.create_variant(10.0)).collect();
+    let scores = evaluate(&individuals, 7, random_fitness::<StdRng>);
+    // The same as evaluating each individual in order
+    let expected: Vec<f32> = individuals
+        .iter()
+        .enumerate()
+        .map(|(i, ai)| random_fitness(ai, &mut stream::<StdRng>(7, i)))
+        .collect();
+    assert_eq!(scores, expected);
+    assert_eq!(evaluate(&individuals, 7, random_fitness::<StdRng>), scores);
+    assert_ne!(evaluate(&individuals, 8, random_fitness::<StdRng>), scores);
+
+    // Streams can be any rng
+    let scores = evaluate(&individuals, 7, random_fitness::<ChaCha8Rng>);
+    let expected: Vec<f32> = individuals
+        .iter()
+        .enumerate()
+        .map(|(i, ai)| random_fitness(ai, &mut stream::<ChaCha8Rng>(7, i)))
+        .collect();
+    assert_eq!(scores, expected);
+
+    let mut individuals = vec![VAI::<1, 1, 3, 0>::new_deterministic(0); 16];
+    let mutate = |ai: &mut VAI<1, 1, 3, 0>, rng: &mut rand::rngs::StdRng| {
+        *ai = ai.create_variant(rng.gen::<f32>() * 10.0);
+        return ai.process_slice(&[1.0])[0];
+    };
+    let scores = evaluate_mut(&mut individuals, 3, mutate);
+    let mut again = vec![VAI::<1, 1, 3, 0>::new_deterministic(0); 16];
+    assert_eq!(evaluate_mut(&mut again, 3, mutate), scores);
+    for (a, b) in individuals.iter().zip(&again) {
+        assert_eq!(a.to_string(), b.to_string());
+    }
+}
+
+#[test]
+fn parallel_population() {
+    let fitness = |ai: &VAID| random_fitness(ai, &mut stream::<StdRng>(0, 0));
+    let individuals: Vec<VAID> =
+        (0..16).map(|i| VAID::new_deterministic(i, &[1, 4, 1])).collect();
+    let mut sequential = Population::new_deterministic(1, individuals.clone(), fitness);
+    let mut parallel = Population::new_deterministic(1, individuals, fitness);
+    for _ in 0..10 {
+        assert_eq!(parallel.step_parallel(), sequential.step());
+    }
+    assert_eq!(parallel.best().unwrap().1, sequential.best().unwrap().1);
+}
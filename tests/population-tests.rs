@@ -0,0 +1,61 @@
+#![allow(clippy::needless_return)]
+
+use vai::population::{Mutation, Selection};
+use vai::{Crossover, Network, Population};
+
+/// Negative squared error from a line, so that higher is better
+fn linear_fitness<N: Network<Scalar = f32>>(ai: &N) -> f32 {
+    let mut score = 0.0;
+    for i in 0..=10 {
+        let x = 0.1 * i as f32;
+        let target_y = -2.0 * x + 0.75;
+        let ai_y = ai.process_slice(&[x])[0];
+        score -= (target_y - ai_y) * (target_y - ai_y);
+    }
+    return score;
+}
+
+#[test]
+fn population_selections() {
+    let selections = [
+        Selection::Truncation(0.25),
+        Selection::Tournament(3),
+        Selection::Roulette,
+        Selection::Rank,
+    ];
+    for selection in selections {
+        let individuals = (0..20)
+            .map(vai::VAI::<1, 1, 4, 0>::new_deterministic)
+            .collect();
+        let mut population = Population::new_deterministic(0, individuals, linear_fitness);
+        population.selection = selection;
+        population.elitism = 2;
+        let stats = population.run(100);
+        assert_eq!(stats.len(), 100);
+        assert_eq!(population.generation, 100);
+        let initial = stats[0].best;
+        let (_, best) = population.best().unwrap();
+        println!("{:?}: {} -> {}", selection, initial, best);
+        assert!(best > initial * 0.1);
+        // With elitism, the best never gets worse
+        for pair in stats.windows(2) {
+            assert!(pair[1].best >= pair[0].best);
+            assert!(pair[0].worst <= pair[0].mean + 1e-4);
+            assert!(pair[0].mean <= pair[0].best + 1e-4);
+        }
+    }
+}
+
+#[test]
+fn population_vaid_crossover() {
+    let individuals = (0..20)
+        .map(|i| vai::VAID::new_deterministic(i, &[1, 4, 1]))
+        .collect();
+    let mut population = Population::new_deterministic(0, individuals, linear_fitness);
+    population.crossover = Some(Crossover::Uniform);
+    population.mutation = Mutation::LayerVariant(1.0);
+    let stats = population.run(100);
+    let (best_ai, best) = population.best().unwrap();
+    assert_eq!(best, linear_fitness(best_ai));
+    assert!(best > stats[0].best * 0.1);
+}
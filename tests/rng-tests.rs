@@ -0,0 +1,85 @@
+#![allow(clippy::needless_return)]
+
+use nalgebra as na;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use vai::cmaes::CmaEs;
+use vai::vair::Cell;
+use vai::{Crossover, Network, Parameters, VAI, VAID, VAIR};
+
+/// Evolves a network for a few generations, using only its own rng
+fn evolve<N: Network>(mut ai: N) -> N {
+    for _ in 0..5 {
+        let variant = ai.create_layer_variant(1.0);
+        ai = ai.crossover_with(&variant, Crossover::Uniform).create_variant(1.0);
+    }
+    return ai;
+}
+
+#[test]
+fn vaid_custom_rng() {
+    let a = evolve(VAID::<f32, ChaCha8Rng>::with_seed(3, &[2, 4, 1]));
+    let b = evolve(VAID::<f32, ChaCha8Rng>::with_seed(3, &[2, 4, 1]));
+    assert!(a == b);
+    let c = evolve(VAID::<f32, ChaCha8Rng>::with_seed(4, &[2, 4, 1]));
+    assert_ne!(a.connections, c.connections);
+
+    // Weights and behaviour survive a conversion to a network with another rng
+    let mut buffer = Vec::new();
+    a.write_binary(&mut buffer, true).unwrap();
+    let read_ai = VAID::<f32, StdRng>::read_binary(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read_ai.connections, a.connections);
+    assert_eq!(read_ai.process_slice(&[0.5, 0.1]), a.process_slice(&[0.5, 0.1]));
+}
+
+#[test]
+fn vai_custom_rng() {
+    let a = evolve(VAI::<2, 1, 4, 1, f64, ChaCha8Rng>::new_deterministic(3));
+    let b = evolve(VAI::<2, 1, 4, 1, f64, ChaCha8Rng>::new_deterministic(3));
+    assert_eq!(a.to_string(), b.to_string());
+
+    let vaid = VAID::from(&a);
+    assert_eq!(vaid.process_slice(&[0.5, 0.1]), a.process_slice(&[0.5, 0.1]));
+    let back = VAI::<2, 1, 4, 1, f64, ChaCha8Rng>::try_from(&vaid).unwrap();
+    assert_eq!(back.to_string(), a.to_string());
+}
+
+#[test]
+fn mutation_helpers() {
+    let matrix = na::DMatrix::<f32>::zeros(3, 2);
+    let a = vai::vaid::create_variant_with_rng(&mut ChaCha8Rng::seed_from_u64(1), &matrix, 1.0);
+    let b = vai::vaid::create_variant_with_rng(&mut ChaCha8Rng::seed_from_u64(1), &matrix, 1.0);
+    assert_eq!(a, b);
+    assert_ne!(a, matrix);
+
+    // Unsized rngs can be passed too
+    let rng: &mut dyn RngCore = &mut StdRng::seed_from_u64(1);
+    let c = vai::vaid::create_variant_with_rng(rng, &matrix, 1.0);
+    assert_eq!(c, vai::vaid::create_variant_with_rng(&mut StdRng::seed_from_u64(1), &matrix, 1.0));
+
+    let matrix = na::SMatrix::<f64, 2, 2>::zeros();
+    let a = vai::vai::create_variant_with_rng(&mut ChaCha8Rng::seed_from_u64(1), &matrix, 1.0);
+    let b = vai::vai::create_variant_with_rng(&mut ChaCha8Rng::seed_from_u64(1), &matrix, 1.0);
+    assert_eq!(a, b);
+
+    // The old names still work
+    #[allow(deprecated)]
+    let c = vai::vai::create_variant_stdrng(&mut ChaCha8Rng::seed_from_u64(1), &matrix, 1.0);
+    assert_eq!(a, c);
+}
+
+#[test]
+fn vair_and_drivers_custom_rng() {
+    let mut a = VAIR::<f64, ChaCha8Rng>::with_seed(3, 2, 3, 1, Cell::GRU);
+    let mut b = VAIR::<f64, ChaCha8Rng>::with_seed(3, 2, 3, 1, Cell::GRU);
+    assert!(a.create_variant(1.0) == b.create_variant(1.0));
+
+    let ai = VAID::<f32>::new_deterministic(0, &[2, 2, 1]);
+    let run = || {
+        let mut cmaes = CmaEs::<VAID, ChaCha8Rng>::with_seed(5, ai.clone(), 0.5);
+        cmaes.run(5, |x| -x.parameters().iter().map(|p| p * p).sum::<f32>());
+        return cmaes.mean_network().connections;
+    };
+    assert_eq!(run(), run());
+}
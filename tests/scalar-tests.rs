@@ -0,0 +1,111 @@
+#![allow(clippy::needless_return)]
+
+use vai::vaid::Workspace;
+use vai::{Activation, Network, VAI, VAID};
+
+fn lines(text: &str) -> impl Iterator<Item = std::io::Result<String>> + '_ {
+    return text.lines().map(|x| Ok(x.to_owned()));
+}
+
+#[test]
+fn f64_matches_f32() {
+    let mut ai = VAI::<2, 1, 4, 1>::new_deterministic(0);
+    let mut wide = VAI::<2, 1, 4, 1, f64>::new_deterministic(0);
+    ai = ai.create_variant(10.0);
+    wide = wide.create_variant(10.0);
+    ai.hidden_activations[0] = Activation::Tanh;
+    wide.hidden_activations[0] = Activation::Tanh;
+    // Mutation draws the same random numbers for either scalar type
+    for (a, b) in ai.input_connections.iter().zip(wide.input_connections.iter()) {
+        assert!((*a as f64 - b).abs() < 1e-5);
+    }
+    let output = ai.process_slice(&[0.5, -0.25])[0] as f64;
+    let wide_output = wide.process_slice(&[0.5, -0.25])[0];
+    assert!((output - wide_output).abs() < 1e-4);
+
+    let vaid = VAID::from(&wide);
+    assert_eq!(vaid.process_slice(&[0.5, -0.25]), vec![wide_output]);
+    let mut workspace = Workspace::<f64>::for_network(&vaid);
+    assert_eq!(vaid.process_with(&[0.5, -0.25], &mut workspace), &[wide_output]);
+    let back = VAI::<2, 1, 4, 1, f64>::try_from(&vaid).unwrap();
+    assert_eq!(back.to_string(), wide.to_string());
+}
+
+#[test]
+fn f64_mutation() {
+    let mut ai = VAID::<f64>::with_seed(0, &[3, 5, 2]);
+    let variant = ai.create_variant(10.0);
+    assert_ne!(variant.connections, ai.connections);
+    let variant = Network::create_layer_variant(&mut ai, 10.0);
+    assert_ne!(variant.connections, ai.connections);
+    let mut structural = variant.clone();
+    structural.add_neuron(0);
+    structural.insert_layer(1);
+    let inputs = [0.3, -0.7, 1.1];
+    let expected = variant.process_slice(&inputs);
+    for (a, b) in structural.process_slice(&inputs).iter().zip(&expected) {
+        assert!((a - b).abs() < 1e-12);
+    }
+    let child = ai.crossover(&variant);
+    assert_eq!(child.connections.len(), 2);
+}
+
+#[test]
+fn f64_read_write() {
+    let mut ai = VAID::<f64>::with_seed(0, &[2, 3, 1]);
+    ai = ai.create_variant(10.0);
+    ai.connections[0][0] = 0.1 + 1e-12;
+
+    // The text format keeps every digit of the weights
+    let read_ai: VAID<f64> = ai.to_text().unwrap().parse().unwrap();
+    assert_eq!(read_ai.connections, ai.connections);
+    assert_eq!(read_ai.biases, ai.biases);
+
+    let mut buffer = Vec::new();
+    ai.write_binary(&mut buffer, true).unwrap();
+    let read_ai = VAID::<f64>::read_binary(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read_ai.connections, ai.connections);
+    assert_eq!(read_ai.biases, ai.biases);
+
+    // Either scalar type can be read from a file written with the other
+    let narrow = VAID::<f32>::read_binary(&mut buffer.as_slice()).unwrap();
+    assert_eq!(narrow.connections[0][0], ai.connections[0][0] as f32);
+    let mut buffer = Vec::new();
+    narrow.write_binary(&mut buffer, false).unwrap();
+    let wide = VAID::<f64>::read_binary(&mut buffer.as_slice()).unwrap();
+    assert_eq!(wide.connections[0][0], narrow.connections[0][0] as f64);
+
+    let ai = VAI::<2, 1, 3, 0, f64>::new_deterministic(0).create_variant(10.0);
+    let read_ai: VAI<2, 1, 3, 0, f64> = ai.to_text().unwrap().parse().unwrap();
+    assert_eq!(read_ai.to_string(), ai.to_string());
+    let mut buffer = Vec::new();
+    ai.write_binary(&mut buffer, false).unwrap();
+    let read_ai = VAI::<2, 1, 3, 0, f64>::read_binary(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read_ai.to_string(), ai.to_string());
+
+    let matrix = vai::vaid::read_matrix::<f64>(&mut lines("2\n1 2\n3 4.000000000001\n")).unwrap();
+    assert_eq!(matrix[(1, 1)], 4.000000000001);
+}
+
+#[test]
+fn f64_vair() {
+    use vai::vair::{Cell, VAIR};
+
+    for cell in [Cell::Elman, Cell::GRU, Cell::LSTM] {
+        let mut ai = VAIR::new_deterministic(0, 2, 3, 1, cell).create_variant(10.0);
+        let mut wide = VAIR::<f64>::with_seed(0, 2, 3, 1, cell).create_variant(10.0);
+        for step in 0..3 {
+            let input = [0.5 - step as f32, 0.25];
+            let output = ai.step(&input)[0] as f64;
+            let wide_output = wide.step(&[input[0] as f64, input[1] as f64])[0];
+            assert!((output - wide_output).abs() < 1e-4, "{:?}", cell);
+        }
+
+        let mut buffer = Vec::new();
+        wide.write_binary(&mut buffer, true).unwrap();
+        let read_ai = VAIR::<f64>::read_binary(&mut buffer.as_slice()).unwrap();
+        assert_eq!(read_ai.hidden_connections, wide.hidden_connections);
+        let read_ai: VAIR<f64> = wide.to_text().unwrap().parse().unwrap();
+        assert_eq!(read_ai.output_bias, wide.output_bias);
+    }
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "serde")]
+#![allow(clippy::needless_return)]
+
+use vai::Activation;
+
+#[test]
+fn serde_round_trip() {
+    let mut ai = vai::VAID::new_deterministic(0, &[3, 4, 2]).create_variant(10.0);
+    ai.activations[0] = Activation::LeakyReLU(0.1);
+    let json = serde_json::to_string(&ai).unwrap();
+    let read_ai: vai::VAID = serde_json::from_str(&json).unwrap();
+    assert_eq!(read_ai.connections, ai.connections);
+    assert_eq!(read_ai.biases, ai.biases);
+    assert_eq!(read_ai.activations, ai.activations);
+
+    let mut ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0).create_variant(10.0);
+    ai.hidden_activations[0] = Activation::Tanh;
+    let json = serde_json::to_string(&ai).unwrap();
+    let read_ai: vai::VAI<2, 1, 4, 1> = serde_json::from_str(&json).unwrap();
+    assert_eq!(read_ai.to_string(), ai.to_string());
+    assert_eq!(read_ai.hidden_activations, ai.hidden_activations);
+
+    // A VAID with the same shape can be read from a VAI
+    let vaid: vai::VAID = serde_json::from_str(&json).unwrap();
+    assert_eq!(vaid.process_slice(&[0.5, 0.2]), ai.process_slice(&[0.5, 0.2]));
+}
+
+#[test]
+fn serde_validation() {
+    let ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0);
+    let json = serde_json::to_string(&ai).unwrap();
+    assert!(serde_json::from_str::<vai::VAI<2, 1, 4, 2>>(&json).is_err());
+    assert!(serde_json::from_str::<vai::VAI<2, 1, 5, 1>>(&json).is_err());
+
+    let json = r#"{"layers":[1,1],"weights":[{"activation":"relu","bias":[0],"connections":[1,2]}]}"#;
+    assert!(serde_json::from_str::<vai::VAID>(json).is_err());
+    let json = r#"{"layers":[1,1],"weights":[{"activation":"relu","bias":[0],"connections":[2]}]}"#;
+    let ai: vai::VAID = serde_json::from_str(json).unwrap();
+    assert_eq!(ai.process_slice(&[3.]), vec![6.]);
+
+    // There must be an input size, which a VAID without connections doesn't have
+    assert!(serde_json::from_str::<vai::VAID>(r#"{"layers":[],"weights":[]}"#).is_err());
+    assert!(serde_json::to_string(&vai::VAID::new_deterministic(0, &[2])).is_err());
+
+    // Layer sizes whose product overflows are rejected, rather than panicking
+    let json = format!(
+        r#"{{"layers":[{0},{0}],"weights":[{{"activation":"relu","bias":[0],"connections":[2]}}]}}"#,
+        usize::MAX
+    );
+    assert!(serde_json::from_str::<vai::VAID>(&json).is_err());
+
+    let mut ai = vai::VAID::new_deterministic(0, &[1, 1]);
+    ai.activations[0] = Activation::Custom(f32::sin);
+    assert!(serde_json::to_string(&ai).is_err());
+
+    // A VAID missing a bias or activation can't be serialized
+    let mut ai = vai::VAID::new_deterministic(0, &[1, 2, 1]);
+    ai.biases.pop();
+    assert!(serde_json::to_string(&ai).is_err());
+    let mut ai = vai::VAID::new_deterministic(0, &[1, 2, 1]);
+    ai.activations.clear();
+    assert!(serde_json::to_string(&ai).is_err());
+}
+
+#[test]
+fn serde_f64() {
+    let ai = vai::VAID::<f64>::with_seed(0, &[2, 3, 1]).create_variant(10.0);
+    let json = serde_json::to_string(&ai).unwrap();
+    let read_ai: vai::VAID<f64> = serde_json::from_str(&json).unwrap();
+    // serde_json may round the last digit of an f64
+    for (a, b) in read_ai.connections.iter().zip(&ai.connections) {
+        assert!((a - b).amax() < 1e-12);
+    }
+
+    let ai = vai::VAI::<2, 1, 3, 1, f64>::new_deterministic(0).create_variant(10.0);
+    let json = serde_json::to_string(&ai).unwrap();
+    let read_ai: vai::VAI<2, 1, 3, 1, f64> = serde_json::from_str(&json).unwrap();
+    assert!((read_ai.output_connections - ai.output_connections).amax() < 1e-12);
+    assert!((read_ai.output_bias - ai.output_bias).amax() < 1e-12);
+}
+
+#[test]
+fn serde_with_rng() {
+    use rand::Rng;
+    use rand_chacha::ChaCha12Rng;
+    use vai::serialization::WithRng;
+
+    let mut ai = vai::VAID::<f32, ChaCha12Rng>::with_seed(0, &[2, 3, 1]).create_variant(1.0);
+    let json = serde_json::to_string(&WithRng(ai.clone())).unwrap();
+    let WithRng(mut read_ai): WithRng<vai::VAID<f32, ChaCha12Rng>> =
+        serde_json::from_str(&json).unwrap();
+    assert!(read_ai == ai);
+    assert!(read_ai.create_variant(1.0) == ai.create_variant(1.0));
+
+    // The rng state is ignored without the wrapper, and required with it
+    let read_ai: vai::VAID<f32, ChaCha12Rng> = serde_json::from_str(&json).unwrap();
+    assert_eq!(read_ai.connections, ai.connections);
+    let json = serde_json::to_string(&ai).unwrap();
+    assert!(serde_json::from_str::<WithRng<vai::VAID<f32, ChaCha12Rng>>>(&json).is_err());
+
+    let mut ai = vai::VAI::<2, 1, 3, 1, f64, ChaCha12Rng>::new_deterministic(1);
+    ai.rng.gen::<u64>();
+    let json = serde_json::to_string(&WithRng(ai.clone())).unwrap();
+    let WithRng(mut read_ai): WithRng<vai::VAI<2, 1, 3, 1, f64, ChaCha12Rng>> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(read_ai.to_string(), ai.to_string());
+    assert_eq!(read_ai.rng.gen::<u64>(), ai.rng.gen::<u64>());
+}
@@ -67,3 +67,99 @@ fn non_linear_test() {
     println!("Best AI: {}", best_ai);
     assert!(best_score < initial_score * 0.1);
 }
+
+#[test]
+fn bias_test() {
+    // Without a constant input, the offset must be learned by the biases
+    let score = |ai: &vai::VAI<1, 1, 4, 0>| {
+        let mut score = 0.0;
+        for i in 0..=10 {
+            let x = 0.1 * i as f32;
+            let target_y = -2.0 * x + 0.75;
+            let ai_y = ai.process(&na::SMatrix::<f32, 1, 1>::new(x))[0];
+            score += (target_y - ai_y) * (target_y - ai_y);
+        }
+        return score;
+    };
+    let mut best_ai = vai::VAI::<1, 1, 4, 0>::new_deterministic(0);
+    let mut best_score = score(&best_ai);
+    let initial_score = best_score;
+    for _ in 0..1000 {
+        let test_ai = best_ai.create_variant(1.0);
+        let test_score = score(&test_ai);
+        if test_score < best_score {
+            best_ai = test_ai;
+            best_score = test_score;
+        }
+    }
+    println!("Best Score: {}", best_score);
+    println!("Best AI: {}", best_ai);
+    assert!(best_score < initial_score * 0.1);
+}
+
+#[test]
+fn try_process_test() {
+    let ai = vai::VAI::<2, 1, 4, 1>::new_deterministic(0).create_variant(10.0);
+    let inputs = [0.5, 0.25];
+    assert_eq!(ai.try_process_slice(&inputs, true).unwrap(), ai.process_slice(&inputs));
+    assert!(matches!(
+        ai.try_process_slice(&[0.5], false),
+        Err(vai::VaiError::InputLength { expected: 2, found: 1 })
+    ));
+    assert!(ai.try_process_slice(&[0.5, 0.25, 1.0], false).is_err());
+    let inputs = na::SMatrix::<f32, 2, 1>::new(f32::NEG_INFINITY, 0.);
+    assert!(ai.try_process(&inputs, false).is_ok());
+    assert!(matches!(
+        ai.try_process(&inputs, true),
+        Err(vai::VaiError::NonFiniteInput { index: 0 })
+    ));
+}
+
+#[test]
+fn vaid_conversion_test() {
+    let mut ai = vai::VAI::<3, 2, 4, 2>::new_deterministic(0);
+    for _ in 0..5 {
+        ai = ai.create_variant(10.0);
+    }
+    ai.hidden_activations[1] = vai::Activation::Tanh;
+    let vaid = vai::VAID::from(&ai);
+    assert_eq!(vaid.activations.len(), 4);
+    let back = vai::VAI::<3, 2, 4, 2>::try_from(&vaid).unwrap();
+    for i in 0..=10 {
+        let inputs = [0.1 * i as f32, 1.0 - 0.2 * i as f32, 0.5];
+        let expected = ai.process_slice(&inputs);
+        assert_eq!(vaid.process_slice(&inputs), expected);
+        assert_eq!(back.process_slice(&inputs), expected);
+    }
+    assert_eq!(back.to_string(), ai.to_string());
+    assert_eq!(back.hidden_activations, ai.hidden_activations);
+
+    assert!(vai::VAI::<3, 2, 4, 1>::try_from(&vaid).is_err());
+    assert!(vai::VAI::<3, 2, 5, 2>::try_from(&vaid).is_err());
+    let mut broken = vaid.clone();
+    broken.biases.pop();
+    assert!(vai::VAI::<3, 2, 4, 2>::try_from(&broken).is_err());
+}
+
+#[test]
+fn batch_test() {
+    let mut ai = vai::VAI::<3, 2, 5, 2>::new_deterministic(0);
+    for _ in 0..5 {
+        ai = ai.create_variant(10.0);
+    }
+    ai.output_activation = vai::Activation::Tanh;
+    let samples: Vec<[f32; 3]> = (0..20).map(|i| [i as f32 * 0.1, 1.0, -(i as f32)]).collect();
+    let outputs = ai.process_batch_slice(&samples);
+    assert_eq!(outputs.len(), samples.len());
+    let batch = na::DMatrix::from_fn(3, samples.len(), |r, c| samples[c][r]);
+    let batch_outputs = ai.process_batch(&batch);
+    assert_eq!(batch_outputs.shape(), (2, samples.len()));
+    for (i, sample) in samples.iter().enumerate() {
+        let expected = ai.process_slice(sample);
+        for j in 0..2 {
+            assert!((outputs[i][j] - expected[j]).abs() < 1e-5);
+            assert!((batch_outputs[(j, i)] - expected[j]).abs() < 1e-5);
+        }
+    }
+    assert!(ai.process_batch_slice(&[]).is_empty());
+}
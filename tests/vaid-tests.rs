@@ -1,5 +1,6 @@
 #![allow(clippy::needless_return)]
 
+use nalgebra as na;
 use std::assert;
 
 fn linear_score(ai: &vai::VAID) -> f32 {
@@ -65,4 +66,126 @@ fn non_linear_test() {
     println!("Best Score: {}", best_score);
     println!("Best AI: {}", best_ai);
     assert!(best_score < initial_score * 0.1);
-}
\ No newline at end of file
+}
+#[test]
+fn bias_test() {
+    // Without a constant input, the offset must be learned by the biases
+    let score = |ai: &vai::VAID| {
+        let mut score = 0.0;
+        for i in 0..=10 {
+            let x = 0.1 * i as f32;
+            let target_y = -2.0 * x + 0.75;
+            let ai_y = ai.process_slice(&[x])[0];
+            score += (target_y - ai_y) * (target_y - ai_y);
+        }
+        return score;
+    };
+    let mut best_ai = vai::VAID::new_deterministic(0, &[1, 4, 1]);
+    let mut best_score = score(&best_ai);
+    let initial_score = best_score;
+    for _ in 0..1000 {
+        let test_ai = best_ai.create_variant(1.0);
+        let test_score = score(&test_ai);
+        if test_score < best_score {
+            best_ai = test_ai;
+            best_score = test_score;
+        }
+    }
+    println!("Best Score: {}", best_score);
+    println!("Best AI: {}", best_ai);
+    assert!(best_score < initial_score * 0.1);
+}
+
+#[test]
+fn try_process_test() {
+    let ai = vai::VAID::new_deterministic(0, &[3, 4, 2]).create_variant(10.0);
+    let inputs = [0.1, 0.2, 0.3];
+    assert_eq!(ai.try_process_slice(&inputs, true).unwrap(), ai.process_slice(&inputs));
+    assert!(matches!(
+        ai.try_process_slice(&[0.1, 0.2], false),
+        Err(vai::VaiError::InputLength { expected: 3, found: 2 })
+    ));
+    assert!(ai.try_process_slice(&[0.1, f32::NAN, 0.3], false).is_ok());
+    assert!(matches!(
+        ai.try_process_slice(&[0.1, f32::NAN, f32::INFINITY], true),
+        Err(vai::VaiError::NonFiniteInput { index: 1 })
+    ));
+    let batch = na::DMatrix::<f32>::zeros(2, 5);
+    assert!(ai.try_process(&batch, false).is_err());
+
+    let mut broken = ai.clone();
+    broken.connections[1] = na::DMatrix::zeros(2, 5);
+    assert!(broken.try_process_slice(&inputs, false).is_err());
+}
+
+fn max_difference(a: &vai::VAID, b: &vai::VAID) -> f32 {
+    let inputs = na::DMatrix::<f32>::from_fn(3, 20, |r, c| (r as f32 + 1.) * (c as f32 - 10.) * 0.1);
+    return (a.process(&inputs) - b.process(&inputs)).abs().max();
+}
+
+#[test]
+fn structural_mutation_test() {
+    let mut ai = vai::VAID::new_deterministic(0, &[3, 4, 4, 2]);
+    for _ in 0..5 {
+        ai = ai.create_variant(10.0);
+    }
+
+    let mut grown = ai.clone();
+    grown.add_neuron(1);
+    assert_eq!(grown.connections[1].nrows(), 5);
+    assert_eq!(grown.connections[2].ncols(), 5);
+    assert_eq!(max_difference(&ai, &grown), 0.);
+
+    let mut deeper = ai.clone();
+    deeper.insert_layer(0);
+    deeper.insert_layer(2);
+    assert_eq!(deeper.connections.len(), 5);
+    assert!(max_difference(&ai, &deeper) < 1e-5);
+
+    // Removing what was just added restores the function
+    let mut shallower = deeper.clone();
+    shallower.remove_layer(2);
+    shallower.remove_layer(0);
+    assert_eq!(shallower.connections.len(), 3);
+    assert!(max_difference(&ai, &shallower) < 1e-5);
+
+    let mut shrunk = ai.clone();
+    shrunk.connections[0].row_mut(2).fill(0.);
+    shrunk.biases[0][2] = 0.5;
+    let before = shrunk.clone();
+    shrunk.remove_neuron(0, 2);
+    assert_eq!(shrunk.connections[0].nrows(), 3);
+    assert!(max_difference(&before, &shrunk) < 1e-5);
+
+    for _ in 0..50 {
+        ai = ai.create_structural_variant().create_variant(1.0);
+        assert!(ai.try_process_slice(&[0.1, 0.2, 0.3], false).is_ok());
+    }
+
+    // A VAID without layers has nothing to vary
+    let mut empty = vai::VAID::new_deterministic(0, &[3]);
+    assert!(empty.create_layer_variant(1.0) == empty);
+    assert!(empty.create_structural_variant() == empty);
+}
+
+#[test]
+fn batch_test() {
+    let mut ai = vai::VAID::new_deterministic(0, &[2, 6, 3, 2]);
+    for _ in 0..5 {
+        ai = ai.create_variant(10.0);
+    }
+    let samples: Vec<Vec<f32>> = (0..20).map(|i| vec![i as f32 * 0.1, -1.0]).collect();
+    let slices: Vec<&[f32]> = samples.iter().map(|x| x.as_slice()).collect();
+    let outputs = ai.process_batch_slice(&slices);
+    let batch = na::DMatrix::from_fn(2, samples.len(), |r, c| samples[c][r]);
+    let batch_outputs = ai.process_batch(&batch);
+    assert_eq!(batch_outputs.shape(), (2, samples.len()));
+    for (i, sample) in samples.iter().enumerate() {
+        let expected = ai.process_slice(sample);
+        assert_eq!(outputs[i].len(), 2);
+        for j in 0..2 {
+            assert!((outputs[i][j] - expected[j]).abs() < 1e-5);
+            assert!((batch_outputs[(j, i)] - expected[j]).abs() < 1e-5);
+        }
+    }
+}
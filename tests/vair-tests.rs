@@ -0,0 +1,105 @@
+#![allow(clippy::needless_return)]
+
+use vai::vair::Cell;
+use vai::{VaiError, VAIR};
+
+/// How well the network repeats the input it was given one step earlier
+fn echo_score(ai: &mut VAIR) -> f32 {
+    ai.reset_state();
+    let inputs = [0., 1., 1., 0., 1., 0., 0., 1., 0., 1.];
+    let mut previous = 0.;
+    let mut score = 0.0;
+    for x in inputs {
+        let ai_y = ai.step(&[x])[0];
+        score += (previous - ai_y) * (previous - ai_y);
+        previous = x;
+    }
+    return score;
+}
+
+#[test]
+fn state_test() {
+    let mut ai = VAIR::new_deterministic(0, 1, 1, 1, Cell::Elman);
+    ai.hidden_activation = vai::Activation::Identity;
+    // The state accumulates the inputs, and the output reads the state
+    ai.hidden_connections[(0, 0)] = 1.;
+    ai.hidden_connections[(0, 1)] = 1.;
+    ai.output_connections[(0, 0)] = 1.;
+    assert_eq!(ai.step(&[1.]), vec![1.]);
+    assert_eq!(ai.step(&[2.]), vec![3.]);
+    assert_eq!(ai.process_sequence(&[vec![1.], vec![0.]]), vec![vec![4.], vec![4.]]);
+    ai.reset_state();
+    assert_eq!(ai.step(&[2.]), vec![2.]);
+
+    for cell in [Cell::GRU, Cell::LSTM] {
+        let mut ai = VAIR::new_deterministic(0, 3, 4, 2, cell).create_variant(100.);
+        assert_eq!(ai.hidden_connections.shape(), (cell.gates() * 4, 7));
+        let first = ai.step(&[1., 0., -1.]);
+        assert_eq!(first.len(), 2);
+        let second = ai.step(&[1., 0., -1.]);
+        assert_ne!(first, second);
+        ai.reset_state();
+        assert_eq!(ai.step(&[1., 0., -1.]), first);
+    }
+}
+
+#[test]
+fn echo_test() {
+    for cell in [Cell::Elman, Cell::GRU, Cell::LSTM] {
+        let mut best_ai = VAIR::new_deterministic(0, 1, 3, 1, cell);
+        let mut best_score = echo_score(&mut best_ai);
+        let initial_score = best_score;
+        for _ in 0..2000 {
+            let mut test_ai = best_ai.create_variant(1.0);
+            let test_score = echo_score(&mut test_ai);
+            if test_score < best_score {
+                best_ai = test_ai;
+                best_score = test_score;
+            }
+        }
+        println!("{}: initial score {}, final score {}", cell, initial_score, best_score);
+        assert!(best_score < initial_score / 2.);
+    }
+}
+
+#[test]
+fn read_write_test() {
+    for cell in [Cell::Elman, Cell::GRU, Cell::LSTM] {
+        let mut ai = VAIR::new_deterministic(1, 2, 3, 2, cell).create_variant(10.);
+        ai.output_activation = vai::Activation::Sigmoid;
+        let text = ai.to_text().unwrap();
+        assert!(text.starts_with("VAI-MODEL 1\nkind VAIR\nlayers 2 3 2\nactivations tanh sigmoid\n"));
+        let mut read: VAIR = text.parse().unwrap();
+        assert_eq!(read.cell, cell);
+        assert_eq!(read.output_activation, ai.output_activation);
+        assert_eq!(read.hidden_connections.shape(), ai.hidden_connections.shape());
+
+        let mut buffer = Vec::new();
+        ai.write_binary(&mut buffer, true).unwrap();
+        let mut binary = VAIR::<f32>::read_binary(&mut buffer.as_slice()).unwrap();
+        assert_eq!(binary.hidden_connections, ai.hidden_connections);
+        assert_eq!(binary.output_bias, ai.output_bias);
+        for _ in 0..3 {
+            assert_eq!(binary.step(&[0.5, -1.]), ai.step(&[0.5, -1.]));
+            assert_eq!(read.step(&[0.5, -1.]).len(), 2);
+        }
+    }
+
+    let text = vai::VAID::new_deterministic(0, &[1, 1]).to_text().unwrap();
+    assert!(matches!(text.parse::<VAIR>(), Err(VaiError::WrongKind { .. })));
+    let text = "VAI-MODEL 1\nkind VAIR\nlayers 1 1 1\nweights\ncell rnn\n";
+    assert!(matches!(text.parse::<VAIR>(), Err(VaiError::Parse { line: 5, column: 6, .. })));
+}
+
+#[test]
+fn crossover_test() {
+    let mut a = VAIR::new_deterministic(0, 2, 3, 1, Cell::GRU).create_variant(10.);
+    let b = VAIR::new_deterministic(1, 2, 3, 1, Cell::GRU).create_variant(10.);
+    let child = a.crossover(&b);
+    for (i, x) in child.hidden_connections.iter().enumerate() {
+        assert!(*x == a.hidden_connections[i] || *x == b.hidden_connections[i]);
+    }
+    let c = VAIR::new_deterministic(2, 2, 3, 1, Cell::LSTM).create_variant(10.);
+    let child = a.crossover_with(&c, vai::Crossover::LayerSwap);
+    assert_eq!(child.hidden_connections, a.hidden_connections);
+}
@@ -0,0 +1,82 @@
+#![allow(clippy::needless_return)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use vai::vaid::Workspace;
+
+/// Counts the allocations made by each thread, so tests running in
+/// parallel do not see each other's allocations
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|x| x.set(x.get() + 1));
+        return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    return ALLOCATIONS.with(|x| x.get());
+}
+
+#[test]
+fn vaid_workspace_test() {
+    let mut ai = vai::VAID::new_deterministic(0, &[3, 8, 8, 2]);
+    for _ in 0..5 {
+        ai = ai.create_variant(10.0);
+    }
+    ai.activations[1] = vai::Activation::Tanh;
+    let mut workspace = Workspace::new();
+    let inputs = [0.5, -1.0, 2.0];
+    assert_eq!(ai.process_with(&inputs, &mut workspace).len(), 2);
+
+    let before = allocations();
+    let mut total = 0.;
+    for i in 0..100 {
+        let inputs = [i as f32 * 0.01, -1.0, 2.0];
+        total += ai.process_with(&inputs, &mut workspace)[0];
+    }
+    assert_eq!(allocations(), before);
+    assert!(total.is_finite());
+
+    for i in 0..10 {
+        let inputs = [i as f32, -1.0, 0.25];
+        let expected = ai.process_slice(&inputs);
+        let outputs = ai.process_with(&inputs, &mut workspace);
+        for (x, y) in outputs.iter().zip(&expected) {
+            assert!((x - y).abs() < 1e-5);
+        }
+    }
+
+    // A workspace adapts to a network of a different shape
+    let other = vai::VAID::new_deterministic(0, &[1, 4, 3]).create_variant(10.0);
+    assert_eq!(other.process_with(&[1.0], &mut workspace), other.process_slice(&[1.0]).as_slice());
+    let mut workspace = Workspace::for_network(&other);
+    let before = allocations();
+    other.process_with(&[1.0], &mut workspace);
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn vai_process_into_test() {
+    let ai = vai::VAI::<3, 2, 8, 2>::new_deterministic(0).create_variant(10.0);
+    let mut outputs = [0.; 2];
+    let before = allocations();
+    for i in 0..100 {
+        ai.process_into(&[i as f32, 1.0, -1.0], &mut outputs);
+    }
+    assert_eq!(allocations(), before);
+    assert_eq!(outputs.to_vec(), ai.process_slice(&[99.0, 1.0, -1.0]));
+}